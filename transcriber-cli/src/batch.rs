@@ -0,0 +1,370 @@
+//! Batch mode: expanding many inputs and naming their output files.
+
+use std::path::{Path, PathBuf};
+
+/// File extensions picked up when walking a directory.
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "aac", "aif", "aiff", "amr", "avi", "flac", "m4a", "m4v", "mka", "mkv", "mov", "mp3", "mp4",
+    "mpeg", "mpg", "oga", "ogg", "opus", "wav", "weba", "webm", "wma", "wmv",
+];
+
+/// Placeholders accepted by `--output-template`.
+const TEMPLATE_KEYS: &[&str] = &["stem", "name", "parent", "model", "ext"];
+
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// Whether an input names more than one file (a directory or a glob).
+pub fn is_collection(input: &str) -> bool {
+    !is_url(input) && (is_glob(input) || Path::new(input).is_dir())
+}
+
+/// Expand directories (recursively, media files only) and glob patterns.
+///
+/// URLs and plain paths are passed through unchanged, so a missing file is
+/// reported when it is transcribed rather than here. Duplicates are dropped,
+/// keeping the first occurrence.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::new();
+    for input in inputs {
+        if is_url(input) {
+            out.push(input.clone());
+        } else if Path::new(input).is_dir() {
+            let mut files = Vec::new();
+            walk_dir(Path::new(input), &mut files)
+                .map_err(|e| format!("cannot read directory {input}: {e}"))?;
+            files.sort();
+            out.extend(files.into_iter().map(|p| p.to_string_lossy().into_owned()));
+        } else if is_glob(input) {
+            let paths =
+                glob::glob(input).map_err(|e| format!("invalid pattern {input}: {e}"))?;
+            let mut files: Vec<PathBuf> = paths
+                .filter_map(|p| p.ok())
+                .filter(|p| p.is_file())
+                .collect();
+            files.sort();
+            out.extend(files.into_iter().map(|p| p.to_string_lossy().into_owned()));
+        } else {
+            out.push(input.clone());
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    out.retain(|i| seen.insert(i.clone()));
+    Ok(out)
+}
+
+/// Collect media files below `dir`, skipping hidden entries. Symlinked
+/// directories are not followed.
+fn walk_dir(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk_dir(&path, out)?;
+        } else if is_media_file(&path) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_media_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| MEDIA_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Whether the command line runs as a batch: several inputs (`expanded` after
+/// expanding `inputs`), an output directory, a directory or glob input, or
+/// several formats without `-o` (they can't share stdout, so they are written
+/// next to the input like a batch of one).
+pub fn is_batch(
+    inputs: &[String],
+    expanded: usize,
+    formats: usize,
+    output: Option<&Path>,
+    output_dir: Option<&Path>,
+) -> bool {
+    expanded > 1
+        || output_dir.is_some()
+        || inputs.iter().any(|i| is_collection(i))
+        || (formats > 1 && output.is_none())
+}
+
+/// `items` without repeats, in first-seen order.
+pub fn unique<T: PartialEq + Copy>(items: &[T]) -> Vec<T> {
+    let mut out = Vec::with_capacity(items.len());
+    for item in items {
+        if !out.contains(item) {
+            out.push(*item);
+        }
+    }
+    out
+}
+
+/// File for one of several formats written with `-o base`: `base` with the
+/// format's extension appended, so `talk.v2` gives `talk.v2.srt`. An output
+/// extension (one of `known`) already on `base` is replaced rather than
+/// doubled, so `talk.srt` gives `talk.vtt` too.
+pub fn format_path(base: &Path, ext: &str, known: &[&str]) -> PathBuf {
+    let has_known = base
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| known.contains(&e));
+    if has_known {
+        return base.with_extension(ext);
+    }
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+/// Check that a template only uses known placeholders.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            return Err(format!("unclosed '{{' in output template \"{template}\""));
+        };
+        let key = &rest[open + 1..open + close];
+        if !TEMPLATE_KEYS.contains(&key) {
+            return Err(format!(
+                "unknown placeholder {{{key}}} in output template (use {})",
+                TEMPLATE_KEYS
+                    .iter()
+                    .map(|k| format!("{{{k}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        rest = &rest[open + close + 1..];
+    }
+    Ok(())
+}
+
+/// Where the transcript of `input` goes.
+///
+/// Without `output_dir`, files are written next to their input (URLs go to
+/// the current directory). The template is expanded with:
+/// `{stem}` file name without extension, `{name}` full file name,
+/// `{parent}` name of the containing directory, `{model}` and `{ext}`.
+pub fn output_path(
+    input: &str,
+    output_dir: Option<&Path>,
+    template: &str,
+    model: &str,
+    ext: &str,
+) -> PathBuf {
+    let (dir, name, parent) = if is_url(input) {
+        (PathBuf::from("."), url_file_name(input), String::new())
+    } else {
+        let path = Path::new(input);
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let parent = dir
+            .file_name()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| input.to_string());
+        (dir, name, parent)
+    };
+    let stem = Path::new(&name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.clone());
+
+    let file = template
+        .replace("{stem}", &stem)
+        .replace("{name}", &name)
+        .replace("{parent}", &parent)
+        .replace("{model}", model)
+        .replace("{ext}", ext);
+
+    output_dir.map_or(dir, Path::to_path_buf).join(file)
+}
+
+/// A file-system friendly name for a URL: its last path segment (or host),
+/// plus the query string, with anything unusual replaced by `_`.
+fn url_file_name(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let segment = path.trim_end_matches('/').rsplit('/').next().unwrap_or(path);
+    let raw = if query.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{segment}_{query}")
+    };
+    raw.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .collect()
+}
+
+/// Result of one batch entry, with the output files involved.
+pub enum Outcome {
+    Done(Vec<PathBuf>),
+    Skipped(Vec<PathBuf>),
+    Failed(String),
+}
+
+/// Print a per-input summary to stderr. Returns the number of failures.
+pub fn print_summary(results: &[(String, Outcome)]) -> usize {
+    let (mut done, mut skipped, mut failed) = (0, 0, 0);
+    eprintln!("\nBatch summary:");
+    for (input, outcome) in results {
+        match outcome {
+            Outcome::Done(paths) => {
+                done += 1;
+                eprintln!("  ok       {input} -> {}", join_paths(paths));
+            }
+            Outcome::Skipped(paths) => {
+                skipped += 1;
+                eprintln!("  skipped  {input} ({} already written)", join_paths(paths));
+            }
+            Outcome::Failed(e) => {
+                failed += 1;
+                eprintln!("  FAILED   {input}: {e}");
+            }
+        }
+    }
+    eprintln!("{done} transcribed, {skipped} skipped, {failed} failed");
+    failed
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("transcriber_cli_batch_{name}"));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        std::fs::create_dir_all(root.join(".hidden")).unwrap();
+        for file in ["b.mp3", "a.WAV", "notes.txt", "sub/c.flac", "sub/deeper/d.webm", ".hidden/e.mp3"] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_expand_directory_recursive() {
+        let root = temp_tree("dir");
+        let files = expand_inputs(&[root.to_string_lossy().into_owned()]).unwrap();
+        let rel: Vec<String> = files
+            .iter()
+            .map(|f| Path::new(f).strip_prefix(&root).unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(rel, vec!["a.WAV", "b.mp3", "sub/c.flac", "sub/deeper/d.webm"]);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_expand_glob_and_dedup() {
+        let root = temp_tree("glob");
+        let pattern = root.join("*.mp3").to_string_lossy().into_owned();
+        let explicit = root.join("b.mp3").to_string_lossy().into_owned();
+        let files = expand_inputs(&[explicit.clone(), pattern.clone()]).unwrap();
+        assert_eq!(files, vec![explicit]);
+        assert!(is_collection(&pattern));
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_expand_passes_through_urls_and_missing_files() {
+        let inputs = vec!["https://example.com/a?b=*".to_string(), "missing.mp3".to_string()];
+        assert_eq!(expand_inputs(&inputs).unwrap(), inputs);
+        assert!(!is_collection("https://example.com/*"));
+    }
+
+    #[test]
+    fn test_output_path_next_to_input() {
+        let path = output_path("talks/day1/intro.mp3", None, "{stem}.{ext}", "small", "srt");
+        assert_eq!(path, PathBuf::from("talks/day1/intro.srt"));
+    }
+
+    #[test]
+    fn test_output_path_template() {
+        let path = output_path(
+            "talks/day1/intro.mp3",
+            Some(Path::new("out")),
+            "{parent}-{name}.{model}.{ext}",
+            "small",
+            "json",
+        );
+        assert_eq!(path, PathBuf::from("out/day1-intro.mp3.small.json"));
+    }
+
+    #[test]
+    fn test_output_path_url() {
+        let path = output_path(
+            "https://www.youtube.com/watch?v=abc&t=1",
+            Some(Path::new("out")),
+            "{stem}.{ext}",
+            "small",
+            "txt",
+        );
+        assert_eq!(path, PathBuf::from("out/watch_v_abc_t_1.txt"));
+    }
+
+    #[test]
+    fn test_format_path_appends_extension() {
+        let known = ["txt", "srt", "vtt", "json"];
+        let path = |base: &str, ext| format_path(Path::new(base), ext, &known);
+        assert_eq!(path("talk", "srt"), PathBuf::from("talk.srt"));
+        assert_eq!(path("out/talk.v2", "srt"), PathBuf::from("out/talk.v2.srt"));
+        assert_eq!(path("out/talk.v2", "json"), PathBuf::from("out/talk.v2.json"));
+        assert_eq!(path("talk.srt", "vtt"), PathBuf::from("talk.vtt"));
+        assert_eq!(path("talk.srt", "srt"), PathBuf::from("talk.srt"));
+    }
+
+    #[test]
+    fn test_unique_keeps_first_order() {
+        assert_eq!(unique(&["srt", "json", "srt", "vtt", "json"]), vec!["srt", "json", "vtt"]);
+        assert!(unique::<u8>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_is_batch() {
+        let one = vec!["talk.mp3".to_string()];
+        let out = Some(Path::new("talk"));
+        // One input, one format: stdout or -o
+        assert!(!is_batch(&one, 1, 1, None, None));
+        assert!(!is_batch(&one, 1, 1, out, None));
+        // Several formats need -o to stay a single run
+        assert!(is_batch(&one, 1, 3, None, None));
+        assert!(!is_batch(&one, 1, 3, out, None));
+        // Several inputs, an output directory or a glob are always batches
+        let two = vec!["a.mp3".to_string(), "b.mp3".to_string()];
+        assert!(is_batch(&two, 2, 1, out, None));
+        assert!(is_batch(&one, 1, 1, None, Some(Path::new("subs"))));
+        assert!(is_batch(&["*.mp3".to_string()], 1, 1, None, None));
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("{stem}.{ext}").is_ok());
+        assert!(validate_template("fixed.txt").is_ok());
+        assert!(validate_template("{stem}.{lang}").unwrap_err().contains("{lang}"));
+        assert!(validate_template("{stem").is_err());
+    }
+}
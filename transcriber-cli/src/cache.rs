@@ -0,0 +1,212 @@
+//! `cache` subcommand: inspect and clean up the model cache.
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use clap::Subcommand;
+use transcriber::cache::{self, CacheEntry, ChecksumStatus, ModelKind};
+use transcriber::TranscribeOptions;
+
+use crate::format_bytes;
+
+#[derive(clap::Args)]
+pub struct CacheArgs {
+    /// Model cache directory.
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached models with size, last use and checksum status.
+    List,
+    /// Hash cached models and compare them with their known checksum.
+    Verify {
+        /// Models to check (names like "small" or file names); all if omitted.
+        models: Vec<String>,
+    },
+    /// Delete cached models.
+    Rm {
+        /// Models to delete (names like "small" or file names).
+        #[arg(required = true)]
+        models: Vec<String>,
+    },
+    /// Delete least recently used models until the cache fits in a budget.
+    Prune {
+        /// Size budget, e.g. "5G", "800MB" or a number of bytes.
+        #[arg(long, value_parser = parse_size)]
+        max_size: u64,
+    },
+    /// Delete partial downloads and download locks left behind by interrupted runs.
+    Clean,
+}
+
+pub fn cmd_cache(args: CacheArgs) {
+    let cache_dir = args
+        .cache_dir
+        .unwrap_or_else(|| TranscribeOptions::default().resolve_cache_dir());
+
+    let result = match args.action {
+        CacheAction::List => list(&cache_dir),
+        CacheAction::Verify { models } => verify(&cache_dir, &models),
+        CacheAction::Rm { models } => remove(&cache_dir, &models),
+        CacheAction::Prune { max_size } => prune(&cache_dir, max_size),
+        CacheAction::Clean => clean(&cache_dir),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn list(cache_dir: &std::path::Path) -> transcriber::Result<()> {
+    let entries = cache::list(cache_dir)?;
+    if entries.is_empty() {
+        println!("No cached models in {}", cache_dir.display());
+        return Ok(());
+    }
+
+    println!("{:<34} {:<8} {:>8}  {:<12} CHECKSUM", "MODEL", "KIND", "SIZE", "LAST USED");
+    println!("{:<34} {:<8} {:>8}  {:<12} --------", "-----", "----", "----", "---------");
+    for entry in &entries {
+        let kind = match entry.kind {
+            ModelKind::Whisper => "whisper",
+            ModelKind::Speaker => "speaker",
+        };
+        println!(
+            "{:<34} {kind:<8} {:>8}  {:<12} {}",
+            entry.display_name(),
+            format_bytes(entry.size),
+            format_age(entry.last_used),
+            entry.checksum.as_str()
+        );
+    }
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    println!("\n{} in {}", format_bytes(total), cache_dir.display());
+    Ok(())
+}
+
+/// Look up the named models, or all of them when `names` is empty.
+fn select(cache_dir: &std::path::Path, names: &[String]) -> transcriber::Result<Vec<CacheEntry>> {
+    if names.is_empty() {
+        return cache::list(cache_dir);
+    }
+    names.iter().map(|name| cache::find(cache_dir, name)).collect()
+}
+
+fn verify(cache_dir: &std::path::Path, names: &[String]) -> transcriber::Result<()> {
+    let mut failed = 0;
+    for entry in select(cache_dir, names)? {
+        eprint!("{} ... ", entry.display_name());
+        let status = cache::verify(&entry)?;
+        eprintln!("{}", status.as_str());
+        if status == ChecksumStatus::Mismatch {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        eprintln!("{failed} model(s) failed verification — remove them with `cache rm` to download again");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn remove(cache_dir: &std::path::Path, names: &[String]) -> transcriber::Result<()> {
+    for entry in select(cache_dir, names)? {
+        let freed = cache::remove(&entry)?;
+        println!("Removed {} ({})", entry.display_name(), format_bytes(freed));
+    }
+    Ok(())
+}
+
+fn prune(cache_dir: &std::path::Path, max_size: u64) -> transcriber::Result<()> {
+    let removed = cache::prune(cache_dir, max_size)?;
+    if removed.is_empty() {
+        println!("Cache already within {}", format_bytes(max_size));
+    }
+    for entry in &removed {
+        println!(
+            "Removed {} ({}, last used {})",
+            entry.display_name(),
+            format_bytes(entry.size),
+            format_age(entry.last_used)
+        );
+    }
+    Ok(())
+}
+
+fn clean(cache_dir: &std::path::Path) -> transcriber::Result<()> {
+    let removed = cache::clean_partial(cache_dir)?;
+    if removed.is_empty() {
+        println!("No partial downloads to clean up");
+    }
+    for (path, size) in &removed {
+        println!("Removed {} ({})", path.display(), format_bytes(*size));
+    }
+    Ok(())
+}
+
+/// Parse a size such as "5G", "1.5GB", "800M" or "1000000" into bytes
+/// (decimal units, as used for display).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size \"{s}\" (e.g. 5G, 800MB)"))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1e3,
+        "M" | "MB" => 1e6,
+        "G" | "GB" => 1e9,
+        "T" | "TB" => 1e12,
+        other => return Err(format!("unknown size unit \"{other}\" (use K, M, G or T)")),
+    };
+    Ok((number * multiplier) as u64)
+}
+
+/// How long ago `time` was, roughly ("3 days ago").
+fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |d| d.as_secs());
+    match secs {
+        0..60 => "just now".into(),
+        60..3_600 => format!("{} min ago", secs / 60),
+        3_600..86_400 => format!("{} h ago", secs / 3_600),
+        _ => format!("{} days ago", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("5G"), Ok(5_000_000_000));
+        assert_eq!(parse_size("1.5GB"), Ok(1_500_000_000));
+        assert_eq!(parse_size("800 mb"), Ok(800_000_000));
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert!(parse_size("5X").is_err());
+        assert!(parse_size("big").is_err());
+    }
+
+    #[test]
+    fn test_format_age() {
+        let ago = |secs| SystemTime::now() - Duration::from_secs(secs);
+        assert_eq!(format_age(ago(5)), "just now");
+        assert_eq!(format_age(ago(600)), "10 min ago");
+        assert_eq!(format_age(ago(7_300)), "2 h ago");
+        assert_eq!(format_age(ago(3 * 86_400 + 10)), "3 days ago");
+        // Clock skew: a time in the future is "now"
+        assert_eq!(format_age(SystemTime::now() + Duration::from_secs(60)), "just now");
+    }
+}
@@ -0,0 +1,135 @@
+//! `detect-language` subcommand: find the spoken language without transcribing.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+use transcriber::{Language, LanguageDetection, TranscribeOptions, Transcriber};
+
+/// Options for `transcriber-cli detect-language`.
+#[derive(clap::Args)]
+pub struct DetectArgs {
+    /// Audio/video files to examine.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Model to use (a multilingual one; small models are usually enough).
+    #[arg(short, long, default_value = "large-v3")]
+    model: String,
+
+    /// Languages to show per file.
+    #[arg(long, default_value = "5")]
+    top: usize,
+
+    /// Number of windows to examine.
+    #[arg(long, default_value = "1")]
+    windows: u32,
+
+    /// Length of each window in seconds (at most 30).
+    #[arg(long, default_value = "30")]
+    window_secs: f32,
+
+    /// Spread the windows over the whole file instead of taking them from
+    /// the start.
+    #[arg(long)]
+    spread: bool,
+
+    /// Print JSON instead of a table.
+    #[arg(long)]
+    json: bool,
+
+    /// Disable GPU acceleration.
+    #[arg(long)]
+    no_gpu: bool,
+
+    /// GPU device ID.
+    #[arg(long, default_value = "0")]
+    gpu_device: u32,
+
+    /// Number of threads (default: auto).
+    #[arg(long)]
+    threads: Option<u32>,
+
+    /// Model cache directory.
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    source: crate::ModelSource,
+}
+
+#[derive(Serialize)]
+struct Detection {
+    input: String,
+    languages: Vec<Probability>,
+}
+
+#[derive(Serialize)]
+struct Probability {
+    code: String,
+    name: String,
+    probability: f32,
+}
+
+/// Load the model once and report the likely languages of every input.
+/// Exits non-zero if any input failed.
+pub async fn cmd_detect(args: DetectArgs) {
+    let detection = LanguageDetection::new()
+        .window_secs(args.window_secs)
+        .and_then(|d| d.windows(args.windows))
+        .unwrap_or_else(|e| crate::exit_with(e))
+        .spread(args.spread);
+    let mut opts = TranscribeOptions::new()
+        .model(crate::resolve_model(&args.model))
+        .gpu(!args.no_gpu)
+        .gpu_device(args.gpu_device)
+        .language_detection(detection);
+    if let Some(n) = args.threads {
+        opts = opts.n_threads(n).unwrap_or_else(|e| crate::exit_with(e));
+    }
+    if let Some(dir) = args.cache_dir {
+        opts = opts.cache_dir(dir);
+    }
+    let opts = crate::model_source(opts, &args.source);
+
+    let engine = Transcriber::new(opts).await.unwrap_or_else(|e| crate::exit_with(e));
+
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for input in &args.inputs {
+        match engine.detect_language(input).await {
+            Ok(ranked) => results.push(Detection {
+                input: input.display().to_string(),
+                languages: ranked.iter().take(args.top).map(probability).collect(),
+            }),
+            Err(e) => {
+                failed += 1;
+                eprintln!("Error: {}: {e}", input.display());
+            }
+        }
+    }
+
+    if args.json {
+        match serde_json::to_string_pretty(&results) {
+            Ok(json) => println!("{json}"),
+            Err(e) => crate::exit_with(e),
+        }
+    } else {
+        for result in &results {
+            println!("{}", result.input);
+            for lang in &result.languages {
+                println!("  {:<4} {:<14} {:>6.1}%", lang.code, lang.name, lang.probability * 100.0);
+            }
+        }
+    }
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn probability((language, p): &(Language, f32)) -> Probability {
+    Probability {
+        code: language.code().unwrap_or_default().to_string(),
+        name: language.name().unwrap_or_default().to_string(),
+        probability: *p,
+    }
+}
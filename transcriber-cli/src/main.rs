@@ -1,7 +1,12 @@
+mod batch;
+mod cache;
+mod detect;
+mod serve;
+
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand, ValueEnum};
-use transcriber::{Language, Model, TranscribeOptions};
+use transcriber::{AudioBackend, ChannelMode, Conditioning, DecoderOptions, Language, Model, TranscribeOptions};
 
 #[derive(Parser)]
 #[command(name = "transcriber", about = "Transcribe audio/video from URL or file")]
@@ -9,18 +14,36 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Command>,
 
-    /// URL or local file path to transcribe.
-    input: Option<String>,
+    /// URLs, files, directories (searched recursively) or glob patterns to
+    /// transcribe. More than one input, a directory or a glob switches to batch
+    /// mode, which writes one output file per input.
+    inputs: Vec<String>,
 
-    /// Output format.
-    #[arg(short, long, default_value = "text")]
-    format: OutputFormat,
+    /// Output format. Repeat or comma-separate (e.g. srt,vtt,json) to write
+    /// several renderings of one transcription; each goes to its own file.
+    #[arg(short, long, value_delimiter = ',', default_value = "text")]
+    format: Vec<OutputFormat>,
 
-    /// Write output to file instead of stdout.
+    /// Write output to file instead of stdout (single input only). With several
+    /// formats, used as the base name with each format's extension appended.
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Whisper model to use.
+    /// Batch mode: write outputs into this directory instead of next to each input.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Batch mode: output file name. Placeholders: {stem}, {name}, {parent},
+    /// {model}, {ext}.
+    #[arg(long, default_value = "{stem}.{ext}")]
+    output_template: String,
+
+    /// Batch mode: transcribe inputs again even if their output already exists.
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Whisper model to use, e.g. small, large-v3-turbo-q5_0 or distil-large-v3
+    /// (see --list-models).
     #[arg(short, long, default_value = "large-v3")]
     model: String,
 
@@ -28,6 +51,20 @@ struct Cli {
     #[arg(short, long, default_value = "auto")]
     language: String,
 
+    /// Detect the language of every 30s window and transcribe each in its own
+    /// language, for recordings that switch languages (needs --language auto).
+    #[arg(long)]
+    language_per_window: bool,
+
+    /// Transcribe from this time on (e.g. "01:10:00" or "83.5"). Timestamps
+    /// stay relative to the start of the input.
+    #[arg(long)]
+    start: Option<String>,
+
+    /// Stop transcribing at this time (e.g. "01:25:00").
+    #[arg(long)]
+    end: Option<String>,
+
     /// Translate to English.
     #[arg(long)]
     translate: bool,
@@ -64,10 +101,73 @@ struct Cli {
     #[arg(long)]
     beam_size: Option<u32>,
 
+    /// Retry a window at a higher temperature when its text's entropy is above
+    /// this (default 2.4; lower catches more repetition).
+    #[arg(long)]
+    entropy_threshold: Option<f32>,
+
+    /// Retry a window when its average token log probability is below this
+    /// (default -1.0).
+    #[arg(long, allow_negative_numbers = true)]
+    logprob_threshold: Option<f32>,
+
+    /// Temperature added on each retry; 0 disables the fallback (default 0.2).
+    #[arg(long)]
+    temperature_increment: Option<f32>,
+
+    /// Drop a window as silence when its no-speech probability is above this
+    /// (default 0.6).
+    #[arg(long)]
+    no_speech_threshold: Option<f32>,
+
+    /// Keep non-speech tokens such as [Music] instead of suppressing them.
+    #[arg(long)]
+    no_suppress_nst: bool,
+
+    /// Candidates decoded per window with greedy sampling (default 5).
+    #[arg(long)]
+    best_of: Option<u32>,
+
+    /// Split segments longer than this many characters.
+    #[arg(long)]
+    max_len: Option<u32>,
+
+    /// With --max-len, only split between words.
+    #[arg(long)]
+    split_on_word: bool,
+
+    /// Tokens of preceding text the decoder sees in each window (default 0;
+    /// higher values can carry hallucinations across windows).
+    #[arg(long)]
+    max_text_ctx: Option<u32>,
+
+    /// Text to prime the decoder with, e.g. the topic or a sentence in the
+    /// expected style.
+    #[arg(long)]
+    prompt: Option<String>,
+
+    /// File of names and terms whisper should spell correctly, one per line
+    /// (blank lines and lines starting with # are ignored).
+    #[arg(long)]
+    vocabulary_file: Option<PathBuf>,
+
+    /// Split long recordings at silences into chunks of about this many
+    /// minutes and transcribe them in parallel.
+    #[arg(long)]
+    chunk_minutes: Option<f32>,
+
+    /// Chunks transcribed at once (default: CPU cores / threads; use 1 on a
+    /// GPU). Each one holds its own whisper state in memory.
+    #[arg(long, requires = "chunk_minutes")]
+    chunk_workers: Option<usize>,
+
     /// Model cache directory.
     #[arg(long)]
     cache_dir: Option<PathBuf>,
 
+    #[command(flatten)]
+    source: ModelSource,
+
     /// Enable DC offset removal.
     #[arg(long)]
     dc_offset: bool,
@@ -80,6 +180,36 @@ struct Cli {
     #[arg(long)]
     trim_silence: bool,
 
+    /// Audio decoder: ffmpeg or native (native requires the native-audio feature).
+    #[arg(long, default_value = "ffmpeg")]
+    audio_backend: String,
+
+    /// Keep decoded audio in a temporary file instead of memory, for
+    /// recordings longer than 8 hours (decodes with ffmpeg, transcribes in
+    /// chunks).
+    #[arg(long)]
+    disk_backed_audio: bool,
+
+    /// ffmpeg conditioning preset: none, speech, telephony or
+    /// music-bed-suppression.
+    #[arg(long, default_value = "speech")]
+    conditioning: String,
+
+    /// Custom ffmpeg filter chain instead of a preset, e.g.
+    /// "highpass=f=80,loudnorm" (only level, EQ and denoising filters).
+    #[arg(long, conflicts_with = "conditioning")]
+    audio_filter: Option<String>,
+
+    /// Channels to transcribe: mix, a channel number (from 0), or per-channel
+    /// to transcribe each channel separately and interleave them.
+    #[arg(long, default_value = "mix")]
+    channels: String,
+
+    /// Speaker names for the channels in per-channel mode, in channel order
+    /// (e.g. "Agent,Customer").
+    #[arg(long, value_delimiter = ',')]
+    channel_speakers: Vec<String>,
+
     /// List available models.
     #[arg(long)]
     list_models: bool,
@@ -88,6 +218,11 @@ struct Cli {
     #[arg(long)]
     download_model: Option<String>,
 
+    /// Show the header information (size, languages, weight type) of the
+    /// --model file and exit.
+    #[arg(long)]
+    model_info: bool,
+
     /// List supported languages.
     #[arg(long)]
     list_languages: bool,
@@ -113,6 +248,15 @@ struct Cli {
     /// Download the speaker embedding model.
     #[arg(long)]
     download_speaker_model: bool,
+
+    /// Label speakers by clustering their voices (SPEAKER_00, SPEAKER_01, ...).
+    /// With --speaker-id, clusters matching an enrolled profile use its name.
+    #[arg(long)]
+    diarize_speakers: bool,
+
+    /// Number of speakers for --diarize-speakers (default: estimate).
+    #[arg(long, requires = "diarize_speakers")]
+    num_speakers: Option<usize>,
 }
 
 #[derive(Subcommand)]
@@ -149,6 +293,15 @@ enum Command {
         #[command(subcommand)]
         action: SpeakersAction,
     },
+
+    /// Serve an OpenAI-compatible transcription API over HTTP.
+    Serve(serve::ServeArgs),
+
+    /// Detect the spoken language of files without transcribing them.
+    DetectLanguage(detect::DetectArgs),
+
+    /// Inspect and clean up the model cache.
+    Cache(cache::CacheArgs),
 }
 
 #[derive(Subcommand)]
@@ -170,7 +323,7 @@ enum SpeakersAction {
     },
 }
 
-#[derive(Clone, ValueEnum)]
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
 enum OutputFormat {
     Text,
     Srt,
@@ -178,6 +331,27 @@ enum OutputFormat {
     Json,
 }
 
+impl OutputFormat {
+    /// File extension used for batch and multi-format outputs.
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Srt => "srt",
+            OutputFormat::Vtt => "vtt",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    fn render(&self, transcript: &transcriber::Transcript) -> Result<String, transcriber::Error> {
+        Ok(match self {
+            OutputFormat::Text => transcript.text(),
+            OutputFormat::Srt => transcript.to_srt(),
+            OutputFormat::Vtt => transcript.to_vtt(),
+            OutputFormat::Json => transcript.to_json_pretty()?,
+        })
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
@@ -207,6 +381,9 @@ async fn main() {
                 SpeakersAction::List { profiles } => cmd_speakers_list(profiles),
                 SpeakersAction::Remove { name, profiles } => cmd_speakers_remove(name, profiles),
             },
+            Command::Serve(args) => serve::cmd_serve(args).await,
+            Command::DetectLanguage(args) => detect::cmd_detect(args).await,
+            Command::Cache(args) => cache::cmd_cache(args),
         }
         return;
     }
@@ -221,27 +398,19 @@ async fn main() {
     }
 
     if cli.list_models {
-        let models = [
-            ("tiny", "75 MB"),
-            ("tiny.en", "75 MB"),
-            ("base", "142 MB"),
-            ("base.en", "142 MB"),
-            ("small", "466 MB"),
-            ("small.en", "466 MB"),
-            ("medium", "1.5 GB"),
-            ("medium.en", "1.5 GB"),
-            ("large-v2", "2.9 GB"),
-            ("large-v3", "2.9 GB"),
-            ("large-v3-turbo", "~1.6 GB"),
-        ];
-        println!("{:<16} SIZE", "MODEL");
-        println!("{:<16} ----", "-----");
-        for (name, size) in models {
-            println!("{name:<16} {size}");
+        let opts = TranscribeOptions::default();
+        let cache_dir = cli.cache_dir.clone().unwrap_or_else(|| opts.resolve_cache_dir());
+        println!("{:<22} {:>8}  CACHED", "MODEL", "SIZE");
+        println!("{:<22} {:>8}  ------", "-----", "----");
+        for model in Model::available() {
+            let size = model
+                .size_mb()
+                .map(|mb| format_bytes(u64::from(mb) * 1_000_000))
+                .unwrap_or_default();
+            let cached = if cache_dir.join(model.filename()).exists() { "yes" } else { "" };
+            println!("{:<22} {size:>8}  {cached}", model.name());
         }
 
-        let opts = TranscribeOptions::default();
-        let cache_dir = opts.resolve_cache_dir();
         let cached = transcriber::model::list_cached_models(&cache_dir);
         if !cached.is_empty() {
             println!("\nCached models in {}:", cache_dir.display());
@@ -261,6 +430,31 @@ async fn main() {
         return;
     }
 
+    if cli.model_info {
+        let model = resolve_model(&cli.model);
+        let path = match &model {
+            Model::Custom(path) => path.clone(),
+            m => cli
+                .cache_dir
+                .clone()
+                .unwrap_or_else(|| TranscribeOptions::default().resolve_cache_dir())
+                .join(m.filename()),
+        };
+        match transcriber::model::read_model_info(&path) {
+            Ok(info) => print_model_info(&path, &info),
+            Err(transcriber::Error::ModelNotFound { .. }) => {
+                eprintln!("Model {} is not cached yet", model.name());
+                eprintln!("Download it with --download-model {}", model.name());
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     if let Some(model_name) = &cli.download_model {
         let model = match Model::parse_name(model_name) {
             Some(m) => m,
@@ -270,9 +464,10 @@ async fn main() {
                 std::process::exit(1);
             }
         };
-        let opts = TranscribeOptions::default();
+        let opts = model_source(TranscribeOptions::default(), &cli.source);
         let cache_dir = cli.cache_dir.unwrap_or_else(|| opts.resolve_cache_dir());
-        match transcriber::model::ensure_model(&model, &cache_dir).await {
+        match transcriber::model::ensure_model_with(&model, &cache_dir, &opts.model_fetch()).await
+        {
             Ok(path) => println!("Model ready: {}", path.display()),
             Err(e) => {
                 eprintln!("Error: {e}");
@@ -283,9 +478,11 @@ async fn main() {
     }
 
     if cli.download_speaker_model {
-        let opts = TranscribeOptions::default();
+        let opts = model_source(TranscribeOptions::default(), &cli.source);
         let cache_dir = cli.cache_dir.unwrap_or_else(|| opts.resolve_cache_dir());
-        match transcriber::speaker::ensure_speaker_model(&cache_dir).await {
+        match transcriber::speaker::ensure_speaker_model_with(&cache_dir, &opts.speaker_model_fetch())
+            .await
+        {
             Ok(path) => println!("Speaker model ready: {}", path.display()),
             Err(e) => {
                 eprintln!("Error: {e}");
@@ -295,31 +492,45 @@ async fn main() {
         return;
     }
 
-    let input = match cli.input {
-        Some(i) => i,
-        None => {
-            eprintln!("Error: no input specified. Provide a URL or file path.");
-            eprintln!("Usage: transcriber-cli <INPUT> [OPTIONS]");
-            eprintln!("       transcriber-cli enroll --name <NAME> --audio <AUDIO>");
-            eprintln!("       transcriber-cli speakers list");
+    if cli.inputs.is_empty() {
+        eprintln!("Error: no input specified. Provide a URL or file path.");
+        eprintln!("Usage: transcriber-cli <INPUT>... [OPTIONS]");
+        eprintln!("       transcriber-cli enroll --name <NAME> --audio <AUDIO>");
+        eprintln!("       transcriber-cli speakers list");
+        std::process::exit(1);
+    }
+    let inputs = match batch::expand_inputs(&cli.inputs) {
+        Ok(i) => i,
+        Err(e) => {
+            eprintln!("Error: {e}");
             std::process::exit(1);
         }
     };
+    let formats = batch::unique(&cli.format);
+    let batch_mode = batch::is_batch(
+        &cli.inputs,
+        inputs.len(),
+        formats.len(),
+        cli.output.as_deref(),
+        cli.output_dir.as_deref(),
+    );
+    if batch_mode {
+        if cli.output.is_some() {
+            eprintln!("Error: --output takes a single input; use --output-dir for batches");
+            std::process::exit(1);
+        }
+        if inputs.is_empty() {
+            eprintln!("Error: no audio or video files found in the given inputs");
+            std::process::exit(1);
+        }
+        if let Err(e) = batch::validate_template(&cli.output_template) {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
 
     // Build options
-    let model = match Model::parse_name(&cli.model) {
-        Some(m) => m,
-        None => {
-            let path = PathBuf::from(&cli.model);
-            if path.exists() {
-                Model::Custom(path)
-            } else {
-                eprintln!("Unknown model: {}", cli.model);
-                eprintln!("Use --list-models to see available models, or provide a path to a .ggml file");
-                std::process::exit(1);
-            }
-        }
-    };
+    let model = resolve_model(&cli.model);
 
     let language = match Language::new(&cli.language) {
         Ok(lang) => lang,
@@ -330,8 +541,41 @@ async fn main() {
         }
     };
 
+    let audio_backend = match AudioBackend::parse_name(&cli.audio_backend) {
+        Ok(b) if b.is_available() => b,
+        Ok(b) => {
+            eprintln!("Error: audio backend '{b}' is not available in this build");
+            eprintln!("Rebuild with --features native-audio to enable it");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let conditioning = match &cli.audio_filter {
+        Some(filter) => Conditioning::custom(filter.as_str()),
+        None => Conditioning::parse_name(&cli.conditioning),
+    };
+    let conditioning = match conditioning {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    let channels = match ChannelMode::parse_name(&cli.channels) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
     let mut opts = match TranscribeOptions::new()
         .model(model)
+        .language_per_window(cli.language_per_window)
         .translate(cli.translate)
         .word_timestamps(cli.word_timestamps)
         .gpu(!cli.no_gpu)
@@ -343,7 +587,11 @@ async fn main() {
             transcriber::AudioProcessing::new()
                 .dc_offset_removal(cli.dc_offset)
                 .normalize(cli.normalize)
-                .trim_silence(cli.trim_silence),
+                .trim_silence(cli.trim_silence)
+                .backend(audio_backend)
+                .disk_backed(cli.disk_backed_audio)
+                .conditioning(conditioning)
+                .channels(channels),
         ),
         Err(e) => {
             eprintln!("Error: {e}");
@@ -365,79 +613,475 @@ async fn main() {
             Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
         };
     }
-    if let Some(path) = cli.vad_model_path {
+    opts = match decoder_options(&cli) {
+        Ok(decoder) => opts.decoder(decoder),
+        Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+    };
+    if let Some(prompt) = &cli.prompt {
+        opts = match opts.initial_prompt(prompt) {
+            Ok(o) => o,
+            Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+        };
+    }
+    if let Some(path) = &cli.vocabulary_file {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error: cannot read vocabulary file {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        };
+        let terms = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'));
+        opts = match opts.vocabulary(terms) {
+            Ok(o) => o,
+            Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+        };
+    }
+    if cli.start.is_some() || cli.end.is_some() {
+        let (start, end) = match parse_range(cli.start.as_deref(), cli.end.as_deref()) {
+            Ok(range) => range,
+            Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+        };
+        opts = match opts.time_range(start, end) {
+            Ok(o) => o,
+            Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+        };
+    }
+    if !cli.channel_speakers.is_empty() {
+        opts = opts.channel_speakers(&cli.channel_speakers);
+    }
+    if let Some(minutes) = cli.chunk_minutes {
+        let mut chunking = transcriber::ChunkOptions::new().chunk_secs(minutes * 60.0);
+        if let Some(n) = cli.chunk_workers {
+            chunking = chunking.and_then(|c| c.workers(n));
+        }
+        opts = match chunking {
+            Ok(chunking) => opts.chunking(chunking),
+            Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+        };
+    }
+    if let Some(path) = cli.vad_model_path.clone() {
         opts = opts.vad_model_path(path);
     }
-    if let Some(dir) = cli.cache_dir {
+    if let Some(dir) = cli.cache_dir.clone() {
         opts = opts.cache_dir(dir);
     }
+    opts = model_source(opts, &cli.source);
 
     // Speaker identification options
     if cli.speaker_id {
         opts = opts.speaker_identification(true)
             .speaker_threshold(cli.speaker_threshold);
-        if let Some(path) = cli.speaker_profiles {
+        if let Some(path) = cli.speaker_profiles.clone() {
             opts = opts.speaker_profiles_path(path);
         }
-        if let Some(path) = cli.speaker_model {
+    }
+    if cli.diarize_speakers {
+        opts = opts.speaker_diarization(true);
+        if let Some(n) = cli.num_speakers {
+            opts = match opts.num_speakers(n) {
+                Ok(o) => o,
+                Err(e) => { eprintln!("Error: {e}"); std::process::exit(1); }
+            };
+        }
+    }
+    if cli.speaker_id || cli.diarize_speakers {
+        if let Some(path) = cli.speaker_model.clone() {
             opts = opts.speaker_model_path(path);
         }
     }
 
-    // Determine if input is a URL or file
-    let is_url = input.starts_with("http://") || input.starts_with("https://");
+    // Progress bar on stderr (hidden automatically when not a terminal) and
+    // Ctrl-C aborts the running transcription
+    let progress = progress_bar();
+    let cancel = transcriber::CancellationToken::new();
+    opts = opts
+        .on_progress({
+            let progress = progress.clone();
+            move |p| update_progress(&progress, p)
+        })
+        .cancellation_token(cancel.clone());
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+            eprintln!("\nCancelling (press Ctrl-C again to quit now)");
+        }
+        // Decoding and a whisper window can't be interrupted, so don't make
+        // the user wait for them
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
 
-    let result = if is_url {
-        transcriber::transcribe_with_options(&input, &opts).await
-    } else {
-        transcriber::transcribe_file_with_options(&input, &opts).await
+    // Load the model once for every input
+    let engine = match transcriber::Transcriber::new(opts).await {
+        Ok(e) => e,
+        Err(e) => {
+            progress.finish_and_clear();
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
     };
 
+    if batch_mode {
+        run_batch(&cli, &engine, &inputs, &formats, &progress).await;
+        return;
+    }
+
+    let input = &inputs[0];
+    let result = transcribe_input(&engine, input).await;
+    progress.finish_and_clear();
+
     let transcript = match result {
         Ok(t) => t,
+        Err(transcriber::Error::Cancelled) => {
+            eprintln!("Cancelled");
+            std::process::exit(130);
+        }
         Err(e) => {
             eprintln!("Error: {e}");
             std::process::exit(1);
         }
     };
 
+    report_transcript(&cli, &transcript);
+
+    if let [format] = formats[..] {
+        let output_text = match format.render(&transcript) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("JSON error: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        match cli.output {
+            Some(path) => {
+                if let Err(e) = std::fs::write(&path, &output_text) {
+                    eprintln!("Error writing to {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+                eprintln!("Written to {}", path.display());
+            }
+            None => print!("{output_text}"),
+        }
+        return;
+    }
+
+    // Several formats with -o: one file per format sharing the base name
+    let base = cli.output.clone().expect("multi-format output without -o runs as a batch");
+    let known: Vec<&str> = OutputFormat::value_variants().iter().map(|f| f.extension()).collect();
+    for format in &formats {
+        let path = batch::format_path(&base, format.extension(), &known);
+        if let Err(e) = write_rendering(&transcript, *format, &path) {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        eprintln!("Written to {}", path.display());
+    }
+}
+
+/// Decoder tuning from the command line; unset flags keep the library defaults.
+fn decoder_options(cli: &Cli) -> transcriber::Result<DecoderOptions> {
+    let mut decoder = DecoderOptions::new()
+        .suppress_nst(!cli.no_suppress_nst)
+        .split_on_word(cli.split_on_word);
+    if let Some(v) = cli.entropy_threshold {
+        decoder = decoder.entropy_threshold(v)?;
+    }
+    if let Some(v) = cli.logprob_threshold {
+        decoder = decoder.logprob_threshold(v)?;
+    }
+    if let Some(v) = cli.temperature_increment {
+        decoder = decoder.temperature_increment(v)?;
+    }
+    if let Some(v) = cli.no_speech_threshold {
+        decoder = decoder.no_speech_threshold(v)?;
+    }
+    if let Some(n) = cli.best_of {
+        decoder = decoder.best_of(n)?;
+    }
+    if let Some(n) = cli.max_len {
+        decoder = decoder.max_len(n)?;
+    }
+    if let Some(n) = cli.max_text_ctx {
+        decoder = decoder.n_max_text_ctx(n);
+    }
+    Ok(decoder)
+}
+
+/// A model name from --list-models, or a path to a ggml file. Exits if it is
+/// neither.
+fn resolve_model(name: &str) -> Model {
+    if let Some(model) = Model::parse_name(name) {
+        return model;
+    }
+    let path = PathBuf::from(name);
+    if !path.exists() {
+        eprintln!("Unknown model: {name}");
+        eprintln!("Use --list-models to see available models, or provide a path to a .ggml file");
+        std::process::exit(1);
+    }
+    Model::Custom(path)
+}
+
+/// Print `e` the way the other commands report errors and exit.
+fn exit_with(e: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {e}");
+    std::process::exit(1);
+}
+
+fn print_model_info(path: &std::path::Path, info: &transcriber::model::ModelInfo) {
+    println!("File:          {}", path.display());
+    println!("Size:          {}", info.size_name());
+    println!(
+        "Languages:     {}",
+        if info.is_multilingual() { "multilingual" } else { "English only" }
+    );
+    println!("Weights:       {}", info.weight_type());
+    println!("Vocabulary:    {} tokens", info.n_vocab);
+    println!("Mel bins:      {}", info.n_mels);
+    println!(
+        "Encoder:       {} layers, {} heads, width {}, context {}",
+        info.n_audio_layer, info.n_audio_head, info.n_audio_state, info.n_audio_ctx
+    );
+    println!(
+        "Decoder:       {} layers, {} heads, width {}, context {}",
+        info.n_text_layer, info.n_text_head, info.n_text_state, info.n_text_ctx
+    );
+}
+
+/// Flags that control where models come from and how they are checked,
+/// shared by the subcommands that load a model.
+#[derive(clap::Args)]
+struct ModelSource {
+    /// Expected SHA-256 of the model file (overrides the built-in digest;
+    /// needed to verify a model given as a path).
+    #[arg(long)]
+    model_sha256: Option<String>,
+
+    /// Check the model's SHA-256 before loading it, not only after download.
+    #[arg(long)]
+    verify_model: bool,
+
+    /// Download models from this base URL (e.g. an internal mirror) instead of
+    /// HuggingFace. Also set by TRANSCRIBER_MODEL_BASE_URL.
+    #[arg(long)]
+    model_base_url: Option<String>,
+
+    /// Never download models; fail if they are not cached.
+    /// Also set by TRANSCRIBER_OFFLINE=1 (or true).
+    #[arg(long)]
+    offline: bool,
+}
+
+/// Apply the [`ModelSource`] flags. Exits on invalid values.
+fn model_source(mut opts: TranscribeOptions, source: &ModelSource) -> TranscribeOptions {
+    let result = (|| {
+        if let Some(hash) = &source.model_sha256 {
+            opts = opts.model_sha256(hash)?;
+        }
+        if let Some(url) = &source.model_base_url {
+            opts = opts.model_base_url(url)?;
+        }
+        Ok::<_, transcriber::Error>(opts.verify_model(source.verify_model).offline(source.offline))
+    })();
+    match result {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn write_rendering(
+    transcript: &transcriber::Transcript,
+    format: OutputFormat,
+    path: &std::path::Path,
+) -> Result<(), String> {
+    let text = format.render(transcript).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("writing {}: {e}", path.display()))
+}
+
+async fn transcribe_input(
+    engine: &transcriber::Transcriber,
+    input: &str,
+) -> transcriber::Result<transcriber::Transcript> {
+    if batch::is_url(input) {
+        engine.transcribe_url(input).await
+    } else {
+        engine.transcribe_file(input).await
+    }
+}
+
+/// Completion line and, if enabled, the speaker summary.
+fn report_transcript(cli: &Cli, transcript: &transcriber::Transcript) {
     eprintln!(
         "Transcription complete: {:.1}s of audio, {} segments, language: {}",
         transcript.duration,
         transcript.segments.len(),
         transcript.language,
     );
+    if !transcript.languages.is_empty() {
+        let shares: Vec<String> = transcript
+            .languages
+            .iter()
+            .map(|l| format!("{} {:.0}%", l.language, l.share * 100.0))
+            .collect();
+        eprintln!("Languages: {}", shares.join(", "));
+    }
 
-    // Print speaker summary if identification was used
-    if cli.speaker_id {
-        print_speaker_summary(&transcript);
+    // Print speaker summary if identification or diarization was used
+    if cli.speaker_id || cli.diarize_speakers {
+        print_speaker_summary(transcript);
     }
+}
 
-    let output_text = match cli.format {
-        OutputFormat::Text => transcript.text(),
-        OutputFormat::Srt => transcript.to_srt(),
-        OutputFormat::Vtt => transcript.to_vtt(),
-        OutputFormat::Json => match transcript.to_json_pretty() {
-            Ok(j) => j,
-            Err(e) => {
-                eprintln!("JSON error: {e}");
+/// Transcribe every input to its own output file, then print a summary.
+/// Exits non-zero if any input failed (130 if interrupted).
+async fn run_batch(
+    cli: &Cli,
+    engine: &transcriber::Transcriber,
+    inputs: &[String],
+    formats: &[OutputFormat],
+    progress: &indicatif::ProgressBar,
+) {
+    let model = engine.options().model.name().to_string();
+    // One output per (input, format)
+    let outputs: Vec<Vec<(OutputFormat, PathBuf)>> = inputs
+        .iter()
+        .map(|i| {
+            formats
+                .iter()
+                .map(|&f| {
+                    let path = batch::output_path(
+                        i,
+                        cli.output_dir.as_deref(),
+                        &cli.output_template,
+                        &model,
+                        f.extension(),
+                    );
+                    (f, path)
+                })
+                .collect()
+        })
+        .collect();
+
+    // Two outputs must never overwrite each other
+    let mut seen = std::collections::HashMap::new();
+    for (input, paths) in inputs.iter().zip(&outputs) {
+        for (_, path) in paths {
+            if let Some(other) = seen.insert(path, input) {
+                eprintln!(
+                    "Error: {other} and {input} would both be written to {}",
+                    path.display()
+                );
+                eprintln!("Use --output-template with {{parent}}, {{name}} or {{ext}} to tell them apart");
                 std::process::exit(1);
             }
-        },
-    };
+        }
+    }
 
-    match cli.output {
-        Some(path) => {
-            if let Err(e) = std::fs::write(&path, &output_text) {
-                eprintln!("Error writing to {}: {e}", path.display());
-                std::process::exit(1);
-            }
-            eprintln!("Written to {}", path.display());
+    if let Some(dir) = &cli.output_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("Error creating {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+    }
+
+    let total = inputs.len();
+    let mut results = Vec::with_capacity(total);
+    let mut cancelled = false;
+    for (i, (input, paths)) in inputs.iter().zip(outputs).enumerate() {
+        // Only renderings that are missing (or all, with --overwrite)
+        let pending: Vec<(OutputFormat, PathBuf)> = paths
+            .iter()
+            .filter(|(_, p)| cli.overwrite || !p.exists())
+            .cloned()
+            .collect();
+        if pending.is_empty() {
+            let existing = paths.into_iter().map(|(_, p)| p).collect();
+            results.push((input.clone(), batch::Outcome::Skipped(existing)));
+            continue;
         }
-        None => print!("{output_text}"),
+
+        progress.reset();
+        eprintln!("[{}/{}] {input}", i + 1, total);
+        let outcome = match transcribe_input(engine, input).await {
+            Ok(transcript) => {
+                progress.finish_and_clear();
+                report_transcript(cli, &transcript);
+                let written: Result<Vec<PathBuf>, String> = pending
+                    .into_iter()
+                    .map(|(format, path)| write_rendering(&transcript, format, &path).map(|()| path))
+                    .collect();
+                match written {
+                    Ok(paths) => batch::Outcome::Done(paths),
+                    Err(e) => batch::Outcome::Failed(e),
+                }
+            }
+            Err(transcriber::Error::Cancelled) => {
+                progress.finish_and_clear();
+                cancelled = true;
+                results.push((input.clone(), batch::Outcome::Failed("cancelled".into())));
+                break;
+            }
+            Err(e) => {
+                progress.finish_and_clear();
+                eprintln!("Error: {e}");
+                batch::Outcome::Failed(e.to_string())
+            }
+        };
+        results.push((input.clone(), outcome));
+    }
+
+    let failed = batch::print_summary(&results);
+    if cancelled {
+        eprintln!("Cancelled");
+        std::process::exit(130);
+    }
+    if failed > 0 {
+        std::process::exit(1);
     }
 }
 
+/// Progress bar for a single transcription (position in permille).
+fn progress_bar() -> indicatif::ProgressBar {
+    let pb = indicatif::ProgressBar::with_draw_target(
+        Some(1000),
+        indicatif::ProgressDrawTarget::stderr(),
+    );
+    pb.set_style(
+        indicatif::ProgressStyle::with_template("{spinner} {msg:<24} [{bar:30}] {percent:>3}%")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    pb
+}
+
+fn update_progress(pb: &indicatif::ProgressBar, p: &transcriber::Progress) {
+    use transcriber::Stage;
+    let label = match p.stage {
+        Stage::Downloading => "Downloading".to_string(),
+        Stage::DecodingAudio => "Decoding audio".to_string(),
+        Stage::Transcribing => match p.duration_secs {
+            Some(total) => format!("Transcribing {:.0}/{:.0}s", p.position_secs, total),
+            None => "Transcribing".to_string(),
+        },
+        Stage::IdentifyingSpeakers => "Identifying speakers".to_string(),
+        Stage::Done => {
+            pb.finish_and_clear();
+            return;
+        }
+    };
+    pb.set_message(label);
+    pb.set_position((p.fraction.clamp(0.0, 1.0) * 1000.0) as u64);
+}
+
 /// Enroll a speaker from an audio file.
 async fn cmd_enroll(
     name: String,
@@ -449,6 +1093,21 @@ async fn cmd_enroll(
 ) {
     let profiles_path = profiles.unwrap_or_else(transcriber::speaker::default_profiles_path);
 
+    let range = if start.is_some() || end.is_some() {
+        let range = parse_range(start.as_deref(), end.as_deref()).and_then(|(start, end)| {
+            TranscribeOptions::new().time_range(start, end).map_err(|e| e.to_string())
+        });
+        match range {
+            Ok(o) => o.time_range,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
     // Ensure speaker model is available
     let opts = TranscribeOptions::default();
     let cache_dir = opts.resolve_cache_dir();
@@ -467,7 +1126,7 @@ async fn cmd_enroll(
     let processing = transcriber::AudioProcessing::default();
     let audio_clone = audio.clone();
     let samples = match tokio::task::spawn_blocking(move || {
-        transcriber::__test_load_audio(&audio_clone, &processing)
+        transcriber::load_audio_range(&audio_clone, &processing, range)
     })
     .await
     {
@@ -481,31 +1140,14 @@ async fn cmd_enroll(
             std::process::exit(1);
         }
     };
-
-    // Extract time range if specified
-    let samples = if start.is_some() || end.is_some() {
-        let total_duration = samples.len() as f64 / 16000.0;
-        let start_secs = start.map(|s| parse_time(&s)).unwrap_or(0.0);
-        let end_secs = end.map(|s| parse_time(&s)).unwrap_or(total_duration);
-
-        let start_sample = (start_secs * 16000.0) as usize;
-        let end_sample = (end_secs * 16000.0) as usize;
-        let start_sample = start_sample.min(samples.len());
-        let end_sample = end_sample.min(samples.len());
-
-        if end_sample <= start_sample {
-            eprintln!("Error: invalid time range ({start_secs:.1}s - {end_secs:.1}s)");
-            std::process::exit(1);
-        }
-
+    if let Some(range) = range {
+        let secs = samples.len() as f64 / 16000.0;
         eprintln!(
-            "Using time range {start_secs:.1}s - {end_secs:.1}s ({:.1}s)",
-            (end_sample - start_sample) as f64 / 16000.0
+            "Using time range {:.1}s - {:.1}s ({secs:.1}s)",
+            range.start,
+            range.start + secs
         );
-        samples[start_sample..end_sample].to_vec()
-    } else {
-        samples
-    };
+    }
 
     // Create identifier and enroll
     let result = tokio::task::spawn_blocking(move || -> Result<(), transcriber::Error> {
@@ -640,7 +1282,7 @@ fn print_speaker_summary(transcript: &transcriber::Transcript) {
 
     eprintln!("\nSpeaker summary:");
     let mut sorted: Vec<_> = stats.iter().collect();
-    sorted.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+    sorted.sort_by_key(|(_, s)| std::cmp::Reverse(s.count));
     for (name, s) in &sorted {
         let avg_conf = s.total_conf / s.count as f32;
         let mins = (s.total_duration / 60.0) as u32;
@@ -703,27 +1345,27 @@ fn format_time_suggestion(seconds: f64) -> String {
     format!("{m:02}:{s:02}")
 }
 
-/// Parse a time string like "01:23" or "83.5" into seconds.
-fn parse_time(s: &str) -> f64 {
-    if s.contains(':') {
-        let parts: Vec<&str> = s.split(':').collect();
-        match parts.len() {
-            2 => {
-                let mins: f64 = parts[0].parse().unwrap_or(0.0);
-                let secs: f64 = parts[1].parse().unwrap_or(0.0);
-                mins * 60.0 + secs
-            }
-            3 => {
-                let hours: f64 = parts[0].parse().unwrap_or(0.0);
-                let mins: f64 = parts[1].parse().unwrap_or(0.0);
-                let secs: f64 = parts[2].parse().unwrap_or(0.0);
-                hours * 3600.0 + mins * 60.0 + secs
-            }
-            _ => s.parse().unwrap_or(0.0),
+/// Parse `--start` and `--end` into seconds (start defaults to 0).
+fn parse_range(start: Option<&str>, end: Option<&str>) -> Result<(f64, Option<f64>), String> {
+    Ok((start.map_or(Ok(0.0), parse_time)?, end.map(parse_time).transpose()?))
+}
+
+/// Parse a time string like "01:23", "1:02:03" or "83.5" into seconds.
+fn parse_time(s: &str) -> Result<f64, String> {
+    let invalid = || format!("invalid time '{s}' (expected seconds, MM:SS or HH:MM:SS)");
+    let mut secs = 0.0;
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    for part in parts {
+        let value: f64 = part.parse().map_err(|_| invalid())?;
+        if !(value >= 0.0 && value.is_finite()) {
+            return Err(invalid());
         }
-    } else {
-        s.parse().unwrap_or(0.0)
+        secs = secs * 60.0 + value;
     }
+    Ok(secs)
 }
 
 fn format_bytes(bytes: u64) -> String {
@@ -0,0 +1,613 @@
+//! `serve` subcommand: a local HTTP server speaking the OpenAI audio API.
+//!
+//! Models are loaded once at startup and stay resident. Clients that already
+//! talk to `POST /v1/audio/transcriptions` can point their base URL here.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{DefaultBodyLimit, Multipart, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use transcriber::{Language, Model, TranscribeOptions, Transcriber, Transcript, Word};
+
+/// Options for `transcriber-cli serve`.
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on.
+    #[arg(long, default_value = "8000")]
+    port: u16,
+
+    /// Model to keep loaded. Repeat to serve several; the first one answers
+    /// requests naming an unknown model (such as "whisper-1").
+    #[arg(short, long = "model", default_value = "large-v3")]
+    models: Vec<String>,
+
+    /// Disable GPU acceleration.
+    #[arg(long)]
+    no_gpu: bool,
+
+    /// GPU device ID.
+    #[arg(long, default_value = "0")]
+    gpu_device: u32,
+
+    /// Number of threads per transcription (default: auto).
+    #[arg(long)]
+    threads: Option<u32>,
+
+    /// Model cache directory.
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Transcriptions allowed to run at the same time.
+    #[arg(long, default_value = "1")]
+    concurrency: usize,
+
+    /// Maximum upload size in megabytes.
+    #[arg(long, default_value = "512")]
+    max_upload_mb: usize,
+
+    #[command(flatten)]
+    source: crate::ModelSource,
+}
+
+/// Load the models and serve until Ctrl-C.
+pub async fn cmd_serve(args: ServeArgs) {
+    let mut base = match TranscribeOptions::new()
+        .gpu(!args.no_gpu)
+        .gpu_device(args.gpu_device)
+        .temperature(0.0)
+    {
+        Ok(o) => o,
+        Err(e) => crate::exit_with(e),
+    };
+    if let Some(n) = args.threads {
+        base = base.n_threads(n).unwrap_or_else(|e| crate::exit_with(e));
+    }
+    if let Some(dir) = args.cache_dir {
+        base = base.cache_dir(dir);
+    }
+    if args.source.model_sha256.is_some() && args.models.len() > 1 {
+        crate::exit_with("--model-sha256 can only check a single --model");
+    }
+    base = crate::model_source(base, &args.source);
+    if args.concurrency == 0 {
+        crate::exit_with("--concurrency must be at least 1");
+    }
+
+    let mut models = Vec::with_capacity(args.models.len());
+    for name in &args.models {
+        let Some(model) = Model::parse_name(name) else {
+            eprintln!("Unknown model: {name}");
+            eprintln!("Use --list-models to see available models");
+            std::process::exit(1);
+        };
+        eprintln!("Loading model {name}...");
+        match Transcriber::new(base.clone().model(model)).await {
+            Ok(engine) => models.push((name.clone(), engine)),
+            Err(e) => crate::exit_with(e),
+        }
+    }
+
+    let app = router(
+        AppState::new(models, args.concurrency),
+        args.max_upload_mb.saturating_mul(1024 * 1024),
+    );
+
+    let addr = format!("{}:{}", args.host, args.port);
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(l) => l,
+        Err(e) => crate::exit_with(format!("cannot listen on {addr}: {e}")),
+    };
+    eprintln!("Listening on http://{addr}");
+    eprintln!("  POST /v1/audio/transcriptions");
+    eprintln!("  GET  /health");
+
+    let shutdown = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+    if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(shutdown).await {
+        crate::exit_with(e);
+    }
+}
+
+/// Loaded models and the limit on concurrent transcriptions.
+#[derive(Clone)]
+pub struct AppState {
+    /// `(name, engine)` pairs; the first is the default.
+    models: Arc<Vec<(String, Transcriber)>>,
+    permits: Arc<Semaphore>,
+}
+
+impl AppState {
+    pub fn new(models: Vec<(String, Transcriber)>, concurrency: usize) -> Self {
+        Self {
+            models: Arc::new(models),
+            permits: Arc::new(Semaphore::new(concurrency)),
+        }
+    }
+
+    /// The engine serving `requested`, falling back to the default model.
+    fn engine(&self, requested: Option<&str>) -> Option<&Transcriber> {
+        requested
+            .and_then(|name| self.models.iter().find(|(n, _)| n == name))
+            .or_else(|| self.models.first())
+            .map(|(_, engine)| engine)
+    }
+}
+
+pub fn router(state: AppState, max_upload_bytes: usize) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/v1/audio/transcriptions", post(transcriptions))
+        .layer(DefaultBodyLimit::max(max_upload_bytes))
+        .with_state(state)
+}
+
+async fn health(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let models: Vec<&str> = state.models.iter().map(|(n, _)| n.as_str()).collect();
+    Json(serde_json::json!({ "status": "ok", "models": models }))
+}
+
+/// `response_format` values accepted by the OpenAI API.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl ResponseFormat {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "json" => Some(Self::Json),
+            "text" => Some(Self::Text),
+            "srt" => Some(Self::Srt),
+            "vtt" => Some(Self::Vtt),
+            "verbose_json" => Some(Self::VerboseJson),
+            _ => None,
+        }
+    }
+}
+
+/// Fields of a transcription request.
+#[derive(Debug)]
+struct TranscriptionRequest {
+    file: Vec<u8>,
+    model: Option<String>,
+    language: Option<String>,
+    temperature: Option<f32>,
+    prompt: Option<String>,
+    response_format: ResponseFormat,
+    word_timestamps: bool,
+}
+
+impl TranscriptionRequest {
+    async fn from_multipart(mut multipart: Multipart) -> Result<Self, ApiError> {
+        let mut file = None;
+        let mut model = None;
+        let mut language = None;
+        let mut temperature = None;
+        let mut prompt = None;
+        let mut response_format = ResponseFormat::Json;
+        let mut word_timestamps = false;
+
+        while let Some(field) = multipart.next_field().await.map_err(|e| {
+            ApiError::new(e.status(), format!("invalid multipart body: {}", e.body_text()))
+        })? {
+            let name = field.name().unwrap_or_default().to_string();
+            if name == "file" {
+                let data = field.bytes().await.map_err(|e| {
+                    ApiError::new(e.status(), format!("failed to read file: {}", e.body_text()))
+                })?;
+                file = Some(data.to_vec());
+                continue;
+            }
+
+            let value = field
+                .text()
+                .await
+                .map_err(|e| ApiError::bad_request(format!("invalid field '{name}': {e}")))?;
+            match name.as_str() {
+                "model" => model = Some(value),
+                "language" if !value.is_empty() => language = Some(value),
+                "temperature" => {
+                    let t = value.parse().map_err(|_| {
+                        ApiError::bad_request(format!("temperature must be a number, got '{value}'"))
+                    })?;
+                    temperature = Some(t);
+                }
+                "response_format" => {
+                    response_format = ResponseFormat::parse(&value).ok_or_else(|| {
+                        ApiError::bad_request(format!(
+                            "unsupported response_format '{value}' (json, text, srt, vtt, verbose_json)"
+                        ))
+                    })?;
+                }
+                "timestamp_granularities[]" | "timestamp_granularities" => match value.as_str() {
+                    "word" => word_timestamps = true,
+                    "segment" => {}
+                    _ => {
+                        return Err(ApiError::bad_request(format!(
+                            "unsupported timestamp granularity '{value}' (word, segment)"
+                        )))
+                    }
+                },
+                "prompt" => prompt = Some(value),
+                // any future fields are accepted and ignored
+                _ => {}
+            }
+        }
+
+        let file = file.ok_or_else(|| ApiError::bad_request("missing 'file' field"))?;
+        Ok(Self {
+            file,
+            model,
+            language,
+            temperature,
+            prompt,
+            response_format,
+            word_timestamps,
+        })
+    }
+}
+
+async fn transcriptions(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Response, ApiError> {
+    // Taken before reading the upload, so waiting clients don't each hold a
+    // whole file in memory
+    let _permit = state
+        .permits
+        .acquire()
+        .await
+        .map_err(|_| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "server shutting down"))?;
+
+    let request = TranscriptionRequest::from_multipart(multipart).await?;
+
+    let engine = state
+        .engine(request.model.as_deref())
+        .ok_or_else(|| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "no model loaded"))?;
+
+    let mut options = engine.options().clone().word_timestamps(request.word_timestamps);
+    if let Some(lang) = &request.language {
+        options.language = Language::new(lang).map_err(ApiError::from)?;
+    }
+    if let Some(t) = request.temperature {
+        options = options.temperature(t).map_err(ApiError::from)?;
+    }
+    if let Some(prompt) = &request.prompt {
+        options = options.initial_prompt(prompt).map_err(ApiError::from)?;
+    }
+
+    let size = request.file.len();
+    let transcript = engine
+        .with_options(options)
+        .transcribe_bytes(request.file)
+        .await
+        .map_err(ApiError::from)?;
+    tracing::info!(
+        bytes = size,
+        duration = transcript.duration,
+        segments = transcript.segments.len(),
+        "served transcription"
+    );
+
+    Ok(render(&transcript, request.response_format, request.word_timestamps))
+}
+
+fn render(transcript: &Transcript, format: ResponseFormat, words: bool) -> Response {
+    match format {
+        ResponseFormat::Json => Json(serde_json::json!({ "text": transcript.text() })).into_response(),
+        ResponseFormat::Text => plain("text/plain; charset=utf-8", transcript.text()),
+        ResponseFormat::Srt => plain("text/plain; charset=utf-8", transcript.to_srt()),
+        ResponseFormat::Vtt => plain("text/vtt; charset=utf-8", transcript.to_vtt()),
+        ResponseFormat::VerboseJson => Json(VerboseTranscript::new(transcript, words)).into_response(),
+    }
+}
+
+fn plain(content_type: &'static str, body: String) -> Response {
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// `verbose_json` response body.
+#[derive(Debug, Serialize)]
+struct VerboseTranscript {
+    task: &'static str,
+    language: String,
+    duration: f64,
+    text: String,
+    segments: Vec<VerboseSegment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<Vec<VerboseWord>>,
+}
+
+#[derive(Debug, Serialize)]
+struct VerboseSegment {
+    id: usize,
+    seek: u64,
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avg_logprob: Option<f32>,
+    no_speech_prob: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct VerboseWord {
+    word: String,
+    start: f64,
+    end: f64,
+}
+
+impl VerboseTranscript {
+    fn new(transcript: &Transcript, words: bool) -> Self {
+        // OpenAI reports the language by name ("english"), whisper by code
+        let language = Language::supported()
+            .into_iter()
+            .find(|(code, _)| *code == transcript.language)
+            .map_or_else(|| transcript.language.clone(), |(_, name)| name.to_string());
+
+        let segments = transcript
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(id, seg)| VerboseSegment {
+                id,
+                seek: (seg.start * 100.0) as u64,
+                start: seg.start,
+                end: seg.end,
+                text: seg.text.clone(),
+                avg_logprob: seg.words.as_deref().filter(|w| !w.is_empty()).map(|w| {
+                    w.iter().map(|w| w.probability.max(f32::EPSILON).ln()).sum::<f32>() / w.len() as f32
+                }),
+                no_speech_prob: seg.no_speech_probability,
+                speaker: seg.speaker_id.clone(),
+            })
+            .collect();
+
+        let words = words.then(|| {
+            transcript
+                .segments
+                .iter()
+                .filter_map(|s| s.words.as_deref())
+                .flat_map(merge_tokens)
+                .collect()
+        });
+
+        Self {
+            task: "transcribe",
+            language,
+            duration: transcript.duration,
+            text: transcript.text(),
+            segments,
+            words,
+        }
+    }
+}
+
+/// Join whisper's sub-word tokens into words; a new word starts at a token
+/// beginning with whitespace.
+fn merge_tokens(tokens: &[Word]) -> Vec<VerboseWord> {
+    let mut words: Vec<VerboseWord> = Vec::new();
+    for token in tokens {
+        match words.last_mut() {
+            Some(last) if !token.text.starts_with(char::is_whitespace) => {
+                last.word.push_str(&token.text);
+                last.end = token.end;
+            }
+            _ => words.push(VerboseWord {
+                word: token.text.trim_start().to_string(),
+                start: token.start,
+                end: token.end,
+            }),
+        }
+    }
+    words
+}
+
+/// An error in the OpenAI shape: `{"error": {"message", "type", ...}}`.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl From<transcriber::Error> for ApiError {
+    fn from(e: transcriber::Error) -> Self {
+        use transcriber::Error;
+        let status = match e {
+            Error::AudioDecode(_) | Error::UnsupportedLanguage(_) | Error::InvalidOption(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let kind = if self.status.is_client_error() {
+            "invalid_request_error"
+        } else {
+            "server_error"
+        };
+        let body = serde_json::json!({
+            "error": { "message": self.message, "type": kind, "param": null, "code": null }
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn token(text: &str, start: f64, end: f64) -> Word {
+        Word {
+            text: text.into(),
+            start,
+            end,
+            probability: 0.5,
+        }
+    }
+
+    /// Serve `router` on an ephemeral localhost port and send one raw request.
+    async fn request(router: Router, raw: Vec<u8>) -> (u16, String) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(&raw).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    fn multipart(fields: &[(&str, &str)]) -> Vec<u8> {
+        let mut body = String::new();
+        for (name, value) in fields {
+            body.push_str(&format!(
+                "--XBOUNDARY\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            ));
+        }
+        body.push_str("--XBOUNDARY--\r\n");
+        format!(
+            "POST /v1/audio/transcriptions HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: multipart/form-data; boundary=XBOUNDARY\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .into_bytes()
+    }
+
+    fn empty_router() -> Router {
+        router(AppState::new(Vec::new(), 1), 1024 * 1024)
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let raw = b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n".to_vec();
+        let (status, body) = request(empty_router(), raw).await;
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["models"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_is_openai_error() {
+        let (status, body) = request(empty_router(), multipart(&[("model", "whisper-1")])).await;
+        assert_eq!(status, 400);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"]["type"], "invalid_request_error");
+        assert!(json["error"]["message"].as_str().unwrap().contains("file"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_fields() {
+        let (status, body) =
+            request(empty_router(), multipart(&[("response_format", "xml"), ("file", "x")])).await;
+        assert_eq!(status, 400);
+        assert!(body.contains("response_format"));
+
+        let raw = multipart(&[("timestamp_granularities[]", "char"), ("file", "x")]);
+        assert_eq!(request(empty_router(), raw).await.0, 400);
+    }
+
+    #[tokio::test]
+    async fn test_no_model_loaded() {
+        let (status, _) = request(empty_router(), multipart(&[("file", "RIFF")])).await;
+        assert_eq!(status, 503);
+    }
+
+    #[test]
+    fn test_response_format_parse() {
+        assert_eq!(ResponseFormat::parse("verbose_json"), Some(ResponseFormat::VerboseJson));
+        assert_eq!(ResponseFormat::parse("srt"), Some(ResponseFormat::Srt));
+        assert_eq!(ResponseFormat::parse("JSON"), None);
+    }
+
+    #[test]
+    fn test_merge_tokens() {
+        let tokens = [
+            token(" Hel", 0.0, 0.2),
+            token("lo", 0.2, 0.4),
+            token(" world", 0.5, 0.9),
+        ];
+        assert_eq!(
+            merge_tokens(&tokens),
+            vec![
+                VerboseWord { word: "Hello".into(), start: 0.0, end: 0.4 },
+                VerboseWord { word: "world".into(), start: 0.5, end: 0.9 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verbose_transcript_shape() {
+        let transcript: Transcript = serde_json::from_value(serde_json::json!({
+            "segments": [{
+                "start": 0.0, "end": 1.0, "text": " Hello world", "speaker_turn": false,
+                "no_speech_probability": 0.1,
+                "words": [
+                    { "text": " Hello", "start": 0.0, "end": 0.4, "probability": 1.0 },
+                    { "text": " world", "start": 0.5, "end": 0.9, "probability": 1.0 }
+                ]
+            }],
+            "language": "xx", "duration": 1.0, "model": "tiny",
+            "source_url": null, "source_title": null
+        }))
+        .unwrap();
+
+        let json = serde_json::to_value(VerboseTranscript::new(&transcript, true)).unwrap();
+        assert_eq!(json["task"], "transcribe");
+        assert_eq!(json["text"], "Hello world");
+        assert_eq!(json["segments"][0]["id"], 0);
+        assert_eq!(json["segments"][0]["avg_logprob"], 0.0);
+        assert_eq!(json["words"][1]["word"], "world");
+
+        let json = serde_json::to_value(VerboseTranscript::new(&transcript, false)).unwrap();
+        assert!(json.get("words").is_none());
+    }
+
+    #[test]
+    fn test_error_status_mapping() {
+        let e = ApiError::from(transcriber::Error::AudioDecode("bad".into()));
+        assert_eq!(e.status, StatusCode::BAD_REQUEST);
+        let e = ApiError::from(transcriber::Error::Transcription("boom".into()));
+        assert_eq!(e.status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
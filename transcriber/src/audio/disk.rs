@@ -0,0 +1,284 @@
+//! Decoded audio kept in a temporary file.
+//!
+//! A day of 16kHz f32 samples is over 5 GB, too much to hold in memory.
+//! [`DiskAudio`] stores the samples as 16-bit PCM (what ffmpeg decodes to, so
+//! nothing is lost) in a temporary file and reads back only the ranges a
+//! transcription chunk or speaker window needs. The file is deleted on drop.
+
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tracing::{debug, warn};
+
+use super::{
+    db_to_linear, find_first_active, find_last_active, padded_active_range, SampleSource,
+    MIN_RMS, SILENCE_WINDOW, WHISPER_SAMPLE_RATE,
+};
+use crate::config::AudioProcessing;
+use crate::error::{Error, Result};
+
+/// Samples read at a time when looking for leading/trailing silence (1 minute;
+/// small in tests so they cross block boundaries cheaply).
+const SCAN_BLOCK: usize = if cfg!(test) { 1000 } else { 60 * WHISPER_SAMPLE_RATE as usize };
+
+/// 16kHz mono audio in a temporary file, read on demand.
+///
+/// DC offset removal and peak normalization are applied as samples are read;
+/// trimmed silence is left in the file and skipped.
+pub(crate) struct DiskAudio {
+    // Declared before `path` so the file is closed before it is removed
+    file: Mutex<File>,
+    path: TempPath,
+    /// Stored samples that are read, after trimming silence.
+    view: Range<usize>,
+    offset: f32,
+    gain: f32,
+}
+
+impl SampleSource for DiskAudio {
+    fn len(&self) -> usize {
+        self.view.len()
+    }
+
+    fn read(&self, range: Range<usize>) -> Result<Cow<'_, [f32]>> {
+        debug_assert!(range.end <= self.view.len(), "read past the end of disk audio");
+        let mut bytes = vec![0u8; range.len() * 2];
+        {
+            let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+            file.seek(SeekFrom::Start((self.view.start + range.start) as u64 * 2))?;
+            file.read_exact(&mut bytes)?;
+        }
+        Ok(Cow::Owned(
+            bytes
+                .chunks_exact(2)
+                .map(|b| {
+                    let sample = i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0;
+                    (sample - self.offset) * self.gain
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl DiskAudio {
+    /// Narrow the view to the audio between leading and trailing silence.
+    fn trim_silence(&mut self, threshold_db: f32, pad_ms: u32) -> Result<()> {
+        let threshold = db_to_linear(threshold_db);
+        let len = self.len();
+        // Blocks overlap by one window less a sample so no window is missed
+        let overlap = SILENCE_WINDOW - 1;
+
+        let mut first = None;
+        let mut from = 0;
+        while from < len {
+            let block = self.read(from..(from + SCAN_BLOCK + overlap).min(len))?;
+            if let Some(i) = find_first_active(&block, SILENCE_WINDOW, threshold) {
+                first = Some(from + i);
+                break;
+            }
+            from += SCAN_BLOCK;
+        }
+
+        let mut last = None;
+        let mut to = len;
+        while to > 0 {
+            let from = to.saturating_sub(SCAN_BLOCK + overlap);
+            let block = self.read(from..to)?;
+            if let Some(i) = find_last_active(&block, SILENCE_WINDOW, threshold) {
+                last = Some(from + i);
+                break;
+            }
+            if from == 0 {
+                break;
+            }
+            to = from + overlap;
+        }
+
+        let keep = padded_active_range(first, last, len, pad_ms);
+        if keep != (0..len) {
+            debug!(
+                trimmed_start_ms = (keep.start as f64 / WHISPER_SAMPLE_RATE as f64 * 1000.0) as u64,
+                trimmed_end_ms =
+                    ((len - keep.end) as f64 / WHISPER_SAMPLE_RATE as f64 * 1000.0) as u64,
+                "trimmed silence"
+            );
+            self.view = self.view.start + keep.start..self.view.start + keep.end;
+        }
+        Ok(())
+    }
+}
+
+/// Writes decoded samples to a new temporary file, collecting what the
+/// processing steps need along the way.
+pub(crate) struct DiskAudioWriter {
+    file: BufWriter<File>,
+    path: TempPath,
+    len: usize,
+    sum: f64,
+    min: f32,
+    max: f32,
+    buf: Vec<u8>,
+}
+
+impl DiskAudioWriter {
+    /// Create a temporary file in the system temp directory.
+    pub(crate) fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "transcriber-audio-{}-{}.pcm",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| {
+                Error::AudioDecode(format!("cannot create {}: {e}", path.display()))
+            })?;
+        debug!(path = %path.display(), "writing decoded audio to disk");
+        Ok(Self {
+            file: BufWriter::new(file),
+            path: TempPath(path),
+            len: 0,
+            sum: 0.0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Append 16kHz mono samples.
+    pub(crate) fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.buf.clear();
+        for &sample in samples {
+            self.sum += sample as f64;
+            self.min = self.min.min(sample);
+            self.max = self.max.max(sample);
+            let pcm = (sample * 32768.0).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            self.buf.extend_from_slice(&pcm.to_le_bytes());
+        }
+        self.file.write_all(&self.buf).map_err(|e| {
+            Error::AudioDecode(format!("cannot write {}: {e}", self.path.0.display()))
+        })?;
+        self.len += samples.len();
+        Ok(())
+    }
+
+    /// Flush the file and set up `processing` for reading it back.
+    pub(crate) fn finish(self, processing: &AudioProcessing) -> Result<DiskAudio> {
+        let file = self.file.into_inner().map_err(|e| e.into_error())?;
+        let mut audio = DiskAudio {
+            file: Mutex::new(file),
+            path: self.path,
+            view: 0..self.len,
+            offset: 0.0,
+            gain: 1.0,
+        };
+        debug!(
+            samples = self.len,
+            path = %audio.path.0.display(),
+            "decoded audio"
+        );
+
+        if processing.dc_offset_removal && self.len > 0 {
+            let mean = (self.sum / self.len as f64) as f32;
+            if mean.abs() > MIN_RMS {
+                debug!(dc_offset = mean, "removing DC offset");
+                audio.offset = mean;
+            }
+        }
+
+        if processing.normalize {
+            let peak = (self.max - audio.offset).max(audio.offset - self.min);
+            if peak < MIN_RMS {
+                debug!("audio is silent (peak below threshold)");
+            } else if (peak - 1.0).abs() > 0.01 {
+                debug!(peak, "normalizing peak amplitude");
+                audio.gain = 1.0 / peak;
+            }
+        }
+
+        if processing.trim_silence {
+            audio.trim_silence(processing.silence_threshold_db, processing.silence_pad_ms)?;
+        }
+
+        Ok(audio)
+    }
+}
+
+/// Removes the file when dropped.
+struct TempPath(PathBuf);
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            warn!(path = %self.0.display(), error = %e, "failed to remove temp audio file");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk_audio(samples: &[f32], processing: &AudioProcessing) -> DiskAudio {
+        let mut writer = DiskAudioWriter::create().unwrap();
+        // Written in pieces, as ffmpeg output arrives
+        for chunk in samples.chunks(1000) {
+            writer.write(chunk).unwrap();
+        }
+        writer.finish(processing).unwrap()
+    }
+
+    #[test]
+    fn test_reads_back_ranges() {
+        let samples: Vec<f32> = (0..5000).map(|i| ((i % 200) as f32 - 100.0) / 128.0).collect();
+        let audio = disk_audio(&samples, &AudioProcessing::default());
+        assert_eq!(audio.len(), 5000);
+        assert_eq!(audio.read(0..5000).unwrap().as_ref(), samples.as_slice());
+        assert_eq!(audio.read(1234..1240).unwrap().as_ref(), &samples[1234..1240]);
+        assert!(audio.read(10..10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_applies_dc_offset_and_normalization() {
+        let samples: Vec<f32> = (0..1600).map(|i| if i % 2 == 0 { 0.75 } else { 0.25 }).collect();
+        let processing = AudioProcessing::new().dc_offset_removal(true).normalize(true);
+        let audio = disk_audio(&samples, &processing);
+        let read = audio.read(0..4).unwrap();
+        for (got, want) in read.iter().zip([1.0, -1.0, 1.0, -1.0]) {
+            assert!((got - want).abs() < 1e-3, "{got} != {want}");
+        }
+    }
+
+    #[test]
+    fn test_trims_silence_like_memory() {
+        let mut samples = vec![0.0f32; 3 * SCAN_BLOCK / 2];
+        // Exactly representable in 16 bits, so nothing changes on disk
+        samples.extend((0..16_000).map(|i| ((i as f32 * 0.1).sin() * 16384.0).round() / 32768.0));
+        samples.extend(vec![0.0f32; SCAN_BLOCK + 500]);
+        let processing = AudioProcessing::new().trim_silence(true);
+
+        let audio = disk_audio(&samples, &processing);
+        let in_memory = super::super::trim_silence(&samples, -40.0, 50);
+        assert_eq!(audio.len(), in_memory.len());
+        assert!(audio.read(0..audio.len()).unwrap().as_ref() == in_memory.as_slice());
+    }
+
+    #[test]
+    fn test_removes_file_on_drop() {
+        let audio = disk_audio(&[0.1; 100], &AudioProcessing::default());
+        let path = audio.path.0.clone();
+        assert!(path.exists());
+        drop(audio);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,1321 @@
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use tracing::{debug, info};
+
+use crate::config::{AudioBackend, AudioProcessing, ChannelMode, TimeRange};
+use crate::error::{Error, Result};
+
+mod disk;
+#[cfg(feature = "native-audio")]
+mod native;
+mod resample;
+
+pub(crate) use disk::DiskAudio;
+
+/// Target sample rate for whisper.cpp.
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Maximum duration in seconds of audio held in memory (8 hours).
+/// Prevents unbounded memory allocation from very long audio files.
+/// 8 hours at 16kHz mono f32 = ~1.8 GB. Disk-backed audio has no limit.
+const MAX_AUDIO_DURATION_SECS: f64 = 8.0 * 3600.0;
+
+/// Maximum raw PCM samples we accept from ffmpeg.
+/// 8 hours at 16kHz mono = ~460M samples.
+const MAX_FFMPEG_OUTPUT_SAMPLES: usize = 500_000_000;
+
+/// Maximum stderr bytes to include in error messages.
+const MAX_STDERR_BYTES: usize = 4_000;
+
+/// Minimum RMS level — below this we consider the audio silent/empty.
+const MIN_RMS: f32 = 1e-6;
+
+/// Decoded 16kHz mono audio that can be read piece by piece, whether it is
+/// held in memory or kept on disk ([`DiskAudio`]).
+pub(crate) trait SampleSource: Sync {
+    /// Number of samples.
+    fn len(&self) -> usize;
+
+    /// The samples in `range`, which must lie within `0..len()`.
+    fn read(&self, range: Range<usize>) -> Result<Cow<'_, [f32]>>;
+
+    /// All samples at once, if they are held in memory.
+    fn as_slice(&self) -> Option<&[f32]> {
+        None
+    }
+}
+
+impl SampleSource for [f32] {
+    fn len(&self) -> usize {
+        <[f32]>::len(self)
+    }
+
+    fn read(&self, range: Range<usize>) -> Result<Cow<'_, [f32]>> {
+        Ok(Cow::Borrowed(&self[range]))
+    }
+
+    fn as_slice(&self) -> Option<&[f32]> {
+        Some(self)
+    }
+}
+
+impl SampleSource for Vec<f32> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn read(&self, range: Range<usize>) -> Result<Cow<'_, [f32]>> {
+        Ok(Cow::Borrowed(&self[range]))
+    }
+
+    fn as_slice(&self) -> Option<&[f32]> {
+        Some(self)
+    }
+}
+
+/// Load an audio file, decode it, and return 16kHz mono f32 samples ready for whisper.
+///
+/// With [`AudioBackend::Ffmpeg`] (the default), uses ffmpeg to decode any audio format,
+/// downmix to mono, and resample to 16kHz — exactly like the proven brewery/whisperx
+/// pipeline. Supports every format ffmpeg does (mp3, wav, ogg, opus, webm, aac, flac,
+/// m4a, wma, aiff, ...).
+///
+/// With [`AudioBackend::Native`], decodes in-process and falls back to ffmpeg for
+/// containers or codecs the native decoders don't cover.
+///
+/// Optional processing (controlled by `AudioProcessing`):
+/// - Pick a single channel
+/// - Remove DC offset
+/// - Peak normalize
+/// - Trim leading/trailing silence
+pub fn load_audio(path: &Path, processing: &AudioProcessing) -> Result<Vec<f32>> {
+    load_audio_range(path, processing, None)
+}
+
+/// [`load_audio`] limited to `range` of the file. ffmpeg seeks to the start
+/// of the range instead of decoding everything before it.
+pub fn load_audio_range(
+    path: &Path,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    info!(path = %path.display(), ?range, "loading audio");
+
+    if !path.exists() {
+        return Err(Error::AudioNotFound {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let samples = match processing.backend {
+        AudioBackend::Native if processing.channels == ChannelMode::Mix => {
+            load_native(path, processing, range)?
+        }
+        _ => load_with_ffmpeg(path, processing, range)?,
+    };
+
+    finish_samples(samples, processing)
+}
+
+/// Decode an encoded audio/video file held in memory (e.g. an HTTP upload).
+///
+/// The container format is detected from the content. With the ffmpeg backend
+/// the bytes are piped through stdin, so formats that need seeking (MP4/M4A with
+/// the index at the end of the file) may fail; the native backend reads them
+/// from memory and has no such restriction.
+///
+/// Only `range` of the input is decoded, if given.
+pub fn load_audio_bytes(
+    data: Vec<u8>,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    info!(bytes = data.len(), ?range, "loading audio from memory");
+
+    if data.is_empty() {
+        return Err(Error::AudioDecode("audio buffer is empty".into()));
+    }
+
+    let samples = match processing.backend {
+        AudioBackend::Native if processing.channels == ChannelMode::Mix => {
+            load_native_bytes(data, processing, range)?
+        }
+        _ => {
+            check_channel(FfmpegInput::Bytes(&data), processing.channels)?;
+            decode_with_ffmpeg(FfmpegInput::Bytes(&data), processing, range)?
+        }
+    };
+
+    finish_samples(samples, processing)
+}
+
+/// Convert already-decoded PCM to 16kHz mono and apply `processing`.
+///
+/// `samples` are interleaved when `channels > 1`; channels are averaged to mono
+/// unless [`AudioProcessing::channels`] picks one. The ffmpeg conditioning
+/// filters are not applied — the PCM is taken as-is.
+pub fn load_pcm(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    processing: &AudioProcessing,
+) -> Result<Vec<f32>> {
+    let mut converter = PcmConverter::new(sample_rate, channels)?;
+    if samples.len() % channels as usize != 0 {
+        return Err(Error::InvalidOption(format!(
+            "{} samples is not a whole number of {channels}-channel frames",
+            samples.len()
+        )));
+    }
+    let picked;
+    let (samples, channels) = match processing.channels {
+        ChannelMode::Mix => (samples, channels),
+        ChannelMode::Channel(n) => {
+            if n >= channels {
+                return Err(missing_channel(n, channels));
+            }
+            converter = PcmConverter::new(sample_rate, 1)?;
+            picked = samples
+                .iter()
+                .skip(n as usize)
+                .step_by(channels as usize)
+                .copied()
+                .collect::<Vec<_>>();
+            (&picked[..], 1)
+        }
+        ChannelMode::PerChannel => return Err(per_channel_unsupported()),
+    };
+
+    let duration = samples.len() as f64 / channels as f64 / sample_rate as f64;
+    if duration > MAX_AUDIO_DURATION_SECS {
+        return Err(too_long(duration));
+    }
+
+    debug!(sample_rate, channels, "converting PCM");
+
+    let mut out = Vec::with_capacity(
+        (samples.len() as u64 * WHISPER_SAMPLE_RATE as u64
+            / (sample_rate as u64 * channels as u64)) as usize
+            + 1,
+    );
+    converter.push(samples, &mut out);
+    converter.finish(&mut out);
+
+    finish_samples(out, processing)
+}
+
+/// Incremental conversion of interleaved PCM to 16kHz mono.
+///
+/// Chunks may split frames anywhere; partial frames are held until completed.
+pub(crate) struct PcmConverter {
+    channels: usize,
+    resampler: Option<resample::Resampler>,
+    /// Trailing samples of an incomplete frame from the last push.
+    partial: Vec<f32>,
+    mono: Vec<f32>,
+}
+
+impl PcmConverter {
+    pub(crate) fn new(sample_rate: u32, channels: u16) -> Result<Self> {
+        if sample_rate == 0 {
+            return Err(Error::InvalidOption("sample rate must be non-zero".into()));
+        }
+        if channels == 0 {
+            return Err(Error::InvalidOption("channel count must be non-zero".into()));
+        }
+        Ok(Self {
+            channels: channels as usize,
+            resampler: (sample_rate != WHISPER_SAMPLE_RATE)
+                .then(|| resample::Resampler::new(sample_rate, WHISPER_SAMPLE_RATE)),
+            partial: Vec::new(),
+            mono: Vec::new(),
+        })
+    }
+
+    /// Convert `input`, appending the 16kHz mono output available so far.
+    pub(crate) fn push(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.mono.clear();
+        let rest = if self.partial.is_empty() {
+            input
+        } else {
+            let need = (self.channels - self.partial.len()).min(input.len());
+            self.partial.extend_from_slice(&input[..need]);
+            if self.partial.len() == self.channels {
+                downmix_into(&self.partial, self.channels, &mut self.mono);
+                self.partial.clear();
+            }
+            &input[need..]
+        };
+        let whole = rest.len() - rest.len() % self.channels;
+        downmix_into(&rest[..whole], self.channels, &mut self.mono);
+        self.partial.extend_from_slice(&rest[whole..]);
+
+        match &mut self.resampler {
+            Some(r) => r.process(&self.mono, out),
+            None => out.extend_from_slice(&self.mono),
+        }
+    }
+
+    /// Flush the resampler tail. An incomplete trailing frame is dropped.
+    pub(crate) fn finish(&mut self, out: &mut Vec<f32>) {
+        self.partial.clear();
+        if let Some(r) = &mut self.resampler {
+            r.finish(out);
+        }
+    }
+}
+
+/// Duration safety check plus the optional processing steps shared by all inputs.
+fn finish_samples(mut samples: Vec<f32>, processing: &AudioProcessing) -> Result<Vec<f32>> {
+    let duration_raw = samples.len() as f64 / WHISPER_SAMPLE_RATE as f64;
+    debug!(
+        samples = samples.len(),
+        duration_secs = format!("{duration_raw:.1}"),
+        "decoded audio"
+    );
+
+    // Post-decode check as a safety net (ffprobe might not be available or may
+    // report duration inaccurately for some formats).
+    if duration_raw > MAX_AUDIO_DURATION_SECS {
+        return Err(too_long(duration_raw));
+    }
+
+    // Optional processing steps
+    if processing.dc_offset_removal {
+        remove_dc_offset(&mut samples);
+    }
+
+    if processing.normalize {
+        normalize_peak(&mut samples);
+    }
+
+    if processing.trim_silence {
+        samples = trim_silence(
+            &samples,
+            processing.silence_threshold_db,
+            processing.silence_pad_ms,
+        );
+    }
+
+    let duration = samples.len() as f64 / WHISPER_SAMPLE_RATE as f64;
+    info!(duration_secs = format!("{duration:.1}"), "audio ready");
+
+    Ok(samples)
+}
+
+fn too_long(duration: f64) -> Error {
+    Error::AudioDecode(format!(
+        "audio too long ({duration:.0}s) — maximum duration in memory is {MAX_AUDIO_DURATION_SECS:.0}s, \
+         use disk-backed audio for longer recordings"
+    ))
+}
+
+/// Decode an audio/video file with ffmpeg into a temporary file instead of
+/// memory, so recordings of any length can be transcribed.
+///
+/// Always decodes with ffmpeg, whatever the configured backend. The
+/// processing steps are applied as the audio is read back; silence trimming
+/// only narrows the part that is read.
+pub fn load_audio_to_disk(
+    path: &Path,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<DiskAudio> {
+    info!(path = %path.display(), ?range, "loading audio to disk");
+
+    if !path.exists() {
+        return Err(Error::AudioNotFound {
+            path: path.to_path_buf(),
+        });
+    }
+
+    check_channel(FfmpegInput::Path(path), processing.channels)?;
+    let mut writer = disk::DiskAudioWriter::create()?;
+    let mut child = spawn_ffmpeg(FfmpegInput::Path(path), processing, range)?;
+    stream_ffmpeg_output(&mut child, |chunk| writer.write(chunk))?;
+    let audio = writer.finish(processing)?;
+
+    let duration = audio.len() as f64 / WHISPER_SAMPLE_RATE as f64;
+    info!(duration_secs = format!("{duration:.1}"), "audio ready");
+    Ok(audio)
+}
+
+/// Decode with ffmpeg after checking the duration with ffprobe.
+fn load_with_ffmpeg(
+    path: &Path,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    // Pre-check duration via ffprobe before loading entire file into memory.
+    // This prevents OOM from very long audio files.
+    if let Some(mut duration) = probe_duration(path) {
+        if let Some(range) = range {
+            duration = range.duration_within(duration)?;
+        }
+        if duration > MAX_AUDIO_DURATION_SECS {
+            return Err(too_long(duration));
+        }
+    }
+
+    check_channel(FfmpegInput::Path(path), processing.channels)?;
+    decode_with_ffmpeg(FfmpegInput::Path(path), processing, range)
+}
+
+/// Keep only `range` of fully decoded samples.
+pub(crate) fn cut(mut samples: Vec<f32>, range: Option<TimeRange>) -> Result<Vec<f32>> {
+    if let Some(range) = range {
+        let keep = range.samples(WHISPER_SAMPLE_RATE, samples.len())?;
+        samples.truncate(keep.end);
+        samples.drain(..keep.start);
+    }
+    Ok(samples)
+}
+
+/// Decode in-process, falling back to ffmpeg for unsupported formats.
+/// The native decoders can't seek, so a range is cut from the decoded file.
+#[cfg(feature = "native-audio")]
+fn load_native(
+    path: &Path,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    match native::decode(path, MAX_AUDIO_DURATION_SECS)? {
+        Some(samples) => cut(samples, range),
+        None => {
+            debug!(path = %path.display(), "format not supported natively, falling back to ffmpeg");
+            load_with_ffmpeg(path, processing, range)
+        }
+    }
+}
+
+#[cfg(not(feature = "native-audio"))]
+fn load_native(
+    _path: &Path,
+    _processing: &AudioProcessing,
+    _range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    Err(native_unavailable())
+}
+
+/// In-memory variant of [`load_native`].
+#[cfg(feature = "native-audio")]
+fn load_native_bytes(
+    data: Vec<u8>,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    // Shared so the bytes are still around if ffmpeg has to take over
+    let data: std::sync::Arc<[u8]> = data.into();
+    match native::decode_bytes(std::sync::Arc::clone(&data), MAX_AUDIO_DURATION_SECS)? {
+        Some(samples) => cut(samples, range),
+        None => {
+            debug!("format not supported natively, falling back to ffmpeg");
+            decode_with_ffmpeg(FfmpegInput::Bytes(&data), processing, range)
+        }
+    }
+}
+
+#[cfg(not(feature = "native-audio"))]
+fn load_native_bytes(
+    _data: Vec<u8>,
+    _processing: &AudioProcessing,
+    _range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    Err(native_unavailable())
+}
+
+#[cfg(not(feature = "native-audio"))]
+fn native_unavailable() -> Error {
+    Error::InvalidOption("the native audio backend requires the `native-audio` feature".into())
+}
+
+/// Probe audio duration using ffprobe without decoding the full file.
+/// Returns None if ffprobe is not available or can't determine duration.
+pub(crate) fn probe_duration(path: &Path) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "quiet",
+            "-show_entries", "format=duration",
+            "-of", "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(path)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.trim().parse::<f64>().ok()
+}
+
+/// Count the channels of the first audio stream with ffprobe.
+/// Returns None if ffprobe is not available or can't tell.
+fn probe_channels(input: FfmpegInput<'_>) -> Option<u16> {
+    let mut cmd = Command::new("ffprobe");
+    cmd.args([
+        "-v", "quiet",
+        "-select_streams", "a:0",
+        "-show_entries", "stream=channels",
+        "-of", "default=noprint_wrappers=1:nokey=1",
+    ]);
+    match input {
+        FfmpegInput::Path(path) => cmd.arg(path).stdin(Stdio::null()),
+        FfmpegInput::Bytes(_) => cmd.arg("pipe:0").stdin(Stdio::piped()),
+    };
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::null()).spawn().ok()?;
+    let stdin = child.stdin.take();
+    let output = std::thread::scope(|scope| {
+        if let (FfmpegInput::Bytes(data), Some(mut stdin)) = (input, stdin) {
+            // ffprobe stops reading once it has seen the stream headers
+            scope.spawn(move || {
+                let _ = stdin.write_all(data);
+            });
+        }
+        child.wait_with_output()
+    })
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok().filter(|&n| n > 0)
+}
+
+/// Number of channels in an audio/video file, for per-channel transcription.
+pub(crate) fn count_channels(path: &Path) -> Result<u16> {
+    if !path.exists() {
+        return Err(Error::AudioNotFound {
+            path: path.to_path_buf(),
+        });
+    }
+    probe_channels(FfmpegInput::Path(path)).ok_or_else(|| {
+        Error::AudioDecode(format!(
+            "cannot count the channels of {} (is ffprobe installed?)",
+            path.display()
+        ))
+    })
+}
+
+/// [`count_channels`] for an encoded file held in memory.
+pub(crate) fn count_channels_bytes(data: &[u8]) -> Result<u16> {
+    probe_channels(FfmpegInput::Bytes(data)).ok_or_else(|| {
+        Error::AudioDecode("cannot count the channels of the input (is ffprobe installed?)".into())
+    })
+}
+
+/// Fail early with a clear message if `channels` picks a channel `input`
+/// doesn't have. Inputs ffprobe can't read are left for ffmpeg to reject.
+fn check_channel(input: FfmpegInput<'_>, channels: ChannelMode) -> Result<()> {
+    if let ChannelMode::Channel(n) = channels {
+        if let Some(count) = probe_channels(input).filter(|&count| n >= count) {
+            return Err(missing_channel(n, count));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn missing_channel(channel: u16, count: u16) -> Error {
+    Error::InvalidOption(format!(
+        "channel {channel} does not exist: the input has {count} channel(s), numbered from 0"
+    ))
+}
+
+/// Per-channel mode is resolved by the engine, which loads one channel at a time.
+fn per_channel_unsupported() -> Error {
+    Error::InvalidOption(
+        "per-channel mode transcribes each channel separately; \
+         load one channel at a time with ChannelMode::Channel"
+            .into(),
+    )
+}
+
+/// Where ffmpeg reads its input from.
+#[derive(Clone, Copy)]
+enum FfmpegInput<'a> {
+    /// A file on disk.
+    Path(&'a Path),
+    /// Encoded bytes, piped through stdin.
+    Bytes(&'a [u8]),
+}
+
+/// Decode any audio file to 16kHz mono f32 via ffmpeg subprocess.
+///
+/// Streams ffmpeg's stdout incrementally to avoid holding the entire raw PCM
+/// output in memory alongside the converted f32 samples. Converts s16le chunks
+/// to f32 on the fly.
+fn decode_with_ffmpeg(
+    input: FfmpegInput<'_>,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    let mut child = spawn_ffmpeg(input, processing, range)?;
+    let stdin = child.stdin.take();
+    std::thread::scope(|scope| {
+        // Feed stdin from a separate thread; writing everything up front would
+        // deadlock once ffmpeg blocks on a full stdout pipe.
+        if let (FfmpegInput::Bytes(data), Some(mut stdin)) = (input, stdin) {
+            scope.spawn(move || {
+                // ffmpeg may stop reading early (bad input); its exit status
+                // reports that, so a broken pipe here is not an error.
+                let _ = stdin.write_all(data);
+            });
+        }
+        read_ffmpeg_output(&mut child)
+    })
+}
+
+/// Start ffmpeg decoding `input` (or `range` of it) to 16kHz mono s16le on
+/// its stdout, with the channel selection and conditioning of `processing`.
+fn spawn_ffmpeg(
+    input: FfmpegInput<'_>,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Child> {
+    let mut filters = Vec::new();
+    match processing.channels {
+        ChannelMode::Mix => {}
+        ChannelMode::Channel(n) => filters.push(format!("pan=mono|c0=c{n}")),
+        ChannelMode::PerChannel => return Err(per_channel_unsupported()),
+    }
+    filters.extend(processing.conditioning.filter()?.map(str::to_string));
+    let mut cmd = Command::new("ffmpeg");
+    if let FfmpegInput::Path(_) = input {
+        cmd.arg("-nostdin");
+    }
+    cmd.args(["-loglevel", "error", "-threads", "0"]);
+    if let Some(range) = range {
+        // As input options, so ffmpeg seeks instead of decoding up to the start
+        cmd.args(["-ss", &range.start.to_string()]);
+        if let Some(end) = range.end {
+            cmd.args(["-to", &end.to_string()]);
+        }
+    }
+    match input {
+        FfmpegInput::Path(path) => {
+            cmd.arg("-i").arg(path).stdin(Stdio::null());
+        }
+        FfmpegInput::Bytes(_) => {
+            cmd.args(["-i", "pipe:0"]).stdin(Stdio::piped());
+        }
+    }
+
+    if !filters.is_empty() {
+        debug!(
+            channels = %processing.channels,
+            conditioning = %processing.conditioning,
+            "filtering audio"
+        );
+        cmd.args(["-af", &filters.join(",")]);
+    }
+
+    cmd
+        .args([
+            "-f",
+            "s16le",
+            "-ac",
+            "1",
+            "-acodec",
+            "pcm_s16le",
+            "-ar",
+            &WHISPER_SAMPLE_RATE.to_string(),
+            "-",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Error::AudioDecode(
+                    "ffmpeg not found — install with: apt install ffmpeg".into(),
+                )
+            } else {
+                Error::AudioDecode(format!("failed to run ffmpeg: {e}"))
+            }
+        })
+}
+
+/// Collect ffmpeg's s16le stdout as f32 samples and check its exit status.
+fn read_ffmpeg_output(child: &mut Child) -> Result<Vec<f32>> {
+    let mut samples = Vec::new();
+    stream_ffmpeg_output(child, |chunk| {
+        samples.extend_from_slice(chunk);
+        if samples.len() > MAX_FFMPEG_OUTPUT_SAMPLES {
+            return Err(Error::AudioDecode(format!(
+                "ffmpeg output too large ({} samples, max {})",
+                samples.len(),
+                MAX_FFMPEG_OUTPUT_SAMPLES
+            )));
+        }
+        Ok(())
+    })?;
+    Ok(samples)
+}
+
+/// Hand ffmpeg's s16le stdout to `sink` as f32 samples, a few thousand at a
+/// time, and check its exit status. ffmpeg is killed if `sink` fails.
+fn stream_ffmpeg_output(
+    child: &mut Child,
+    mut sink: impl FnMut(&[f32]) -> Result<()>,
+) -> Result<()> {
+    let stdout = child.stdout.take()
+        .ok_or_else(|| Error::AudioDecode("failed to capture ffmpeg stdout".into()))?;
+
+    // Read and convert s16le -> f32 in 8 KB chunks (4096 samples per read).
+    let mut reader = std::io::BufReader::new(stdout);
+    let mut samples = Vec::with_capacity(4096);
+    let mut total = 0;
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = reader.read(&mut buf).map_err(|e| {
+            let _ = child.kill();
+            Error::AudioDecode(format!("error reading ffmpeg output: {e}"))
+        })?;
+        if n == 0 {
+            break;
+        }
+
+        // Process complete s16le sample pairs from the buffer
+        let usable = n - (n % 2);
+        samples.clear();
+        for chunk in buf[..usable].chunks_exact(2) {
+            let sample = i16::from_le_bytes([chunk[0], chunk[1]]);
+            samples.push(sample as f32 / 32768.0);
+        }
+        total += samples.len();
+
+        if let Err(e) = sink(&samples) {
+            // Kill the child to avoid leaving an orphan
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+    }
+
+    // Drop the reader so stdout is closed, then wait for the process
+    drop(reader);
+    let status = child.wait().map_err(|e| {
+        Error::AudioDecode(format!("failed to wait for ffmpeg: {e}"))
+    })?;
+
+    if !status.success() {
+        // Read stderr for error context (child.stderr is still available)
+        let mut stderr_buf = Vec::new();
+        if let Some(stderr) = child.stderr.take() {
+            let _ = stderr.take(MAX_STDERR_BYTES as u64).read_to_end(&mut stderr_buf);
+        }
+        let stderr = String::from_utf8_lossy(&stderr_buf);
+        return Err(Error::AudioDecode(format!("ffmpeg failed: {stderr}")));
+    }
+
+    if total == 0 {
+        return Err(Error::AudioDecode("ffmpeg produced no output".into()));
+    }
+
+    Ok(())
+}
+
+/// Average interleaved frames down to one channel.
+fn downmix_into(interleaved: &[f32], channels: usize, out: &mut Vec<f32>) {
+    if channels == 1 {
+        out.extend_from_slice(interleaved);
+        return;
+    }
+    let scale = 1.0 / channels as f32;
+    out.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() * scale),
+    );
+}
+
+/// Remove DC offset by subtracting the mean value.
+fn remove_dc_offset(samples: &mut [f32]) {
+    if samples.is_empty() {
+        return;
+    }
+
+    let mean = samples.iter().copied().sum::<f32>() / samples.len() as f32;
+    if mean.abs() > MIN_RMS {
+        debug!(dc_offset = mean, "removing DC offset");
+        for s in samples.iter_mut() {
+            *s -= mean;
+        }
+    }
+}
+
+/// Normalize peak amplitude to 1.0.
+fn normalize_peak(samples: &mut [f32]) {
+    if samples.is_empty() {
+        return;
+    }
+
+    let peak = samples
+        .iter()
+        .copied()
+        .map(f32::abs)
+        .fold(0.0f32, f32::max);
+
+    if peak < MIN_RMS {
+        debug!("audio is silent (peak below threshold)");
+        return;
+    }
+
+    if (peak - 1.0).abs() > 0.01 {
+        debug!(peak, "normalizing peak amplitude");
+        let scale = 1.0 / peak;
+        for s in samples.iter_mut() {
+            *s *= scale;
+        }
+    }
+}
+
+/// Trim leading and trailing silence using a window-based RMS approach.
+fn trim_silence(samples: &[f32], threshold_db: f32, pad_ms: u32) -> Vec<f32> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+
+    let threshold = db_to_linear(threshold_db);
+    let first = find_first_active(samples, SILENCE_WINDOW, threshold);
+    let last = find_last_active(samples, SILENCE_WINDOW, threshold);
+    let Range { start, end } = padded_active_range(first, last, samples.len(), pad_ms);
+
+    if start == 0 && end == samples.len() {
+        return samples.to_vec();
+    }
+
+    debug!(
+        trimmed_start_ms = (start as f64 / WHISPER_SAMPLE_RATE as f64 * 1000.0) as u64,
+        trimmed_end_ms =
+            ((samples.len() - end) as f64 / WHISPER_SAMPLE_RATE as f64 * 1000.0) as u64,
+        "trimmed silence"
+    );
+
+    samples[start..end].to_vec()
+}
+
+/// Window for silence detection (10ms).
+const SILENCE_WINDOW: usize = WHISPER_SAMPLE_RATE as usize / 100;
+
+/// The range to keep when trimming, given the first and last active sample:
+/// the active part widened by `pad_ms`, or everything if nothing is active.
+fn padded_active_range(
+    first: Option<usize>,
+    last: Option<usize>,
+    len: usize,
+    pad_ms: u32,
+) -> Range<usize> {
+    let start = first.unwrap_or(0);
+    let end = last.unwrap_or(len);
+    if start >= end {
+        return 0..len;
+    }
+
+    let pad_samples = (WHISPER_SAMPLE_RATE as usize * pad_ms as usize) / 1000;
+    start.saturating_sub(pad_samples)..(end + pad_samples).min(len)
+}
+
+fn find_first_active(samples: &[f32], window_size: usize, threshold: f32) -> Option<usize> {
+    for (i, window) in samples.windows(window_size).enumerate() {
+        if rms(window) > threshold {
+            return Some(i);
+        }
+    }
+    None
+}
+
+fn find_last_active(samples: &[f32], window_size: usize, threshold: f32) -> Option<usize> {
+    let total_windows = samples.len().saturating_sub(window_size - 1);
+    for i in (0..total_windows).rev() {
+        if rms(&samples[i..i + window_size]) > threshold {
+            return Some(i + window_size);
+        }
+    }
+    None
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum_sq: f32 = samples.iter().map(|&s| s * s).sum();
+    (sum_sq / samples.len() as f32).sqrt()
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::AudioProcessing;
+
+    fn fixtures_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    // --- ffmpeg decoding tests ---
+
+    #[test]
+    fn test_load_wav() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        // 2 seconds at 16kHz = 32000 samples (roughly)
+        assert!(samples.len() > 30_000);
+        assert!(samples.len() < 34_000);
+    }
+
+    #[test]
+    fn test_load_mp3() {
+        let path = fixtures_dir().join("sine_440hz_1s.mp3");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        // 1 second at 16kHz = ~16000 samples
+        assert!(samples.len() > 14_000);
+        assert!(samples.len() < 18_000);
+    }
+
+    #[test]
+    fn test_load_opus() {
+        let path = fixtures_dir().join("sine_440hz_1s.opus");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        assert!(samples.len() > 14_000);
+        assert!(samples.len() < 18_000);
+    }
+
+    #[test]
+    fn test_load_flac() {
+        let path = fixtures_dir().join("sine_48khz_1s.flac");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        // Resampled from 48kHz to 16kHz, 1 second
+        assert!(samples.len() > 14_000);
+        assert!(samples.len() < 18_000);
+    }
+
+    #[test]
+    fn test_load_stereo_downmix() {
+        let path = fixtures_dir().join("stereo_2s.wav");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        // Should be mono after ffmpeg -ac 1
+        assert!(samples.len() > 30_000);
+        assert!(samples.len() < 34_000);
+    }
+
+    #[test]
+    fn test_load_single_channel() {
+        let path = fixtures_dir().join("stereo_2s.wav");
+        let right = AudioProcessing::new().channels(ChannelMode::Channel(1));
+        let samples = load_audio(&path, &right).unwrap();
+        assert!(samples.len() > 30_000);
+        assert!(samples.len() < 34_000);
+        assert_eq!(count_channels(&path).unwrap(), 2);
+
+        let missing = AudioProcessing::new().channels(ChannelMode::Channel(2));
+        assert!(matches!(load_audio(&path, &missing), Err(Error::InvalidOption(_))));
+        let per_channel = AudioProcessing::new().channels(ChannelMode::PerChannel);
+        assert!(matches!(load_audio(&path, &per_channel), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn test_load_to_disk_matches_memory() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let processing = AudioProcessing::all();
+        let in_memory = load_audio(&path, &processing).unwrap();
+        let on_disk = load_audio_to_disk(&path, &processing, None).unwrap();
+        assert_eq!(on_disk.len(), in_memory.len());
+        let read = on_disk.read(0..on_disk.len()).unwrap();
+        assert!(read.iter().zip(&in_memory).all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_load_time_range() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let range = TimeRange { start: 0.5, end: Some(1.5) };
+        let samples = load_audio_range(&path, &AudioProcessing::default(), Some(range)).unwrap();
+        // 1 second at 16kHz
+        assert!(samples.len() > 15_000);
+        assert!(samples.len() < 17_000);
+
+        let past_end = TimeRange { start: 10.0, end: None };
+        assert!(load_audio_range(&path, &AudioProcessing::default(), Some(past_end)).is_err());
+    }
+
+    #[test]
+    fn test_cut_time_range() {
+        let samples: Vec<f32> = (0..32_000).map(|i| i as f32).collect();
+        let range = TimeRange { start: 0.5, end: Some(1.0) };
+        let cut_samples = cut(samples.clone(), Some(range)).unwrap();
+        assert_eq!(cut_samples.len(), 8_000);
+        assert_eq!(cut_samples[0], 8_000.0);
+        assert_eq!(cut(samples.clone(), None).unwrap().len(), 32_000);
+        assert!(cut(samples, Some(TimeRange { start: 3.0, end: None })).is_err());
+    }
+
+    #[test]
+    fn test_load_nonexistent_file() {
+        let path = fixtures_dir().join("does_not_exist.wav");
+        let result = load_audio(&path, &AudioProcessing::default());
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), Error::AudioNotFound { .. }));
+    }
+
+    #[test]
+    fn test_samples_in_valid_range() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        for &s in &samples {
+            assert!((-1.0..=1.0).contains(&s), "sample {s} out of range");
+        }
+    }
+
+    // --- Native backend tests ---
+
+    #[cfg(feature = "native-audio")]
+    fn native() -> AudioProcessing {
+        AudioProcessing::new().backend(AudioBackend::Native)
+    }
+
+    #[cfg(feature = "native-audio")]
+    #[test]
+    fn test_native_load_fixtures() {
+        // (fixture, expected seconds)
+        let cases = [
+            ("sine_440hz_2s.wav", 2.0),
+            ("sine_440hz_1s.mp3", 1.0),
+            ("sine_440hz_1s.opus", 1.0),
+            ("sine_48khz_1s.flac", 1.0),
+            ("stereo_2s.wav", 2.0),
+        ];
+        for (name, secs) in cases {
+            let samples = load_audio(&fixtures_dir().join(name), &native())
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+            let expected = secs * WHISPER_SAMPLE_RATE as f64;
+            let ratio = samples.len() as f64 / expected;
+            assert!((0.9..1.1).contains(&ratio), "{name}: {} samples", samples.len());
+            assert!(rms(&samples) > 0.01, "{name}: decoded silence");
+            for &s in &samples {
+                assert!((-1.0..=1.0).contains(&s), "{name}: sample {s} out of range");
+            }
+        }
+    }
+
+    #[cfg(feature = "native-audio")]
+    #[test]
+    fn test_native_processing_applies() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let samples = load_audio(&path, &AudioProcessing::all().backend(AudioBackend::Native))
+            .unwrap();
+        let peak = samples.iter().copied().map(f32::abs).fold(0.0f32, f32::max);
+        assert!((peak - 1.0).abs() < 0.01, "should be normalized, peak={peak}");
+    }
+
+    #[cfg(feature = "native-audio")]
+    #[test]
+    fn test_native_nonexistent_file() {
+        let path = fixtures_dir().join("does_not_exist.wav");
+        let result = load_audio(&path, &native());
+        assert!(matches!(result.unwrap_err(), Error::AudioNotFound { .. }));
+    }
+
+    #[cfg(not(feature = "native-audio"))]
+    #[test]
+    fn test_native_backend_requires_feature() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let processing = AudioProcessing::new().backend(AudioBackend::Native);
+        let result = load_audio(&path, &processing);
+        assert!(matches!(result.unwrap_err(), Error::InvalidOption(_)));
+    }
+
+    // --- In-memory input tests ---
+
+    #[test]
+    fn test_load_bytes() {
+        let data = std::fs::read(fixtures_dir().join("sine_440hz_2s.wav")).unwrap();
+        let samples = load_audio_bytes(data, &AudioProcessing::default(), None).unwrap();
+        assert!(samples.len() > 30_000);
+        assert!(samples.len() < 34_000);
+    }
+
+    #[test]
+    fn test_load_bytes_empty() {
+        let result = load_audio_bytes(Vec::new(), &AudioProcessing::default(), None);
+        assert!(matches!(result.unwrap_err(), Error::AudioDecode(_)));
+    }
+
+    #[cfg(feature = "native-audio")]
+    #[test]
+    fn test_native_load_bytes() {
+        for name in ["sine_440hz_1s.mp3", "sine_440hz_1s.opus", "sine_48khz_1s.flac"] {
+            let data = std::fs::read(fixtures_dir().join(name)).unwrap();
+            let samples = load_audio_bytes(data, &native(), None)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert!(samples.len() > 14_000 && samples.len() < 18_000, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_load_pcm_resamples_and_downmixes() {
+        // 1s of 48kHz stereo: left = 0.5, right = -0.1
+        let pcm: Vec<f32> = (0..48_000).flat_map(|_| [0.5, -0.1]).collect();
+        let samples = load_pcm(&pcm, 48_000, 2, &AudioProcessing::default()).unwrap();
+        assert_eq!(samples.len(), 16_000);
+        // Away from the edges the mono level is the channel average
+        assert!((samples[8_000] - 0.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_load_pcm_picks_channel() {
+        // 1s of 16kHz stereo: left = 0.5, right = -0.1
+        let pcm: Vec<f32> = (0..16_000).flat_map(|_| [0.5, -0.1]).collect();
+        let right = AudioProcessing::new().channels(ChannelMode::Channel(1));
+        assert_eq!(load_pcm(&pcm, 16_000, 2, &right).unwrap(), vec![-0.1; 16_000]);
+
+        let missing = AudioProcessing::new().channels(ChannelMode::Channel(2));
+        assert!(matches!(load_pcm(&pcm, 16_000, 2, &missing), Err(Error::InvalidOption(_))));
+        let per_channel = AudioProcessing::new().channels(ChannelMode::PerChannel);
+        assert!(matches!(load_pcm(&pcm, 16_000, 2, &per_channel), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn test_load_pcm_16k_mono_passthrough() {
+        let pcm: Vec<f32> = (0..1600).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+        let samples = load_pcm(&pcm, WHISPER_SAMPLE_RATE, 1, &AudioProcessing::default()).unwrap();
+        assert_eq!(samples, pcm);
+    }
+
+    #[test]
+    fn test_load_pcm_applies_processing() {
+        let pcm = vec![0.25; 16_000];
+        let samples = load_pcm(&pcm, 16_000, 1, &AudioProcessing::new().normalize(true)).unwrap();
+        assert!((samples[100] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_load_pcm_rejects_bad_layout() {
+        let ap = AudioProcessing::default();
+        assert!(matches!(load_pcm(&[0.0; 10], 0, 1, &ap), Err(Error::InvalidOption(_))));
+        assert!(matches!(load_pcm(&[0.0; 10], 16_000, 0, &ap), Err(Error::InvalidOption(_))));
+        assert!(matches!(load_pcm(&[0.0; 11], 16_000, 2, &ap), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn test_pcm_converter_split_frames() {
+        // Stereo frames split across pushes at odd offsets
+        let pcm: Vec<f32> = (0..100).flat_map(|i| [i as f32, -(i as f32) + 0.5]).collect();
+        let mut converter = PcmConverter::new(16_000, 2).unwrap();
+        let mut out = Vec::new();
+        for chunk in pcm.chunks(7) {
+            converter.push(chunk, &mut out);
+        }
+        converter.finish(&mut out);
+        assert_eq!(out, vec![0.25; 100]);
+    }
+
+    #[test]
+    fn test_downmix_stereo() {
+        let mut out = Vec::new();
+        downmix_into(&[1.0, 0.0, 0.5, 0.5, -1.0, 1.0], 2, &mut out);
+        assert_eq!(out, vec![0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_downmix_mono_passthrough() {
+        let mut out = Vec::new();
+        downmix_into(&[0.1, 0.2], 1, &mut out);
+        assert_eq!(out, vec![0.1, 0.2]);
+    }
+
+    // --- DC offset removal tests ---
+
+    #[test]
+    fn test_dc_offset_removal() {
+        let mut samples = vec![0.5, 0.6, 0.4, 0.5, 0.7, 0.3];
+        let mean_before: f32 = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean_before.abs() > 0.1);
+
+        remove_dc_offset(&mut samples);
+
+        let mean_after: f32 = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean_after.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_dc_offset_removal_empty() {
+        let mut samples: Vec<f32> = vec![];
+        remove_dc_offset(&mut samples); // should not panic
+    }
+
+    #[test]
+    fn test_dc_offset_removal_zero_mean() {
+        let mut samples = vec![-0.5, 0.5, -0.5, 0.5];
+        let original = samples.clone();
+        remove_dc_offset(&mut samples);
+        // Mean is already ~0, samples should be unchanged
+        for (a, b) in samples.iter().zip(original.iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    // --- Peak normalization tests ---
+
+    #[test]
+    fn test_normalize_peak() {
+        let mut samples = vec![0.1, -0.2, 0.3, -0.15, 0.25];
+        normalize_peak(&mut samples);
+
+        let peak = samples.iter().copied().map(f32::abs).fold(0.0f32, f32::max);
+        assert!((peak - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_normalize_already_normalized() {
+        let mut samples = vec![0.5, -1.0, 0.7, -0.3];
+        let original = samples.clone();
+        normalize_peak(&mut samples);
+        // Peak is already 1.0, should be unchanged
+        for (a, b) in samples.iter().zip(original.iter()) {
+            assert!((a - b).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_normalize_silent() {
+        let mut samples = vec![0.0, 0.0, 0.0];
+        normalize_peak(&mut samples); // should not panic or divide by zero
+        assert!(samples.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_normalize_empty() {
+        let mut samples: Vec<f32> = vec![];
+        normalize_peak(&mut samples); // should not panic
+    }
+
+    // --- Silence trimming tests ---
+
+    #[test]
+    fn test_trim_silence_leading() {
+        // 1 second of silence + 1 second of signal
+        let mut samples = vec![0.0; 16_000];
+        samples.extend(vec![0.5; 16_000]);
+
+        let trimmed = trim_silence(&samples, -40.0, 50);
+        assert!(trimmed.len() < samples.len());
+        // Should have trimmed most of the leading silence
+        assert!(trimmed.len() < 18_000);
+    }
+
+    #[test]
+    fn test_trim_silence_trailing() {
+        // 1 second of signal + 1 second of silence
+        let mut samples = vec![0.5; 16_000];
+        samples.extend(vec![0.0; 16_000]);
+
+        let trimmed = trim_silence(&samples, -40.0, 50);
+        assert!(trimmed.len() < samples.len());
+        assert!(trimmed.len() < 18_000);
+    }
+
+    #[test]
+    fn test_trim_silence_both_sides() {
+        // silence + signal + silence
+        let mut samples = vec![0.0; 16_000];
+        samples.extend(vec![0.5; 16_000]);
+        samples.extend(vec![0.0; 16_000]);
+
+        let trimmed = trim_silence(&samples, -40.0, 50);
+        assert!(trimmed.len() < samples.len());
+        // Should be roughly 1 second of signal + padding
+        assert!(trimmed.len() < 20_000);
+    }
+
+    #[test]
+    fn test_trim_silence_no_silence() {
+        let samples = vec![0.5; 16_000];
+        let trimmed = trim_silence(&samples, -40.0, 50);
+        assert_eq!(trimmed.len(), samples.len());
+    }
+
+    #[test]
+    fn test_trim_silence_all_silent() {
+        let samples = vec![0.0; 16_000];
+        let trimmed = trim_silence(&samples, -40.0, 50);
+        // Should return original since there's no active audio
+        assert_eq!(trimmed.len(), samples.len());
+    }
+
+    #[test]
+    fn test_trim_silence_empty() {
+        let trimmed = trim_silence(&[], -40.0, 50);
+        assert!(trimmed.is_empty());
+    }
+
+    // --- Audio processing integration ---
+
+    #[test]
+    fn test_load_with_all_processing() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let samples = load_audio(&path, &AudioProcessing::all()).unwrap();
+        assert!(!samples.is_empty());
+
+        let peak = samples.iter().copied().map(f32::abs).fold(0.0f32, f32::max);
+        assert!((peak - 1.0).abs() < 0.01, "should be normalized, peak={peak}");
+    }
+
+    #[test]
+    fn test_load_with_no_processing() {
+        let path = fixtures_dir().join("sine_440hz_2s.wav");
+        let samples = load_audio(&path, &AudioProcessing::default()).unwrap();
+        assert!(!samples.is_empty());
+    }
+
+    // --- Helper function tests ---
+
+    #[test]
+    fn test_rms() {
+        assert_eq!(rms(&[]), 0.0);
+        assert!((rms(&[1.0, 1.0, 1.0]) - 1.0).abs() < 1e-6);
+        assert!((rms(&[0.0, 0.0, 0.0])).abs() < 1e-6);
+
+        // RMS of [1, -1, 1, -1] = 1.0
+        assert!((rms(&[1.0, -1.0, 1.0, -1.0]) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_db_to_linear() {
+        assert!((db_to_linear(0.0) - 1.0).abs() < 1e-6);
+        assert!((db_to_linear(-20.0) - 0.1).abs() < 1e-6);
+        assert!((db_to_linear(-40.0) - 0.01).abs() < 1e-6);
+        assert!((db_to_linear(-60.0) - 0.001).abs() < 1e-5);
+    }
+
+    // --- Security tests ---
+
+    #[test]
+    fn test_load_rejects_nonexistent() {
+        let result = load_audio(
+            &PathBuf::from("/nonexistent/../../etc/passwd"),
+            &AudioProcessing::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_non_audio_file() {
+        // Try to load a text file — ffmpeg should fail
+        let tmp = std::env::temp_dir().join("transcriber_test_not_audio.txt");
+        std::fs::write(&tmp, "this is not audio").unwrap();
+        let result = load_audio(&tmp, &AudioProcessing::default());
+        assert!(result.is_err());
+        std::fs::remove_file(&tmp).ok();
+    }
+}
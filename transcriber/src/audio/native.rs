@@ -0,0 +1,266 @@
+//! In-process audio decoding via symphonia (no ffmpeg required).
+//!
+//! Handles WAV, FLAC, MP3, Ogg (Vorbis and Opus), MKV/WebM, MP4/M4A (AAC, ALAC).
+//! Opus packets are decoded with the pure-Rust `opus-decoder` crate, since
+//! symphonia has no Opus codec of its own.
+
+use std::fs::File;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+use opus_decoder::OpusDecoder;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::debug;
+
+use super::resample::Resampler;
+use super::{downmix_into, WHISPER_SAMPLE_RATE};
+use crate::error::{Error, Result};
+
+/// Maximum Opus frame length (120 ms) at 16 kHz.
+const OPUS_MAX_FRAME_16K: usize = 1_920;
+
+/// Decode an audio file to 16kHz mono f32.
+///
+/// Returns `Ok(None)` when the container or codec is not supported in-process,
+/// so the caller can fall back to ffmpeg. Files longer than `max_duration_secs`
+/// (as declared by the container) are rejected before decoding.
+pub fn decode(path: &Path, max_duration_secs: f64) -> Result<Option<Vec<f32>>> {
+    let file = File::open(path)?;
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    decode_source(Box::new(file), &hint, max_duration_secs)
+}
+
+/// Decode an in-memory encoded file (container format is sniffed).
+pub fn decode_bytes(data: Arc<[u8]>, max_duration_secs: f64) -> Result<Option<Vec<f32>>> {
+    decode_source(Box::new(Cursor::new(data)), &Hint::new(), max_duration_secs)
+}
+
+fn decode_source(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    max_duration_secs: f64,
+) -> Result<Option<Vec<f32>>> {
+    let mss = MediaSourceStream::new(source, Default::default());
+
+    let probed = match symphonia::default::get_probe().format(
+        hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) {
+        Ok(p) => p,
+        Err(e) => {
+            debug!(error = %e, "symphonia cannot probe input");
+            return Ok(None);
+        }
+    };
+    let mut format = probed.format;
+
+    let Some(track) = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+    else {
+        return Ok(None);
+    };
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    if let (Some(n_frames), Some(rate)) = (params.n_frames, params.sample_rate) {
+        let duration = n_frames as f64 / rate as f64;
+        if duration > max_duration_secs {
+            return Err(Error::AudioDecode(format!(
+                "audio too long ({:.0}s) — maximum supported duration is {:.0}s",
+                duration, max_duration_secs
+            )));
+        }
+    }
+
+    let max_samples = (max_duration_secs * WHISPER_SAMPLE_RATE as f64) as usize;
+
+    if params.codec == CODEC_TYPE_OPUS {
+        decode_opus(format.as_mut(), track_id, &params, max_samples)
+    } else {
+        decode_symphonia(format.as_mut(), track_id, &params, max_samples)
+    }
+}
+
+/// Decode a track with one of symphonia's built-in codecs.
+fn decode_symphonia(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+    max_samples: usize,
+) -> Result<Option<Vec<f32>>> {
+    let mut decoder = match symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+    {
+        Ok(d) => d,
+        Err(e) => {
+            debug!(error = %e, "symphonia has no decoder for this codec");
+            return Ok(None);
+        }
+    };
+
+    let mut samples = Vec::new();
+    let mut mono = Vec::new();
+    let mut resampler: Option<Resampler> = None;
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    while let Some(packet) = next_packet(format)? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(d) => d,
+            // Corrupt frames are skipped, the same as ffmpeg does
+            Err(SymphoniaError::DecodeError(e)) => {
+                debug!(error = e, "skipping undecodable packet");
+                continue;
+            }
+            Err(e) => return Err(Error::AudioDecode(format!("decoding failed: {e}"))),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let buf = match &mut sample_buf {
+            Some(b) if b.capacity() >= decoded.capacity() * channels => b,
+            slot => slot.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buf.copy_interleaved_ref(decoded);
+
+        mono.clear();
+        downmix_into(buf.samples(), channels, &mut mono);
+
+        let resampler = resampler.get_or_insert_with(|| Resampler::new(spec.rate, WHISPER_SAMPLE_RATE));
+        resampler.process(&mono, &mut samples);
+
+        check_len(&samples, max_samples)?;
+    }
+
+    if let Some(mut r) = resampler {
+        r.finish(&mut samples);
+    }
+
+    if samples.is_empty() {
+        return Err(Error::AudioDecode("decoder produced no output".into()));
+    }
+
+    Ok(Some(samples))
+}
+
+/// Decode an Opus track. Opus decodes natively at 16 kHz, so no resampling
+/// is needed — only the encoder pre-skip has to be dropped.
+fn decode_opus(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+    max_samples: usize,
+) -> Result<Option<Vec<f32>>> {
+    // OpusHead: channel count at byte 9, mapping family at byte 18.
+    // Multistream layouts (surround) aren't supported by the single-stream decoder.
+    let head = params.extra_data.as_deref().unwrap_or_default();
+    let channel_count = head.get(9).copied().unwrap_or(1);
+    let mapping_family = head.get(18).copied().unwrap_or(0);
+    if channel_count > 2 || mapping_family > 1 {
+        debug!(channel_count, mapping_family, "multistream opus, deferring to ffmpeg");
+        return Ok(None);
+    }
+
+    // Decoding straight to mono lets the decoder downmix stereo streams
+    let mut decoder = OpusDecoder::new(WHISPER_SAMPLE_RATE, 1)
+        .map_err(|e| Error::AudioDecode(format!("failed to create opus decoder: {e}")))?;
+
+    // Pre-skip is given in 48 kHz samples
+    let mut to_skip = params.delay.unwrap_or(0) as usize * WHISPER_SAMPLE_RATE as usize / 48_000;
+
+    let mut samples = Vec::new();
+    let mut frame = [0.0f32; OPUS_MAX_FRAME_16K];
+
+    while let Some(packet) = next_packet(format)? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let n = match decoder.decode_float(&packet.data, &mut frame, false) {
+            Ok(n) => n,
+            Err(e) => {
+                debug!(error = %e, "skipping undecodable opus packet");
+                continue;
+            }
+        };
+
+        let skip = to_skip.min(n);
+        to_skip -= skip;
+        samples.extend_from_slice(&frame[skip..n]);
+
+        check_len(&samples, max_samples)?;
+    }
+
+    if samples.is_empty() {
+        return Err(Error::AudioDecode("decoder produced no output".into()));
+    }
+
+    Ok(Some(samples))
+}
+
+/// Read the next packet, mapping end-of-stream to `None`.
+fn next_packet(format: &mut dyn FormatReader) -> Result<Option<symphonia::core::formats::Packet>> {
+    match format.next_packet() {
+        Ok(p) => Ok(Some(p)),
+        Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Ok(None)
+        }
+        Err(SymphoniaError::ResetRequired) => Ok(None),
+        Err(e) => Err(Error::AudioDecode(format!("failed to read packet: {e}"))),
+    }
+}
+
+fn check_len(samples: &[f32], max_samples: usize) -> Result<()> {
+    if samples.len() > max_samples {
+        return Err(Error::AudioDecode(format!(
+            "decoded audio too large ({} samples, max {})",
+            samples.len(),
+            max_samples
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_unsupported_returns_none() {
+        let tmp = std::env::temp_dir().join("transcriber_test_native_not_audio.txt");
+        std::fs::write(&tmp, "this is not audio").unwrap();
+        let result = decode(&tmp, 3600.0).unwrap();
+        assert!(result.is_none());
+        std::fs::remove_file(&tmp).ok();
+    }
+
+    #[test]
+    fn test_decode_bytes_matches_file() {
+        let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/sine_48khz_1s.flac");
+        let from_file = decode(&path, 3600.0).unwrap().unwrap();
+        let from_bytes = decode_bytes(std::fs::read(&path).unwrap().into(), 3600.0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(from_file, from_bytes);
+    }
+}
@@ -0,0 +1,582 @@
+//! Inspecting and cleaning up the model cache.
+//!
+//! Besides the model files, the cache directory holds `.index.json`, which
+//! records when each model was last used and the result of its last checksum
+//! check. Files the index doesn't know about (downloaded before it existed or
+//! copied in by hand) are still listed; their last use defaults to the time
+//! they were written.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use fs4::fs_std::FileExt;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::config::Model;
+use crate::error::{Error, Result};
+use crate::model;
+
+const INDEX_FILE: &str = ".index.json";
+const INDEX_LOCK: &str = ".index.lock";
+
+/// What a cached model file is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    /// A whisper ggml model (`.bin`).
+    Whisper,
+    /// A speaker embedding model (`.onnx`).
+    Speaker,
+}
+
+/// Result of the last checksum check of a cached file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// Matched the expected digest, and the file hasn't changed since.
+    Verified,
+    /// Did not match the expected digest.
+    Mismatch,
+    /// Not checked since the file was last written.
+    Unverified,
+    /// No expected digest is known for this file.
+    NoChecksum,
+}
+
+impl ChecksumStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumStatus::Verified => "verified",
+            ChecksumStatus::Mismatch => "MISMATCH",
+            ChecksumStatus::Unverified => "unverified",
+            ChecksumStatus::NoChecksum => "no checksum",
+        }
+    }
+}
+
+/// A model file in the cache.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub file_name: String,
+    /// The built-in model this file belongs to, if it is one.
+    pub model: Option<Model>,
+    pub kind: ModelKind,
+    pub size: u64,
+    /// When the model was last loaded, or when the file was written if that
+    /// was never recorded.
+    pub last_used: SystemTime,
+    pub checksum: ChecksumStatus,
+}
+
+impl CacheEntry {
+    /// Model name for built-in models, the file name otherwise.
+    pub fn display_name(&self) -> String {
+        self.model
+            .as_ref()
+            .map(|m| m.name().into_owned())
+            .unwrap_or_else(|| self.file_name.clone())
+    }
+}
+
+/// List the model files in `cache_dir`, sorted by file name. A missing
+/// directory is an empty cache.
+pub fn list(cache_dir: &Path) -> Result<Vec<CacheEntry>> {
+    let entries = match std::fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let index = read_index(cache_dir);
+
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        let kind = match path.extension().and_then(|e| e.to_str()) {
+            _ if file_name.starts_with('.') => continue,
+            Some("bin") => ModelKind::Whisper,
+            Some("onnx") => ModelKind::Speaker,
+            _ => continue,
+        };
+        let meta = entry.metadata()?;
+        if !meta.is_file() {
+            continue;
+        }
+
+        let record = index.files.get(&file_name);
+        let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+        let last_used = record
+            .and_then(|r| r.last_used)
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap_or(modified);
+        let checksum = checksum_status(
+            model::known_sha256_for_file(&file_name),
+            record.and_then(|r| r.check.as_ref()),
+            meta.len(),
+            unix_secs(modified),
+        );
+
+        out.push(CacheEntry {
+            model: Model::available()
+                .into_iter()
+                .find(|m| m.filename() == file_name),
+            path,
+            file_name,
+            kind,
+            size: meta.len(),
+            last_used,
+            checksum,
+        });
+    }
+    out.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(out)
+}
+
+/// Find a cached model by model name (`small-q5_1`) or file name.
+pub fn find(cache_dir: &Path, name: &str) -> Result<CacheEntry> {
+    let file_name = Model::parse_name(name)
+        .map(|m| m.filename())
+        .unwrap_or_else(|| name.to_string());
+    list(cache_dir)?
+        .into_iter()
+        .find(|e| e.file_name == file_name)
+        .ok_or_else(|| Error::ModelNotFound {
+            path: cache_dir.join(file_name),
+        })
+}
+
+/// Hash a cached file and record the result. Files without a known digest
+/// are not hashed and report [`ChecksumStatus::NoChecksum`].
+pub fn verify(entry: &CacheEntry) -> Result<ChecksumStatus> {
+    let Some(expected) = model::known_sha256_for_file(&entry.file_name) else {
+        return Ok(ChecksumStatus::NoChecksum);
+    };
+    let actual = model::sha256_file(&entry.path)?;
+    write_check(&entry.path, &actual, expected);
+    Ok(if actual.eq_ignore_ascii_case(expected) {
+        ChecksumStatus::Verified
+    } else {
+        ChecksumStatus::Mismatch
+    })
+}
+
+/// Delete a cached model along with any partial download of it. Returns the
+/// number of bytes freed.
+pub fn remove(entry: &CacheEntry) -> Result<u64> {
+    std::fs::remove_file(&entry.path)?;
+    let mut freed = entry.size;
+    for suffix in [".part", ".part.json"] {
+        let mut name = entry.path.as_os_str().to_os_string();
+        name.push(suffix);
+        if let Ok(meta) = std::fs::metadata(&name) {
+            if std::fs::remove_file(&name).is_ok() {
+                freed += meta.len();
+            }
+        }
+    }
+    if let Some(dir) = entry.path.parent() {
+        update_index(dir, |index| {
+            index.files.remove(&entry.file_name);
+        });
+    }
+    Ok(freed)
+}
+
+/// Delete least recently used models until the cache holds at most
+/// `max_bytes`. Returns the removed entries, oldest first.
+pub fn prune(cache_dir: &Path, max_bytes: u64) -> Result<Vec<CacheEntry>> {
+    let mut entries = list(cache_dir)?;
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+    entries.sort_by_key(|e| e.last_used);
+
+    let mut removed = Vec::new();
+    for entry in entries {
+        if total <= max_bytes {
+            break;
+        }
+        remove(&entry)?;
+        total -= entry.size;
+        removed.push(entry);
+    }
+    Ok(removed)
+}
+
+/// Delete partial downloads (`*.part`, `*.part.json` and the `*.part.<pid>`
+/// files of older versions) and download locks (`*.lock`) that no running
+/// download is working on. Returns the removed files with their sizes.
+pub fn clean_partial(cache_dir: &Path) -> Result<Vec<(PathBuf, u64)>> {
+    let entries = match std::fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut removed = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if file_name.ends_with(".lock") && file_name != INDEX_LOCK {
+            if remove_stale_lock(&entry.path()) {
+                removed.push((entry.path(), 0));
+            }
+            continue;
+        }
+        let Some(target) = partial_target(&file_name) else {
+            continue;
+        };
+        if is_downloading(&cache_dir.join(format!("{target}.lock"))) {
+            debug!(file = %file_name, "download in progress, keeping partial file");
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        std::fs::remove_file(entry.path())?;
+        removed.push((entry.path(), size));
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// The model file name a partial download file belongs to: `<target>.part`,
+/// `<target>.part.json` or `<target>.part.<pid>`.
+fn partial_target(file_name: &str) -> Option<&str> {
+    if let Some(target) = file_name
+        .strip_suffix(".part")
+        .or_else(|| file_name.strip_suffix(".part.json"))
+    {
+        return Some(target);
+    }
+    let (target, pid) = file_name.rsplit_once(".part.")?;
+    (!pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit())).then_some(target)
+}
+
+/// Delete the download lock at `lock_path` unless a download holds it.
+fn remove_stale_lock(lock_path: &Path) -> bool {
+    let Ok(file) = std::fs::OpenOptions::new().write(true).open(lock_path) else {
+        return false;
+    };
+    if !file.try_lock_exclusive().unwrap_or(false) {
+        debug!(file = %lock_path.display(), "download in progress, keeping lock file");
+        return false;
+    }
+    // Deleted while locked, so a process that opened it meanwhile retries
+    std::fs::remove_file(lock_path).is_ok()
+}
+
+/// Whether another process holds the download lock at `lock_path`.
+fn is_downloading(lock_path: &Path) -> bool {
+    let Ok(file) = std::fs::OpenOptions::new().write(true).open(lock_path) else {
+        return false;
+    };
+    !file.try_lock_exclusive().unwrap_or(true)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default)]
+    files: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Unix time of the last load.
+    #[serde(default)]
+    last_used: Option<u64>,
+    #[serde(default)]
+    check: Option<Check>,
+}
+
+/// Digest of the file as it was (size and modification time) when hashed.
+#[derive(Debug, Serialize, Deserialize)]
+struct Check {
+    size: u64,
+    modified: u64,
+    sha256: String,
+    /// The digest it was checked against (`--model-sha256`, or the built-in
+    /// one). Indexes written before this was recorded fall back to the
+    /// built-in digest.
+    #[serde(default)]
+    expected: Option<String>,
+}
+
+fn checksum_status(
+    known: Option<&str>,
+    check: Option<&Check>,
+    size: u64,
+    modified: u64,
+) -> ChecksumStatus {
+    let expected = check.and_then(|c| c.expected.as_deref()).or(known);
+    let Some(expected) = expected else {
+        return ChecksumStatus::NoChecksum;
+    };
+    match check {
+        Some(c) if c.size == size && c.modified == modified => {
+            if c.sha256.eq_ignore_ascii_case(expected) {
+                ChecksumStatus::Verified
+            } else {
+                ChecksumStatus::Mismatch
+            }
+        }
+        _ => ChecksumStatus::Unverified,
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn read_index(cache_dir: &Path) -> Index {
+    std::fs::read(cache_dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+/// Read-modify-write the index under an exclusive lock. Failures are only
+/// logged: bookkeeping must never break a transcription.
+fn update_index(cache_dir: &Path, f: impl FnOnce(&mut Index)) {
+    let result = (|| -> Result<()> {
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(cache_dir.join(INDEX_LOCK))?;
+        lock.lock_exclusive()?;
+
+        let mut index = read_index(cache_dir);
+        f(&mut index);
+        let tmp = cache_dir.join(format!("{INDEX_FILE}.{}", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&index)?)?;
+        std::fs::rename(&tmp, cache_dir.join(INDEX_FILE))?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        debug!(dir = %cache_dir.display(), error = %e, "could not update cache index");
+    }
+}
+
+/// Run index bookkeeping on a blocking thread: it waits for the index lock
+/// and rewrites the file, which must not stall the async runtime.
+async fn in_background(f: impl FnOnce() + Send + 'static) {
+    if let Err(e) = tokio::task::spawn_blocking(f).await {
+        debug!(error = %e, "cache index task failed");
+    }
+}
+
+/// Note that the model at `path` was just used.
+pub(crate) async fn record_use(path: &Path) {
+    let path = path.to_path_buf();
+    in_background(move || write_use(&path)).await;
+}
+
+fn write_use(path: &Path) {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    let name = name.to_string_lossy().into_owned();
+    let now = unix_secs(SystemTime::now());
+    update_index(dir, |index| {
+        index.files.entry(name).or_default().last_used = Some(now);
+    });
+}
+
+/// Record the digest of the model at `path` as it is now, and the digest it
+/// was checked against.
+pub(crate) async fn record_check(path: &Path, sha256: &str, expected: &str) {
+    let (path, sha256, expected) = (path.to_path_buf(), sha256.to_string(), expected.to_string());
+    in_background(move || write_check(&path, &sha256, &expected)).await;
+}
+
+fn write_check(path: &Path, sha256: &str, expected: &str) {
+    let (Some(dir), Some(name), Ok(meta)) =
+        (path.parent(), path.file_name(), std::fs::metadata(path))
+    else {
+        return;
+    };
+    let check = Check {
+        size: meta.len(),
+        modified: unix_secs(meta.modified().unwrap_or(UNIX_EPOCH)),
+        sha256: sha256.to_ascii_lowercase(),
+        expected: Some(expected.to_ascii_lowercase()),
+    };
+    let name = name.to_string_lossy().into_owned();
+    update_index(dir, |index| {
+        index.files.entry(name).or_default().check = Some(check);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_cache(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("transcriber_test_cache_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_list_classifies_files() {
+        let dir = temp_cache("list");
+        fs::write(dir.join("ggml-small-q5_1.bin"), b"whisper").unwrap();
+        fs::write(dir.join("my-finetune.bin"), b"custom").unwrap();
+        fs::write(dir.join("wespeaker_en_voxceleb_CAM++.onnx"), b"speaker").unwrap();
+        fs::write(dir.join("ggml-base.bin.part"), b"partial").unwrap();
+        fs::write(dir.join("ggml-base.bin.lock"), b"").unwrap();
+
+        let entries = list(&dir).unwrap();
+        let names: Vec<String> = entries.iter().map(CacheEntry::display_name).collect();
+        assert_eq!(names, vec!["small-q5_1", "my-finetune.bin", "wespeaker_en_voxceleb_CAM++.onnx"]);
+        assert_eq!(entries[0].kind, ModelKind::Whisper);
+        assert_eq!(entries[2].kind, ModelKind::Speaker);
+        assert_eq!(entries[0].size, 7);
+        // The table has no digests for quantized files yet, nor for unknown ones
+        assert!(entries.iter().all(|e| e.checksum == ChecksumStatus::NoChecksum));
+
+        assert!(list(&dir.join("missing")).unwrap().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_verify_records_status() {
+        let dir = temp_cache("verify");
+        fs::write(dir.join("ggml-tiny.bin"), b"not really tiny").unwrap();
+
+        let entry = find(&dir, "tiny").unwrap();
+        assert_eq!(entry.checksum, ChecksumStatus::Unverified);
+        assert_eq!(verify(&entry).unwrap(), ChecksumStatus::Mismatch);
+        // The result is remembered until the file changes
+        assert_eq!(find(&dir, "ggml-tiny.bin").unwrap().checksum, ChecksumStatus::Mismatch);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_checksum_status_tracks_file_changes() {
+        let mut check = Check {
+            size: 10,
+            modified: 100,
+            sha256: "ab".repeat(32),
+            expected: None,
+        };
+        let good = "AB".repeat(32);
+        assert_eq!(checksum_status(Some(&good), Some(&check), 10, 100), ChecksumStatus::Verified);
+        assert_eq!(checksum_status(Some(&good), Some(&check), 11, 100), ChecksumStatus::Unverified);
+        assert_eq!(checksum_status(Some(&good), Some(&check), 10, 101), ChecksumStatus::Unverified);
+        assert_eq!(checksum_status(Some(&"cd".repeat(32)), Some(&check), 10, 100), ChecksumStatus::Mismatch);
+        assert_eq!(checksum_status(None, Some(&check), 10, 100), ChecksumStatus::NoChecksum);
+
+        // Checked against a digest passed with --model-sha256
+        check.expected = Some("ab".repeat(32));
+        assert_eq!(checksum_status(Some(&"cd".repeat(32)), Some(&check), 10, 100), ChecksumStatus::Verified);
+        assert_eq!(checksum_status(None, Some(&check), 10, 100), ChecksumStatus::Verified);
+        assert_eq!(checksum_status(None, Some(&check), 11, 100), ChecksumStatus::Unverified);
+    }
+
+    #[test]
+    fn test_prune_removes_least_recently_used() {
+        let dir = temp_cache("prune");
+        for name in ["ggml-tiny.bin", "ggml-base.bin", "ggml-small.bin"] {
+            fs::write(dir.join(name), vec![0u8; 100]).unwrap();
+        }
+        let now = unix_secs(SystemTime::now());
+        update_index(&dir, |index| {
+            for (name, age) in [("ggml-tiny.bin", 10), ("ggml-base.bin", 30), ("ggml-small.bin", 20)] {
+                index.files.entry(name.into()).or_default().last_used = Some(now - age);
+            }
+        });
+
+        let removed = prune(&dir, 150).unwrap();
+        let names: Vec<&str> = removed.iter().map(|e| e.file_name.as_str()).collect();
+        assert_eq!(names, vec!["ggml-base.bin", "ggml-small.bin"]);
+        let left = list(&dir).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].file_name, "ggml-tiny.bin");
+        assert!(!read_index(&dir).files.contains_key("ggml-base.bin"));
+
+        assert!(prune(&dir, 1_000).unwrap().is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_partial_target() {
+        assert_eq!(partial_target("ggml-base.bin.part"), Some("ggml-base.bin"));
+        assert_eq!(partial_target("ggml-base.bin.part.json"), Some("ggml-base.bin"));
+        assert_eq!(partial_target("ggml-base.bin.part.1234"), Some("ggml-base.bin"));
+        assert_eq!(partial_target("ggml-base.part.bin"), None);
+        assert_eq!(partial_target("my.partial-finetune.bin"), None);
+        assert_eq!(partial_target("ggml-base.bin.part."), None);
+    }
+
+    #[test]
+    fn test_checked_against_pinned_digest_is_verified() {
+        let dir = temp_cache("pinned");
+        let path = dir.join("ggml-tiny.bin");
+        fs::write(&path, b"a fine-tuned tiny").unwrap();
+
+        // Checked with --model-sha256 rather than the built-in digest
+        let actual = model::sha256_file(&path).unwrap();
+        write_check(&path, &actual, &actual);
+        assert_eq!(find(&dir, "tiny").unwrap().checksum, ChecksumStatus::Verified);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_record_use_updates_last_used() {
+        let dir = temp_cache("last_used");
+        let path = dir.join("ggml-base.bin");
+        fs::write(&path, b"model").unwrap();
+        let written = find(&dir, "base").unwrap().last_used;
+
+        std::thread::sleep(Duration::from_millis(1100));
+        write_use(&path);
+        assert!(find(&dir, "base").unwrap().last_used > written);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_clean_partial_skips_active_downloads() {
+        let dir = temp_cache("clean");
+        fs::write(dir.join("ggml-base.bin.part"), vec![0u8; 10]).unwrap();
+        fs::write(dir.join("ggml-base.bin.part.json"), b"{}").unwrap();
+        fs::write(dir.join("ggml-tiny.bin.part.1234"), vec![0u8; 5]).unwrap();
+        fs::write(dir.join("ggml-small.bin.part"), vec![0u8; 10]).unwrap();
+        fs::write(dir.join("ggml-small.bin"), b"complete").unwrap();
+        fs::write(dir.join("ggml-base.bin.lock"), b"").unwrap();
+        // Models that merely have ".part" in their name
+        fs::write(dir.join("my.partial-finetune.bin"), b"model").unwrap();
+        fs::write(dir.join("ggml-base.part2.bin"), b"model").unwrap();
+
+        // A download of small is running
+        let lock = fs::File::create(dir.join("ggml-small.bin.lock")).unwrap();
+        lock.lock_exclusive().unwrap();
+
+        let removed = clean_partial(&dir).unwrap();
+        let names: Vec<String> = removed
+            .iter()
+            .map(|(p, _)| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "ggml-base.bin.lock",
+                "ggml-base.bin.part",
+                "ggml-base.bin.part.json",
+                "ggml-tiny.bin.part.1234"
+            ]
+        );
+        assert!(dir.join("ggml-small.bin.part").exists());
+        assert!(dir.join("ggml-small.bin.lock").exists());
+        assert!(dir.join("my.partial-finetune.bin").exists());
+        assert!(dir.join("ggml-base.part2.bin").exists());
+        assert!(dir.join("ggml-small.bin").exists());
+
+        drop(lock);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,201 @@
+//! Per-channel transcription.
+//!
+//! In [`ChannelMode::PerChannel`](crate::ChannelMode::PerChannel) mode every
+//! channel of the input is decoded and transcribed on its own, so the sides
+//! of a call recorded on separate channels stay apart. The per-channel
+//! transcripts are labelled with their channel (and speaker name, if one is
+//! set) and interleaved by time into one transcript.
+
+use crate::config::TranscribeOptions;
+use crate::progress::{Progress, ProgressCallback, Stage};
+use crate::types::{LanguageShare, Transcript};
+
+/// Progress callback for `channel` of `count`: scales its updates into one
+/// fraction for the whole input and adds the `earlier` channels' segments.
+/// The channel's `Done` is held back; it is reported once, after
+/// interleaving.
+pub(crate) fn progress(
+    callback: &ProgressCallback,
+    channel: u16,
+    count: u16,
+    earlier: usize,
+) -> ProgressCallback {
+    let callback = callback.clone();
+    ProgressCallback::new(move |p| {
+        if p.stage == Stage::Done {
+            return;
+        }
+        callback.call(&Progress {
+            fraction: (f32::from(channel) + p.fraction) / f32::from(count),
+            segments: earlier + p.segments,
+            ..p.clone()
+        });
+    })
+}
+
+/// Mark every segment of `transcript` as heard on `channel`, and name its
+/// speaker if the channel has a name.
+pub(crate) fn label(transcript: &mut Transcript, channel: u16, options: &TranscribeOptions) {
+    let speaker = options.channel_speaker(channel);
+    for seg in &mut transcript.segments {
+        seg.channel = Some(channel);
+        if let Some(name) = speaker {
+            seg.speaker_id = Some(name.to_string());
+        }
+    }
+}
+
+/// Merge labelled per-channel transcripts into one, ordered by start time.
+/// Simultaneous segments keep channel order.
+pub(crate) fn interleave(transcripts: Vec<Transcript>, options: &TranscribeOptions) -> Transcript {
+    let duration = transcripts.iter().map(|t| t.duration).fold(0.0, f64::max);
+    // Speech time per detected language, to pick the transcript's language
+    let mut language_time: Vec<(String, f64)> = Vec::new();
+    let mut segments = Vec::new();
+    for transcript in transcripts {
+        let speech: f64 = transcript.segments.iter().map(|s| (s.end - s.start).max(0.0)).sum();
+        match language_time.iter_mut().find(|(l, _)| *l == transcript.language) {
+            Some((_, time)) => *time += speech,
+            None => language_time.push((transcript.language, speech)),
+        }
+        segments.extend(transcript.segments);
+    }
+    // Stable, so segments starting together stay in channel order
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let languages = if options.language_per_window {
+        LanguageShare::from_segments(&segments)
+    } else {
+        Vec::new()
+    };
+    let language = match languages.first() {
+        Some(share) => share.language.clone(),
+        None => language_time
+            .into_iter()
+            .reduce(|best, l| if l.1 > best.1 { l } else { best })
+            .map_or_else(|| "unknown".into(), |(language, _)| language),
+    };
+    Transcript {
+        segments,
+        language,
+        duration,
+        model: options.model.name().to_string(),
+        source_url: None,
+        source_title: None,
+        speaker_summary: None,
+        languages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Segment;
+
+    fn seg(text: &str, start: f64, end: f64) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+            speaker_turn: false,
+            no_speech_probability: 0.0,
+            words: None,
+            speaker_id: None,
+            speaker_confidence: None,
+            language: None,
+            channel: None,
+        }
+    }
+
+    fn transcript(language: &str, duration: f64, segments: Vec<Segment>) -> Transcript {
+        Transcript {
+            segments,
+            language: language.to_string(),
+            duration,
+            model: "tiny".to_string(),
+            source_url: None,
+            source_title: None,
+            speaker_summary: None,
+            languages: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_label_names_channels() {
+        let options = TranscribeOptions::new().channel_speakers(["Agent", "Customer"]);
+        let mut left = transcript("en", 5.0, vec![seg("Hello", 0.0, 1.0)]);
+        let mut third = transcript("en", 5.0, vec![seg("Hi", 1.0, 2.0)]);
+        label(&mut left, 0, &options);
+        label(&mut third, 2, &options);
+
+        assert_eq!(left.segments[0].channel, Some(0));
+        assert_eq!(left.segments[0].speaker_id.as_deref(), Some("Agent"));
+        assert_eq!(third.segments[0].channel, Some(2));
+        assert_eq!(third.segments[0].speaker_id, None);
+    }
+
+    #[test]
+    fn test_interleave_orders_by_time() {
+        let options = TranscribeOptions::new();
+        let mut agent = transcript(
+            "en",
+            10.0,
+            vec![seg("How can I help?", 0.0, 2.0), seg("Sure.", 5.0, 6.0)],
+        );
+        let mut customer = transcript(
+            "de",
+            12.0,
+            vec![seg("Hallo", 2.0, 2.5), seg("Danke", 5.0, 5.5), seg("Tschüss", 11.0, 12.0)],
+        );
+        label(&mut agent, 0, &options);
+        label(&mut customer, 1, &options);
+
+        let merged = interleave(vec![agent, customer], &options);
+        let order: Vec<(&str, Option<u16>)> =
+            merged.segments.iter().map(|s| (s.text.as_str(), s.channel)).collect();
+        assert_eq!(
+            order,
+            [
+                ("How can I help?", Some(0)),
+                ("Hallo", Some(1)),
+                ("Sure.", Some(0)),
+                ("Danke", Some(1)),
+                ("Tschüss", Some(1)),
+            ]
+        );
+        assert_eq!(merged.duration, 12.0);
+        // The agent speaks longer (3s against 2s)
+        assert_eq!(merged.language, "en");
+        assert!(merged.languages.is_empty());
+    }
+
+    #[test]
+    fn test_progress_spans_all_channels() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let callback = ProgressCallback::new(move |p| log.lock().unwrap().push(p.clone()));
+        let second = progress(&callback, 1, 2, 3);
+
+        second.call(&Progress { segments: 2, ..Progress::new(Stage::Transcribing, 0.5) });
+        second.call(&Progress::new(Stage::Done, 1.0));
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].stage, Stage::Transcribing);
+        assert_eq!(seen[0].fraction, 0.75);
+        assert_eq!(seen[0].segments, 5);
+    }
+
+    #[test]
+    fn test_interleave_empty_channels() {
+        let options = TranscribeOptions::new();
+        let merged = interleave(
+            vec![transcript("en", 3.0, Vec::new()), transcript("en", 3.0, Vec::new())],
+            &options,
+        );
+        assert!(merged.segments.is_empty());
+        assert_eq!(merged.language, "en");
+        assert_eq!(interleave(Vec::new(), &options).language, "unknown");
+    }
+}
@@ -0,0 +1,402 @@
+//! Long-form transcription in parallel chunks.
+//!
+//! The audio is cut at quiet moments into chunks of roughly
+//! [`ChunkOptions::chunk_secs`], each widened by a little overlap for context.
+//! Worker threads take chunks from a shared queue and transcribe them on their
+//! own whisper state, all sharing one loaded model. The results are shifted to
+//! absolute time and stitched: each segment is kept from the chunk whose own
+//! (non-overlapping) part contains its middle, and a segment repeated across
+//! a border is dropped.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tracing::info;
+use whisper_rs::{WhisperContext, WhisperTokenId};
+
+use crate::audio::{SampleSource, WHISPER_SAMPLE_RATE};
+use crate::config::{ChunkOptions, TranscribeOptions};
+use crate::error::{Error, Result};
+use crate::progress::{Progress, ProgressCallback};
+use crate::transcribe::transcribe_pass;
+use crate::types::{LanguageShare, Segment, Transcript};
+
+/// Frame length for finding the quietest cut point (50 ms).
+const CUT_FRAME: usize = WHISPER_SAMPLE_RATE as usize / 20;
+
+/// Threads per chunk assumed when `n_threads` is not set (whisper's default).
+const DEFAULT_CHUNK_THREADS: usize = 4;
+
+/// One chunk: the part it is responsible for, and the audio it decodes.
+struct Chunk {
+    own: Range<usize>,
+    audio: Range<usize>,
+}
+
+/// Transcribe `audio` in parallel chunks, reading only the chunks being
+/// worked on. Audio no longer than about one chunk is transcribed in a single
+/// pass.
+pub(crate) fn transcribe<A: SampleSource + ?Sized>(
+    ctx: &WhisperContext,
+    audio: &A,
+    prompt: &[WhisperTokenId],
+    options: &TranscribeOptions,
+    chunking: &ChunkOptions,
+) -> Result<Transcript> {
+    chunking.validate()?;
+    let secs = |s: f32| (s * WHISPER_SAMPLE_RATE as f32) as usize;
+    let overlap = secs(chunking.overlap_secs);
+    let cuts = cut_points(audio, secs(chunking.chunk_secs), secs(chunking.search_secs))?;
+    let chunks: Vec<Chunk> = cuts
+        .windows(2)
+        .map(|w| Chunk {
+            own: w[0]..w[1],
+            audio: w[0].saturating_sub(overlap)..(w[1] + overlap).min(audio.len()),
+        })
+        .collect();
+    if chunks.len() <= 1 {
+        let mut state = ctx.create_state()?;
+        return transcribe_pass(&mut state, &audio.read(0..audio.len())?, prompt, options);
+    }
+
+    let threads = options.n_threads.map_or(DEFAULT_CHUNK_THREADS, |n| n as usize);
+    let workers = chunking
+        .workers
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |n| n.get() / threads.max(1))
+        })
+        .clamp(1, chunks.len());
+    info!(chunks = chunks.len(), workers, "transcribing in parallel chunks");
+
+    let duration = audio.len() as f64 / WHISPER_SAMPLE_RATE as f64;
+    let progress = options
+        .progress
+        .clone()
+        .map(|callback| Arc::new(ChunkProgress::new(callback, &chunks, duration)));
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<Result<Transcript>>>> =
+        Mutex::new(chunks.iter().map(|_| None).collect());
+
+    let worker = || {
+        let mut state = match ctx.create_state() {
+            Ok(state) => state,
+            Err(e) => {
+                failed.store(true, Ordering::Relaxed);
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if let Some(slot) = results.lock().unwrap().get_mut(i) {
+                    *slot = Some(Err(e.into()));
+                }
+                return;
+            }
+        };
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= chunks.len() || failed.load(Ordering::Relaxed) {
+                return;
+            }
+            let mut chunk_options = options.clone();
+            chunk_options.progress = progress.as_ref().map(|p| p.callback(i));
+            let result = audio.read(chunks[i].audio.clone()).and_then(|samples| {
+                transcribe_pass(&mut state, &samples, prompt, &chunk_options)
+            });
+            if result.is_err() {
+                failed.store(true, Ordering::Relaxed);
+            }
+            results.lock().unwrap()[i] = Some(result);
+        }
+    };
+    std::thread::scope(|scope| {
+        for n in 0..workers {
+            std::thread::Builder::new()
+                .name(format!("transcriber-chunk-{n}"))
+                .spawn_scoped(scope, worker)?;
+        }
+        Ok::<_, std::io::Error>(())
+    })?;
+
+    options.check_cancelled()?;
+    let mut parts = Vec::with_capacity(chunks.len());
+    for (chunk, result) in chunks.iter().zip(results.into_inner().unwrap()) {
+        match result {
+            Some(Ok(transcript)) => parts.push((chunk, transcript)),
+            Some(Err(e)) => return Err(e),
+            // Only skipped after another chunk failed, which is reported above
+            None => {}
+        }
+    }
+    if parts.len() < chunks.len() {
+        return Err(Error::Transcription("chunked transcription stopped early".into()));
+    }
+
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    let mut language_time: Vec<(String, f64)> = Vec::new();
+    let mut pieces = Vec::with_capacity(parts.len());
+    for (i, (chunk, transcript)) in parts.into_iter().enumerate() {
+        let own_secs = (chunk.own.end - chunk.own.start) as f64 / rate;
+        match language_time.iter_mut().find(|(l, _)| *l == transcript.language) {
+            Some((_, time)) => *time += own_secs,
+            None => language_time.push((transcript.language.clone(), own_secs)),
+        }
+
+        let offset = chunk.audio.start as f64 / rate;
+        let segments: Vec<Segment> = transcript
+            .segments
+            .into_iter()
+            .map(|mut seg| {
+                seg.shift(offset);
+                seg
+            })
+            .collect();
+        // The outer edges of the recording belong to the first and last chunk
+        let start = if i == 0 { f64::NEG_INFINITY } else { chunk.own.start as f64 / rate };
+        let end = if i == chunks.len() - 1 { f64::INFINITY } else { chunk.own.end as f64 / rate };
+        pieces.push((start..end, segments));
+    }
+    let segments = stitch(pieces);
+
+    let languages = if options.language_per_window {
+        LanguageShare::from_segments(&segments)
+    } else {
+        Vec::new()
+    };
+    let language = match languages.first() {
+        Some(share) => share.language.clone(),
+        None => language_time
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or_else(|| "unknown".into(), |(language, _)| language),
+    };
+    Ok(Transcript {
+        segments,
+        language,
+        duration,
+        model: options.model.name().to_string(),
+        source_url: None,
+        source_title: None,
+        speaker_summary: None,
+        languages,
+    })
+}
+
+/// Chunk boundaries (sample indices, starting at 0 and ending at the length):
+/// one about every `chunk` samples, moved to the quietest frame within
+/// `search` samples of that target. A remainder shorter than half a chunk is
+/// left to the last chunk.
+fn cut_points<A: SampleSource + ?Sized>(
+    audio: &A,
+    chunk: usize,
+    search: usize,
+) -> Result<Vec<usize>> {
+    let mut cuts = vec![0];
+    let mut last = 0;
+    while last + chunk + chunk / 2 < audio.len() {
+        let target = last + chunk;
+        let from = target.saturating_sub(search).max(last + 1);
+        let to = (target + search).min(audio.len());
+        last = from + quietest_frame(&audio.read(from..to)?);
+        cuts.push(last);
+    }
+    cuts.push(audio.len());
+    Ok(cuts)
+}
+
+/// Offset of the middle of the frame with the least energy.
+fn quietest_frame(samples: &[f32]) -> usize {
+    samples
+        .chunks(CUT_FRAME)
+        .enumerate()
+        .map(|(i, frame)| {
+            let energy: f32 = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+            (i * CUT_FRAME + frame.len() / 2, energy)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(offset, _)| offset)
+}
+
+/// Join per-chunk segments (in absolute time) into one list. Each chunk keeps
+/// the segments whose middle falls into its own time range; a segment that
+/// repeats the last one kept from the previous chunk, overlapping it in
+/// time, is dropped.
+fn stitch(pieces: Vec<(Range<f64>, Vec<Segment>)>) -> Vec<Segment> {
+    let mut out: Vec<Segment> = Vec::new();
+    for (own, segments) in pieces {
+        let border = out.len();
+        for seg in segments {
+            if !own.contains(&((seg.start + seg.end) / 2.0)) {
+                continue;
+            }
+            let repeated = out.len() == border
+                && out.last().is_some_and(|prev| {
+                    seg.start < prev.end && same_text(&prev.text, &seg.text)
+                });
+            if !repeated {
+                out.push(seg);
+            }
+        }
+    }
+    out
+}
+
+fn same_text(a: &str, b: &str) -> bool {
+    let words = |s: &str| -> Vec<String> {
+        s.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+    words(a) == words(b)
+}
+
+/// Combines the progress of concurrently running chunks into one report.
+struct ChunkProgress {
+    callback: ProgressCallback,
+    /// Length of each chunk's audio, and its latest (fraction, segments).
+    lengths: Vec<f64>,
+    done: Mutex<Vec<(f32, usize)>>,
+    duration: f64,
+}
+
+impl ChunkProgress {
+    fn new(callback: ProgressCallback, chunks: &[Chunk], duration: f64) -> Self {
+        Self {
+            callback,
+            lengths: chunks.iter().map(|c| (c.audio.end - c.audio.start) as f64).collect(),
+            done: Mutex::new(vec![(0.0, 0); chunks.len()]),
+            duration,
+        }
+    }
+
+    /// Progress callback for chunk `i`.
+    fn callback(self: &Arc<Self>, i: usize) -> ProgressCallback {
+        let this = Arc::clone(self);
+        ProgressCallback::new(move |p| this.update(i, p))
+    }
+
+    fn update(&self, i: usize, p: &Progress) {
+        let (fraction, segments) = {
+            let mut done = self.done.lock().unwrap();
+            done[i] = (p.fraction, p.segments);
+            let total: f64 = self.lengths.iter().sum();
+            let covered: f64 = done
+                .iter()
+                .zip(&self.lengths)
+                .map(|((fraction, _), len)| *fraction as f64 * len)
+                .sum();
+            let segments = done.iter().map(|(_, n)| n).sum();
+            ((covered / total) as f32, segments)
+        };
+        self.callback.call(&Progress {
+            stage: p.stage,
+            fraction,
+            segments,
+            position_secs: fraction as f64 * self.duration,
+            duration_secs: Some(self.duration),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg(text: &str, start: f64, end: f64) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+            speaker_turn: false,
+            no_speech_probability: 0.0,
+            words: None,
+            speaker_id: None,
+            speaker_confidence: None,
+            language: None,
+            channel: None,
+        }
+    }
+
+    /// A loud signal with silent gaps at the given sample ranges.
+    fn signal(len: usize, gaps: &[Range<usize>]) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                if gaps.iter().any(|g| g.contains(&i)) {
+                    0.0
+                } else {
+                    0.5 * (i as f32 * 0.3).sin()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cut_points_find_silence() {
+        let samples = signal(100_000, &[(37_000..38_000), (71_000..72_000)]);
+        let cuts = cut_points(&samples, 35_000, 5_000).unwrap();
+        assert_eq!(cuts.len(), 4);
+        assert_eq!((cuts[0], cuts[3]), (0, 100_000));
+        assert!((37_000..38_000).contains(&cuts[1]), "cut at {}", cuts[1]);
+        assert!((71_000..72_000).contains(&cuts[2]), "cut at {}", cuts[2]);
+    }
+
+    #[test]
+    fn test_cut_points_short_audio() {
+        let samples = signal(50_000, &[]);
+        assert_eq!(cut_points(&samples, 35_000, 5_000).unwrap(), vec![0, 50_000]);
+        assert_eq!(cut_points(&[][..], 35_000, 5_000).unwrap(), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_stitch_keeps_segments_by_middle() {
+        let pieces = vec![
+            (
+                f64::NEG_INFINITY..10.0,
+                vec![seg("one", 0.0, 4.0), seg("two", 8.0, 11.0), seg("x", 10.5, 12.0)],
+            ),
+            (10.0..f64::INFINITY, vec![seg("y", 8.0, 9.0), seg("three", 11.5, 14.0)]),
+        ];
+        let texts: Vec<String> = stitch(pieces).into_iter().map(|s| s.text).collect();
+        assert_eq!(texts, vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_stitch_drops_repeat_across_border() {
+        let pieces = vec![
+            (f64::NEG_INFINITY..10.0, vec![seg(" Thank you.", 7.0, 9.8)]),
+            (
+                10.0..f64::INFINITY,
+                vec![seg("thank you", 9.5, 10.6), seg("Next.", 11.0, 12.0)],
+            ),
+        ];
+        let texts: Vec<String> = stitch(pieces).into_iter().map(|s| s.text).collect();
+        assert_eq!(texts, vec![" Thank you.", "Next."]);
+    }
+
+    #[test]
+    fn test_progress_combines_chunks() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let callback = ProgressCallback::new({
+            let seen = Arc::clone(&seen);
+            move |p| seen.lock().unwrap().push(p.clone())
+        });
+        let chunks = [
+            Chunk { own: 0..100, audio: 0..100 },
+            Chunk { own: 100..400, audio: 100..400 },
+        ];
+        let progress = Arc::new(ChunkProgress::new(callback, &chunks, 25.0));
+
+        let update = |fraction, segments| Progress {
+            fraction,
+            segments,
+            ..Progress::new(crate::Stage::Transcribing, 0.0)
+        };
+        progress.callback(0).call(&update(1.0, 3));
+        progress.callback(1).call(&update(0.5, 2));
+
+        let seen = seen.lock().unwrap();
+        let last = seen.last().unwrap();
+        assert_eq!(last.fraction, 0.625);
+        assert_eq!(last.segments, 5);
+        assert_eq!(last.duration_secs, Some(25.0));
+    }
+}
@@ -1,7 +1,11 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::ops::Range;
 use std::path::PathBuf;
 
 use crate::error::Error;
+use crate::model::FetchOptions;
+use crate::progress::{CancellationToken, Progress, ProgressCallback};
 
 /// A validated language for whisper transcription.
 ///
@@ -56,6 +60,14 @@ impl Language {
         }
     }
 
+    /// English name of the language (e.g. "german"), or None for Auto.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Language::Auto => None,
+            Language::Code { id, .. } => whisper_rs::get_lang_str_full(*id),
+        }
+    }
+
     /// Whether this is auto-detection mode.
     pub fn is_auto(&self) -> bool {
         matches!(self, Language::Auto)
@@ -84,6 +96,11 @@ impl fmt::Display for Language {
 }
 
 /// Whisper model sizes.
+///
+/// The size variants are the full-precision whisper.cpp files. Most sizes are
+/// also published with quantized weights, which are smaller and faster on CPU
+/// at a small cost in accuracy; see [`Model::quantized`] or parse names such
+/// as `large-v3-turbo-q5_0`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Model {
     Tiny,
@@ -97,11 +114,172 @@ pub enum Model {
     LargeV2,
     LargeV3,
     LargeV3Turbo,
+    /// distil-whisper's distilled medium.en (English only).
+    DistilMediumEn,
+    /// distil-whisper's distilled large-v2 (English only).
+    DistilLargeV2,
+    /// distil-whisper's distilled large-v3.
+    DistilLargeV3,
+    /// A whisper.cpp model with quantized weights.
+    Quantized(QuantizedModel),
     /// User-provided .ggml file path.
     Custom(PathBuf),
 }
 
+/// Weight format of a ggml model file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Quantization {
+    /// Full precision (16-bit floats).
+    #[default]
+    F16,
+    Q5_0,
+    Q5_1,
+    Q8_0,
+}
+
+impl Quantization {
+    /// Suffix used in model names and file names (`q5_0`, …; empty for f16).
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Quantization::F16 => "",
+            Quantization::Q5_0 => "q5_0",
+            Quantization::Q5_1 => "q5_1",
+            Quantization::Q8_0 => "q8_0",
+        }
+    }
+
+    /// Parse a suffix such as `q5_0`.
+    pub fn parse_name(s: &str) -> Option<Self> {
+        match s {
+            "f16" => Some(Quantization::F16),
+            "q5_0" => Some(Quantization::Q5_0),
+            "q5_1" => Some(Quantization::Q5_1),
+            "q8_0" => Some(Quantization::Q8_0),
+            _ => None,
+        }
+    }
+}
+
+/// A full-precision model together with one of the quantizations
+/// whisper.cpp publishes for it. Built by [`Model::quantized`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantizedModel {
+    base: Box<Model>,
+    quantization: Quantization,
+}
+
+impl QuantizedModel {
+    /// The full-precision model the weights were quantized from.
+    pub fn base(&self) -> &Model {
+        &self.base
+    }
+
+    pub fn quantization(&self) -> Quantization {
+        self.quantization
+    }
+}
+
 impl Model {
+    /// Every model that can be downloaded by name, full-precision sizes first,
+    /// then quantized variants, then distilled models.
+    pub fn available() -> Vec<Model> {
+        let sizes = [
+            Model::Tiny,
+            Model::TinyEn,
+            Model::Base,
+            Model::BaseEn,
+            Model::Small,
+            Model::SmallEn,
+            Model::Medium,
+            Model::MediumEn,
+            Model::LargeV2,
+            Model::LargeV3,
+            Model::LargeV3Turbo,
+        ];
+        let quantized: Vec<Model> = sizes
+            .iter()
+            .flat_map(|m| {
+                m.published_quantizations()
+                    .iter()
+                    .filter_map(|&q| m.clone().quantized(q).ok())
+            })
+            .collect();
+        sizes
+            .into_iter()
+            .chain(quantized)
+            .chain([Model::DistilMediumEn, Model::DistilLargeV2, Model::DistilLargeV3])
+            .collect()
+    }
+
+    /// Quantizations whisper.cpp publishes for this model.
+    fn published_quantizations(&self) -> &'static [Quantization] {
+        use Quantization::*;
+        match self {
+            Model::Tiny | Model::Base | Model::Small => &[Q5_1, Q8_0],
+            Model::TinyEn | Model::BaseEn | Model::SmallEn => &[Q5_1],
+            Model::Medium | Model::LargeV2 | Model::LargeV3Turbo => &[Q5_0, Q8_0],
+            Model::MediumEn | Model::LargeV3 => &[Q5_0],
+            _ => &[],
+        }
+    }
+
+    /// This model with `quantization` applied to its weights. Fails if
+    /// whisper.cpp doesn't publish that combination. `F16` gives back the
+    /// full-precision model.
+    pub fn quantized(self, quantization: Quantization) -> Result<Self, Error> {
+        let base = match self {
+            Model::Quantized(q) => *q.base,
+            other => other,
+        };
+        if quantization == Quantization::F16 {
+            return Ok(base);
+        }
+        if !base.published_quantizations().contains(&quantization) {
+            return Err(Error::InvalidOption(format!(
+                "no {} variant of model \"{}\"",
+                quantization.suffix(),
+                base.name()
+            )));
+        }
+        Ok(Model::Quantized(QuantizedModel {
+            base: Box::new(base),
+            quantization,
+        }))
+    }
+
+    /// Weight format of this model (`F16` for full-precision and custom files).
+    pub fn quantization(&self) -> Quantization {
+        match self {
+            Model::Quantized(q) => q.quantization,
+            _ => Quantization::F16,
+        }
+    }
+
+    /// Approximate download size in megabytes (`None` for custom files).
+    pub fn size_mb(&self) -> Option<u32> {
+        let mb = match self {
+            Model::Tiny | Model::TinyEn => 75,
+            Model::Base | Model::BaseEn => 142,
+            Model::Small | Model::SmallEn => 466,
+            Model::Medium | Model::MediumEn => 1_500,
+            Model::LargeV2 | Model::LargeV3 => 2_900,
+            Model::LargeV3Turbo => 1_600,
+            Model::DistilMediumEn => 790,
+            Model::DistilLargeV2 | Model::DistilLargeV3 => 1_500,
+            // Relative to f16: roughly 0.36x for q5 and 0.53x for q8_0
+            Model::Quantized(q) => {
+                let base = q.base.size_mb()?;
+                return Some(match q.quantization {
+                    Quantization::F16 => base,
+                    Quantization::Q5_0 | Quantization::Q5_1 => base * 36 / 100,
+                    Quantization::Q8_0 => base * 53 / 100,
+                });
+            }
+            Model::Custom(_) => return None,
+        };
+        Some(mb)
+    }
+
     /// Model filename as used by HuggingFace / whisper.cpp.
     pub fn filename(&self) -> String {
         match self {
@@ -116,6 +294,12 @@ impl Model {
             Model::LargeV2 => "ggml-large-v2.bin".into(),
             Model::LargeV3 => "ggml-large-v3.bin".into(),
             Model::LargeV3Turbo => "ggml-large-v3-turbo.bin".into(),
+            Model::DistilMediumEn => "ggml-medium-32-2.en.bin".into(),
+            Model::DistilLargeV2 => "ggml-large-32-2.en.bin".into(),
+            Model::DistilLargeV3 => "ggml-distil-large-v3.bin".into(),
+            Model::Quantized(q) => {
+                format!("ggml-{}-{}.bin", q.base.name(), q.quantization.suffix())
+            }
             Model::Custom(path) => path
                 .file_name()
                 .map(|f| f.to_string_lossy().into_owned())
@@ -123,9 +307,10 @@ impl Model {
         }
     }
 
-    /// Human-readable name.
-    pub fn name(&self) -> &str {
-        match self {
+    /// Human-readable name. Borrowed for the fixed models; quantized
+    /// variants build theirs from the base name and quantization.
+    pub fn name(&self) -> Cow<'static, str> {
+        let name = match self {
             Model::Tiny => "tiny",
             Model::TinyEn => "tiny.en",
             Model::Base => "base",
@@ -137,26 +322,48 @@ impl Model {
             Model::LargeV2 => "large-v2",
             Model::LargeV3 => "large-v3",
             Model::LargeV3Turbo => "large-v3-turbo",
+            Model::DistilMediumEn => "distil-medium.en",
+            Model::DistilLargeV2 => "distil-large-v2",
+            Model::DistilLargeV3 => "distil-large-v3",
+            Model::Quantized(q) => {
+                return format!("{}-{}", q.base.name(), q.quantization.suffix()).into()
+            }
             Model::Custom(_) => "custom",
-        }
+        };
+        Cow::Borrowed(name)
     }
 
-    /// Parse from string (e.g. CLI argument).
+    /// Parse from string (e.g. CLI argument). A quantization suffix selects
+    /// a quantized variant: `small-q8_0`, `large-v3-turbo-q5_0`.
     pub fn parse_name(s: &str) -> Option<Self> {
-        match s {
-            "tiny" => Some(Model::Tiny),
-            "tiny.en" => Some(Model::TinyEn),
-            "base" => Some(Model::Base),
-            "base.en" => Some(Model::BaseEn),
-            "small" => Some(Model::Small),
-            "small.en" => Some(Model::SmallEn),
-            "medium" => Some(Model::Medium),
-            "medium.en" => Some(Model::MediumEn),
-            "large-v2" => Some(Model::LargeV2),
-            "large-v3" => Some(Model::LargeV3),
-            "large-v3-turbo" => Some(Model::LargeV3Turbo),
-            _ => None,
-        }
+        let model = match s {
+            "tiny" => Model::Tiny,
+            "tiny.en" => Model::TinyEn,
+            "base" => Model::Base,
+            "base.en" => Model::BaseEn,
+            "small" => Model::Small,
+            "small.en" => Model::SmallEn,
+            "medium" => Model::Medium,
+            "medium.en" => Model::MediumEn,
+            "large-v2" => Model::LargeV2,
+            "large-v3" => Model::LargeV3,
+            "large-v3-turbo" => Model::LargeV3Turbo,
+            "distil-medium.en" => Model::DistilMediumEn,
+            "distil-large-v2" => Model::DistilLargeV2,
+            "distil-large-v3" => Model::DistilLargeV3,
+            _ => {
+                let (base, suffix) = s.rsplit_once('-')?;
+                let quantization = Quantization::parse_name(suffix)?;
+                if quantization == Quantization::F16 {
+                    return None;
+                }
+                match Model::parse_name(base)? {
+                    Model::Quantized(_) => return None,
+                    base => return base.quantized(quantization).ok(),
+                }
+            }
+        };
+        Some(model)
     }
 }
 
@@ -170,12 +377,244 @@ impl std::str::FromStr for Model {
     }
 }
 
+/// Audio decoding backend.
+///
+/// `Ffmpeg` shells out to the `ffmpeg` binary and supports every format it does.
+/// `Native` decodes in-process with pure-Rust decoders (WAV, FLAC, MP3, Ogg
+/// Vorbis/Opus, WebM/MKV, MP4/M4A AAC/ALAC) and needs no external binaries; it
+/// requires the `native-audio` feature. Files the native backend cannot handle
+/// fall back to ffmpeg automatically.
+///
+/// The native backend skips the ffmpeg [`Conditioning`] chain, so levels are
+/// passed through as recorded. Enable [`AudioProcessing::normalize`] if the
+/// source material is very quiet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AudioBackend {
+    /// Decode via the `ffmpeg` subprocess.
+    #[default]
+    Ffmpeg,
+    /// Decode in-process (requires the `native-audio` feature).
+    Native,
+}
+
+impl AudioBackend {
+    /// Parse a backend name ("ffmpeg" or "native").
+    pub fn parse_name(name: &str) -> Result<Self, Error> {
+        match name.to_lowercase().as_str() {
+            "ffmpeg" => Ok(AudioBackend::Ffmpeg),
+            "native" => Ok(AudioBackend::Native),
+            _ => Err(Error::InvalidOption(format!(
+                "unknown audio backend '{name}' (expected 'ffmpeg' or 'native')"
+            ))),
+        }
+    }
+
+    /// Whether this backend is available in the current build.
+    pub fn is_available(&self) -> bool {
+        match self {
+            AudioBackend::Ffmpeg => true,
+            AudioBackend::Native => cfg!(feature = "native-audio"),
+        }
+    }
+}
+
+impl fmt::Display for AudioBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioBackend::Ffmpeg => write!(f, "ffmpeg"),
+            AudioBackend::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// Speech chain: `compand` boosts quiet passages (soft speakers, distant mic)
+/// while limiting loud peaks, with a 0.3s/0.8s attack/decay tuned for speech;
+/// `loudnorm` normalizes to -16 LUFS (EBU R128), whisper's preferred level.
+const SPEECH_FILTER: &str = "compand=attacks=0.3:decays=0.8:points=-80/-80|-45/-25|-27/-15|0/-10:gain=5,\
+                             loudnorm=I=-16:TP=-1.5:LRA=11";
+
+/// Telephone band (300-3400 Hz) ahead of the speech chain.
+const TELEPHONY_FILTER: &str = "highpass=f=300,lowpass=f=3400,\
+                                compand=attacks=0.3:decays=0.8:points=-80/-80|-45/-25|-27/-15|0/-10:gain=5,\
+                                loudnorm=I=-16:TP=-1.5:LRA=11";
+
+/// Voice band plus a noise-tracking FFT denoiser, which settles on a steady
+/// music bed and pulls it down, ahead of the speech chain.
+const MUSIC_BED_FILTER: &str = "highpass=f=100,lowpass=f=6000,afftdn=nr=15:nf=-40:tn=1,\
+                                compand=attacks=0.3:decays=0.8:points=-80/-80|-45/-25|-27/-15|0/-10:gain=5,\
+                                loudnorm=I=-16:TP=-1.5:LRA=11";
+
+/// Filters a custom chain may use. None of them read or write files, open
+/// devices or sockets, or take inputs other than the decoded audio.
+const CUSTOM_FILTERS: &[&str] = &[
+    "acompressor", "adeclick", "adeclip", "afftdn", "agate", "alimiter", "allpass", "anlmdn",
+    "bandpass", "bandreject", "bass", "compand", "deesser", "dynaudnorm", "equalizer",
+    "highpass", "highshelf", "loudnorm", "lowpass", "lowshelf", "speechnorm", "treble",
+    "volume",
+];
+
+/// Filter chain ffmpeg applies while decoding, before resampling to 16kHz mono.
+///
+/// Only the ffmpeg backend runs it; the native backend and PCM input are taken
+/// as-is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Conditioning {
+    /// No filtering, for clean studio audio where compression only hurts.
+    None,
+    /// Dynamic range compression and loudness normalization, for speakers at
+    /// different distances from the microphone.
+    #[default]
+    Speech,
+    /// Band-pass to the telephone band (300-3400 Hz), then the speech chain.
+    Telephony,
+    /// Attenuate background music under speech, then the speech chain.
+    MusicBedSuppression,
+    /// A raw ffmpeg filter chain (`-af` syntax). Build with
+    /// [`Conditioning::custom`]; the chain is checked again before ffmpeg runs.
+    Custom(String),
+}
+
+impl Conditioning {
+    /// Parse a preset name ("none", "speech", "telephony" or
+    /// "music-bed-suppression").
+    pub fn parse_name(name: &str) -> Result<Self, Error> {
+        match name.to_lowercase().as_str() {
+            "none" => Ok(Conditioning::None),
+            "speech" | "speech-default" => Ok(Conditioning::Speech),
+            "telephony" => Ok(Conditioning::Telephony),
+            "music-bed-suppression" => Ok(Conditioning::MusicBedSuppression),
+            _ => Err(Error::InvalidOption(format!(
+                "unknown conditioning preset '{name}' \
+                 (expected 'none', 'speech', 'telephony' or 'music-bed-suppression')"
+            ))),
+        }
+    }
+
+    /// A custom filter chain such as `highpass=f=80,volume=2`.
+    ///
+    /// The chain is a comma-separated list of `name=option:option...` filters.
+    /// Only a fixed set of level, EQ and denoising filters is accepted (none
+    /// of which can touch files or the network), and options may not contain
+    /// quotes, escapes or graph syntax, so the string can only ever be a
+    /// simple filter chain.
+    pub fn custom(filter: impl Into<String>) -> Result<Self, Error> {
+        let filter = filter.into();
+        validate_filter(&filter)?;
+        Ok(Conditioning::Custom(filter))
+    }
+
+    /// The `-af` argument for ffmpeg, if any.
+    pub(crate) fn filter(&self) -> Result<Option<&str>, Error> {
+        Ok(match self {
+            Conditioning::None => None,
+            Conditioning::Speech => Some(SPEECH_FILTER),
+            Conditioning::Telephony => Some(TELEPHONY_FILTER),
+            Conditioning::MusicBedSuppression => Some(MUSIC_BED_FILTER),
+            Conditioning::Custom(filter) => {
+                validate_filter(filter)?;
+                Some(filter)
+            }
+        })
+    }
+}
+
+impl fmt::Display for Conditioning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conditioning::None => write!(f, "none"),
+            Conditioning::Speech => write!(f, "speech"),
+            Conditioning::Telephony => write!(f, "telephony"),
+            Conditioning::MusicBedSuppression => write!(f, "music-bed-suppression"),
+            Conditioning::Custom(filter) => write!(f, "custom ({filter})"),
+        }
+    }
+}
+
+/// Check that `chain` is a plain chain of allowed filters.
+fn validate_filter(chain: &str) -> Result<(), Error> {
+    let invalid = |reason: String| {
+        Error::InvalidOption(format!("invalid conditioning filter '{chain}': {reason}"))
+    };
+    if chain.trim().is_empty() {
+        return Err(invalid("empty filter chain".into()));
+    }
+    if chain.len() > 1024 {
+        return Err(invalid("longer than 1024 characters".into()));
+    }
+    for filter in chain.split(',') {
+        let (name, options) = filter.split_once('=').unwrap_or((filter, ""));
+        if !CUSTOM_FILTERS.contains(&name) {
+            return Err(invalid(format!(
+                "filter '{name}' is not allowed (allowed: {})",
+                CUSTOM_FILTERS.join(", ")
+            )));
+        }
+        if let Some(c) = options
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || "=:|/.+-_*".contains(*c)))
+        {
+            return Err(invalid(format!("character {c:?} is not allowed in filter options")));
+        }
+    }
+    Ok(())
+}
+
+/// Which channels of a multi-channel input are transcribed.
+///
+/// Picking channels always decodes with ffmpeg (whatever the configured
+/// [`AudioBackend`]); decoded 16kHz mono samples only have channel 0. Live
+/// streams are always mixed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// Mix all channels down to mono.
+    #[default]
+    Mix,
+    /// Transcribe only this channel (0-based).
+    Channel(u16),
+    /// Transcribe every channel on its own, e.g. the agent and customer
+    /// sides of a call, and interleave the segments by time. Segments record
+    /// their channel, and the names set with
+    /// [`TranscribeOptions::channel_speakers`] as their speaker. Progress
+    /// covers all channels, which are transcribed one after another. Cannot
+    /// be combined with speaker identification or diarization.
+    PerChannel,
+}
+
+impl ChannelMode {
+    /// Parse "mix", "per-channel", or a channel number.
+    pub fn parse_name(name: &str) -> Result<Self, Error> {
+        match name.to_lowercase().as_str() {
+            "mix" => Ok(ChannelMode::Mix),
+            "per-channel" => Ok(ChannelMode::PerChannel),
+            other => other.parse().map(ChannelMode::Channel).map_err(|_| {
+                Error::InvalidOption(format!(
+                    "unknown channel mode '{name}' (expected 'mix', 'per-channel' or a channel number)"
+                ))
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelMode::Mix => write!(f, "mix"),
+            ChannelMode::Channel(n) => write!(f, "channel {n}"),
+            ChannelMode::PerChannel => write!(f, "per-channel"),
+        }
+    }
+}
+
 /// Audio processing options.
 ///
 /// By default all processing steps are **off** — the raw decoded/resampled PCM
 /// is passed straight to whisper, which is what the proven brewery pipeline does.
 /// Enable individual steps only when you know the source material needs it
 /// (e.g. recordings with DC bias, wildly varying levels, or long silence padding).
+///
+/// The one exception is ffmpeg's [`conditioning`](Self::conditioning) chain,
+/// which defaults to [`Conditioning::Speech`]; set it to
+/// [`Conditioning::None`] for untouched audio.
 #[derive(Debug, Clone)]
 pub struct AudioProcessing {
     /// Remove DC offset by subtracting the sample mean.
@@ -190,6 +629,17 @@ pub struct AudioProcessing {
     /// Padding in milliseconds to keep around detected speech boundaries.
     /// Prevents clipping speech onset/offset. Only used when `trim_silence` is true.
     pub silence_pad_ms: u32,
+    /// Decoder used to turn the input file into PCM (default: ffmpeg).
+    pub backend: AudioBackend,
+    /// Keep decoded files in a temporary file instead of memory, lifting the
+    /// 8-hour limit (default false). Files are then always decoded with ffmpeg
+    /// and transcribed in chunks (see [`ChunkOptions`]); in-memory inputs are
+    /// unaffected.
+    pub disk_backed: bool,
+    /// Filter chain ffmpeg applies while decoding (default: the speech chain).
+    pub conditioning: Conditioning,
+    /// Channels to transcribe (default: all, mixed to mono).
+    pub channels: ChannelMode,
 }
 
 impl Default for AudioProcessing {
@@ -200,6 +650,10 @@ impl Default for AudioProcessing {
             trim_silence: false,
             silence_threshold_db: -40.0,
             silence_pad_ms: 50,
+            backend: AudioBackend::default(),
+            disk_backed: false,
+            conditioning: Conditioning::default(),
+            channels: ChannelMode::default(),
         }
     }
 }
@@ -234,6 +688,26 @@ impl AudioProcessing {
         self
     }
 
+    pub fn backend(mut self, backend: AudioBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn disk_backed(mut self, enabled: bool) -> Self {
+        self.disk_backed = enabled;
+        self
+    }
+
+    pub fn conditioning(mut self, conditioning: Conditioning) -> Self {
+        self.conditioning = conditioning;
+        self
+    }
+
+    pub fn channels(mut self, channels: ChannelMode) -> Self {
+        self.channels = channels;
+        self
+    }
+
     /// Enable all processing steps (DC offset removal, normalization, silence trimming).
     pub fn all() -> Self {
         Self {
@@ -245,11 +719,403 @@ impl AudioProcessing {
     }
 }
 
+/// Options for streaming transcription (see [`Transcriber::stream`](crate::Transcriber::stream)).
+///
+/// Whisper is re-run over a sliding window of the most recent audio every `step`
+/// seconds. Segments that end before the last `overlap` seconds of the window
+/// (and are followed by another segment) are finalized and dropped from the
+/// window; the rest is re-decoded on the next pass and reported as provisional.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Maximum audio per whisper pass in seconds (default 15, at most 30).
+    pub window_secs: f32,
+    /// New audio required before the next pass in seconds (default 2).
+    pub step_secs: f32,
+    /// Tail of the window that is never finalized, in seconds (default 1).
+    pub overlap_secs: f32,
+    /// Sample rate of pushed audio (default 16000).
+    pub sample_rate: u32,
+    /// Channel count of pushed (interleaved) audio (default 1).
+    pub channels: u16,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            window_secs: 15.0,
+            step_secs: 2.0,
+            overlap_secs: 1.0,
+            sample_rate: 16_000,
+            channels: 1,
+        }
+    }
+}
+
+impl StreamOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn window_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(2.0..=30.0).contains(&secs) {
+            return Err(Error::InvalidOption(
+                "stream window must be between 2 and 30 seconds".into(),
+            ));
+        }
+        self.window_secs = secs;
+        Ok(self)
+    }
+
+    pub fn step_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(secs > 0.0 && secs.is_finite()) {
+            return Err(Error::InvalidOption("stream step must be > 0".into()));
+        }
+        self.step_secs = secs;
+        Ok(self)
+    }
+
+    pub fn overlap_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(secs >= 0.0 && secs.is_finite()) {
+            return Err(Error::InvalidOption("stream overlap must be >= 0".into()));
+        }
+        self.overlap_secs = secs;
+        Ok(self)
+    }
+
+    pub fn sample_rate(mut self, rate: u32) -> Result<Self, Error> {
+        if rate == 0 {
+            return Err(Error::InvalidOption("sample_rate must be >= 1".into()));
+        }
+        self.sample_rate = rate;
+        Ok(self)
+    }
+
+    pub fn channels(mut self, channels: u16) -> Result<Self, Error> {
+        if channels == 0 {
+            return Err(Error::InvalidOption("channels must be >= 1".into()));
+        }
+        self.channels = channels;
+        Ok(self)
+    }
+
+    /// Check that the settings fit together (each setter only checks its own value).
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.step_secs > self.window_secs {
+            return Err(Error::InvalidOption(
+                "stream step must not exceed the window".into(),
+            ));
+        }
+        if self.overlap_secs >= self.window_secs / 2.0 {
+            return Err(Error::InvalidOption(
+                "stream overlap must be less than half the window".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whisper decoder tuning. The defaults are the settings transcriber has
+/// always used; see the README on hallucination prevention before relaxing
+/// them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecoderOptions {
+    /// Retry a window at a higher temperature when the entropy of its text is
+    /// above this, i.e. it is repetitive (default 2.4).
+    pub entropy_threshold: f32,
+    /// Retry a window when its average token log probability is below this
+    /// (default -1.0).
+    pub logprob_threshold: f32,
+    /// Temperature added on each retry; 0 disables the fallback (default 0.2).
+    pub temperature_increment: f32,
+    /// Drop a window as silence when its no-speech probability is above this
+    /// and its log probability below `logprob_threshold` (default 0.6).
+    pub no_speech_threshold: f32,
+    /// Suppress non-speech tokens such as `[Music]` (default true).
+    pub suppress_nst: bool,
+    /// Candidates decoded per window with greedy sampling (default 5).
+    pub best_of: u32,
+    /// Split segments longer than this many characters (default: no limit).
+    pub max_len: Option<u32>,
+    /// With `max_len`, only split between words (default false).
+    pub split_on_word: bool,
+    /// Tokens of preceding text the decoder sees in each window (default 0,
+    /// at most half the model's text context). A prompt comes on top of it.
+    pub n_max_text_ctx: u32,
+}
+
+impl Default for DecoderOptions {
+    fn default() -> Self {
+        Self {
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+            temperature_increment: 0.2,
+            no_speech_threshold: 0.6,
+            suppress_nst: true,
+            best_of: 5,
+            max_len: None,
+            split_on_word: false,
+            n_max_text_ctx: 0,
+        }
+    }
+}
+
+impl DecoderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entropy_threshold(mut self, threshold: f32) -> Result<Self, Error> {
+        if !(threshold > 0.0 && threshold.is_finite()) {
+            return Err(Error::InvalidOption("entropy_threshold must be > 0".into()));
+        }
+        self.entropy_threshold = threshold;
+        Ok(self)
+    }
+
+    pub fn logprob_threshold(mut self, threshold: f32) -> Result<Self, Error> {
+        if !(threshold <= 0.0 && threshold.is_finite()) {
+            return Err(Error::InvalidOption("logprob_threshold must be <= 0".into()));
+        }
+        self.logprob_threshold = threshold;
+        Ok(self)
+    }
+
+    pub fn temperature_increment(mut self, increment: f32) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&increment) {
+            return Err(Error::InvalidOption(
+                "temperature_increment must be between 0.0 and 1.0".into(),
+            ));
+        }
+        self.temperature_increment = increment;
+        Ok(self)
+    }
+
+    pub fn no_speech_threshold(mut self, threshold: f32) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(Error::InvalidOption(
+                "no_speech_threshold must be between 0.0 and 1.0".into(),
+            ));
+        }
+        self.no_speech_threshold = threshold;
+        Ok(self)
+    }
+
+    pub fn suppress_nst(mut self, enabled: bool) -> Self {
+        self.suppress_nst = enabled;
+        self
+    }
+
+    pub fn best_of(mut self, n: u32) -> Result<Self, Error> {
+        if n == 0 || i32::try_from(n).is_err() {
+            return Err(Error::InvalidOption("best_of must be >= 1".into()));
+        }
+        self.best_of = n;
+        Ok(self)
+    }
+
+    pub fn max_len(mut self, chars: u32) -> Result<Self, Error> {
+        if chars == 0 || i32::try_from(chars).is_err() {
+            return Err(Error::InvalidOption("max_len must be >= 1".into()));
+        }
+        self.max_len = Some(chars);
+        Ok(self)
+    }
+
+    pub fn split_on_word(mut self, enabled: bool) -> Self {
+        self.split_on_word = enabled;
+        self
+    }
+
+    pub fn n_max_text_ctx(mut self, tokens: u32) -> Self {
+        self.n_max_text_ctx = tokens;
+        self
+    }
+}
+
+/// Which audio [`Transcriber::detect_language`](crate::Transcriber::detect_language)
+/// listens to. Whisper looks at up to 30 seconds at a time; the probabilities
+/// of several windows are averaged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageDetection {
+    /// Length of each window in seconds (default 30, at most 30).
+    pub window_secs: f32,
+    /// Number of windows (default 1).
+    pub windows: u32,
+    /// Spread the windows evenly over the recording instead of taking them
+    /// back to back from the start (default false).
+    pub spread: bool,
+}
+
+impl Default for LanguageDetection {
+    fn default() -> Self {
+        Self {
+            window_secs: 30.0,
+            windows: 1,
+            spread: false,
+        }
+    }
+}
+
+impl LanguageDetection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn window_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(1.0..=30.0).contains(&secs) {
+            return Err(Error::InvalidOption(
+                "language detection window must be between 1 and 30 seconds".into(),
+            ));
+        }
+        self.window_secs = secs;
+        Ok(self)
+    }
+
+    pub fn windows(mut self, n: u32) -> Result<Self, Error> {
+        if n == 0 {
+            return Err(Error::InvalidOption("language detection needs at least 1 window".into()));
+        }
+        self.windows = n;
+        Ok(self)
+    }
+
+    pub fn spread(mut self, enabled: bool) -> Self {
+        self.spread = enabled;
+        self
+    }
+}
+
+/// Split long recordings into chunks that are transcribed in parallel, each on
+/// its own whisper state sharing the loaded model (see
+/// [`TranscribeOptions::chunking`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkOptions {
+    /// Target chunk length in seconds (default 300, at least 30). Chunks end
+    /// at the quietest moment near the target.
+    pub chunk_secs: f32,
+    /// How far a chunk boundary may move from its target to find silence, in
+    /// seconds (default 15).
+    pub search_secs: f32,
+    /// Audio neighbouring chunks share, in seconds (default 2). A segment in
+    /// the overlap is kept from the chunk its middle falls into.
+    pub overlap_secs: f32,
+    /// Chunks transcribed at once (default: CPU cores divided by threads per
+    /// chunk). Every worker holds its own whisper state in memory.
+    pub workers: Option<usize>,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            chunk_secs: 300.0,
+            search_secs: 15.0,
+            overlap_secs: 2.0,
+            workers: None,
+        }
+    }
+}
+
+impl ChunkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(secs >= 30.0 && secs.is_finite()) {
+            return Err(Error::InvalidOption("chunks must be at least 30 seconds long".into()));
+        }
+        self.chunk_secs = secs;
+        Ok(self)
+    }
+
+    pub fn search_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(secs >= 0.0 && secs.is_finite()) {
+            return Err(Error::InvalidOption("chunk boundary search must be >= 0".into()));
+        }
+        self.search_secs = secs;
+        Ok(self)
+    }
+
+    pub fn overlap_secs(mut self, secs: f32) -> Result<Self, Error> {
+        if !(0.0..=30.0).contains(&secs) {
+            return Err(Error::InvalidOption(
+                "chunk overlap must be between 0 and 30 seconds".into(),
+            ));
+        }
+        self.overlap_secs = secs;
+        Ok(self)
+    }
+
+    pub fn workers(mut self, n: usize) -> Result<Self, Error> {
+        if n == 0 {
+            return Err(Error::InvalidOption("chunk workers must be >= 1".into()));
+        }
+        self.workers = Some(n);
+        Ok(self)
+    }
+
+    /// Check that the settings fit together (each setter only checks its own value).
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.search_secs >= self.chunk_secs / 2.0 {
+            return Err(Error::InvalidOption(
+                "chunk boundary search must be less than half the chunk length".into(),
+            ));
+        }
+        if self.overlap_secs >= self.chunk_secs / 2.0 {
+            return Err(Error::InvalidOption(
+                "chunk overlap must be less than half the chunk length".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Part of the input to transcribe, in seconds from its start (see
+/// [`TranscribeOptions::time_range`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: f64,
+    /// End of the range; `None` runs to the end of the input.
+    pub end: Option<f64>,
+}
+
+impl TimeRange {
+    /// Sample indices of the range in audio of `len` samples at `rate` Hz,
+    /// cut off at the end of the audio.
+    pub(crate) fn samples(&self, rate: u32, len: usize) -> Result<Range<usize>, Error> {
+        let index = |secs: f64| ((secs * rate as f64) as usize).min(len);
+        let range = index(self.start)..self.end.map_or(len, index);
+        if range.is_empty() {
+            return Err(self.past_end(len as f64 / rate as f64));
+        }
+        Ok(range)
+    }
+
+    /// Seconds of the range within audio of `duration` seconds.
+    pub(crate) fn duration_within(&self, duration: f64) -> Result<f64, Error> {
+        if self.start >= duration {
+            return Err(self.past_end(duration));
+        }
+        Ok(self.end.unwrap_or(duration).min(duration) - self.start)
+    }
+
+    fn past_end(&self, duration: f64) -> Error {
+        Error::InvalidOption(format!(
+            "time range starts at {:.1}s, after the end of the audio ({duration:.1}s)",
+            self.start
+        ))
+    }
+}
+
 /// Builder for transcription options.
 #[derive(Debug, Clone)]
 pub struct TranscribeOptions {
     pub model: Model,
     pub language: Language,
+    /// Detect the language of every 30-second window and transcribe each in
+    /// its own language, for recordings that switch languages. Requires
+    /// [`Language::Auto`]; segments and the transcript record the languages.
+    pub language_per_window: bool,
     pub translate: bool,
     pub word_timestamps: bool,
     pub diarize: bool,
@@ -260,7 +1126,33 @@ pub struct TranscribeOptions {
     pub vad_model_path: Option<String>,
     pub temperature: f32,
     pub beam_size: Option<u32>,
+    /// Text the decoder is conditioned on, e.g. a sentence in the expected
+    /// style or the topic of the recording.
+    pub initial_prompt: Option<String>,
+    /// Names, acronyms and other terms whisper should spell this way. They
+    /// are put in front of `initial_prompt`, as many as fit whisper's prompt
+    /// budget, and share its limits.
+    pub vocabulary: Vec<String>,
+    pub decoder: DecoderOptions,
+    pub language_detection: LanguageDetection,
+    /// Transcribe long recordings in parallel chunks (default: one pass).
+    pub chunking: Option<ChunkOptions>,
+    /// Transcribe only this part of the input (default: all of it).
+    pub time_range: Option<TimeRange>,
+    /// Speaker name for each input channel, by channel index. Only valid with
+    /// [`ChannelMode::PerChannel`]. Blank names leave a channel unnamed.
+    pub channel_speakers: Vec<String>,
     pub cache_dir: Option<PathBuf>,
+    /// Expected SHA-256 of the model file. Overrides the built-in digest and
+    /// is required to verify a [`Model::Custom`] file.
+    pub model_sha256: Option<String>,
+    /// Hash cached models before loading them. Downloads are always verified.
+    pub verify_model: bool,
+    /// Base URL whisper models are downloaded from, such as an internal
+    /// mirror (default: `TRANSCRIBER_MODEL_BASE_URL`, then HuggingFace).
+    pub model_base_url: Option<String>,
+    /// Never download anything: models that aren't cached are an error.
+    pub offline: bool,
     pub audio_processing: AudioProcessing,
     /// Enable speaker identification (requires `speaker-id` feature).
     pub speaker_identification: bool,
@@ -268,8 +1160,24 @@ pub struct TranscribeOptions {
     pub speaker_profiles_path: Option<PathBuf>,
     /// Path to wespeaker ONNX model file.
     pub speaker_model_path: Option<PathBuf>,
+    /// Expected SHA-256 of the speaker model, checked after download (and on
+    /// load with `verify_model`, or always for `speaker_model_path`).
+    pub speaker_model_sha256: Option<String>,
+    /// Base URL the speaker model is downloaded from
+    /// (default: `TRANSCRIBER_SPEAKER_MODEL_BASE_URL`, then GitHub).
+    pub speaker_model_base_url: Option<String>,
     /// Cosine similarity threshold for speaker matching (default: 0.6).
     pub speaker_threshold: f32,
+    /// Cluster voices into anonymous speakers (`SPEAKER_00`, …) instead of
+    /// requiring enrolled profiles (requires `speaker-id` feature). Combined
+    /// with `speaker_identification`, clusters matching a profile take its name.
+    pub speaker_diarization: bool,
+    /// Number of speakers for diarization, or `None` to estimate it.
+    pub num_speakers: Option<usize>,
+    /// Called with progress updates from every pipeline stage.
+    pub progress: Option<ProgressCallback>,
+    /// Aborts the transcription when cancelled.
+    pub cancellation: Option<CancellationToken>,
 }
 
 impl Default for TranscribeOptions {
@@ -277,6 +1185,7 @@ impl Default for TranscribeOptions {
         Self {
             model: Model::LargeV3,
             language: Language::Auto,
+            language_per_window: false,
             translate: false,
             word_timestamps: false,
             diarize: false,
@@ -287,12 +1196,29 @@ impl Default for TranscribeOptions {
             vad_model_path: None,
             temperature: 0.0,
             beam_size: None,
+            initial_prompt: None,
+            vocabulary: Vec::new(),
+            decoder: DecoderOptions::default(),
+            language_detection: LanguageDetection::default(),
+            chunking: None,
+            time_range: None,
+            channel_speakers: Vec::new(),
             cache_dir: None,
+            model_sha256: None,
+            verify_model: false,
+            model_base_url: None,
+            offline: false,
             audio_processing: AudioProcessing::default(),
             speaker_identification: false,
             speaker_profiles_path: None,
             speaker_model_path: None,
+            speaker_model_sha256: None,
+            speaker_model_base_url: None,
             speaker_threshold: 0.6,
+            speaker_diarization: false,
+            num_speakers: None,
+            progress: None,
+            cancellation: None,
         }
     }
 }
@@ -314,6 +1240,11 @@ impl TranscribeOptions {
         Ok(self)
     }
 
+    pub fn language_per_window(mut self, enabled: bool) -> Self {
+        self.language_per_window = enabled;
+        self
+    }
+
     pub fn translate(mut self, translate: bool) -> Self {
         self.translate = translate;
         self
@@ -375,11 +1306,131 @@ impl TranscribeOptions {
         Ok(self)
     }
 
+    /// Condition the decoder on `prompt`. Only the last part of an overlong
+    /// prompt is used (half of the model's text context, 224 tokens for the
+    /// standard models).
+    ///
+    /// Every 30-second window is decoded with the prompt in front of it, so
+    /// longer audio is transcribed one window at a time.
+    pub fn initial_prompt(mut self, prompt: &str) -> Result<Self, Error> {
+        let prompt = prompt.trim();
+        if prompt.contains('\0') {
+            return Err(Error::InvalidOption("initial_prompt must not contain NUL bytes".into()));
+        }
+        self.initial_prompt = (!prompt.is_empty()).then(|| prompt.to_string());
+        Ok(self)
+    }
+
+    /// Terms to prime the decoder with, in order of importance. Blank and
+    /// repeated terms are dropped.
+    pub fn vocabulary<I, S>(mut self, terms: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut vocabulary: Vec<String> = Vec::new();
+        for term in terms {
+            let term = term.as_ref().trim();
+            if term.contains('\0') {
+                return Err(Error::InvalidOption("vocabulary must not contain NUL bytes".into()));
+            }
+            if !term.is_empty() && !vocabulary.iter().any(|t| t == term) {
+                vocabulary.push(term.to_string());
+            }
+        }
+        self.vocabulary = vocabulary;
+        Ok(self)
+    }
+
+    pub fn decoder(mut self, decoder: DecoderOptions) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    pub fn language_detection(mut self, detection: LanguageDetection) -> Self {
+        self.language_detection = detection;
+        self
+    }
+
+    /// Split recordings longer than one chunk and transcribe the chunks in
+    /// parallel.
+    pub fn chunking(mut self, chunking: ChunkOptions) -> Self {
+        self.chunking = Some(chunking);
+        self
+    }
+
+    /// Transcribe only `start..end` seconds of the input (`end: None` to run
+    /// to the end). Files are seeked by ffmpeg rather than decoded in full;
+    /// timestamps in the transcript stay relative to the start of the input,
+    /// and its `duration` is the length of the range. Live streams ignore the
+    /// range.
+    pub fn time_range(mut self, start: f64, end: Option<f64>) -> Result<Self, Error> {
+        if !(start >= 0.0 && start.is_finite()) {
+            return Err(Error::InvalidOption("time range start must be >= 0".into()));
+        }
+        if let Some(end) = end {
+            if !(end > start && end.is_finite()) {
+                return Err(Error::InvalidOption(
+                    "time range end must be after its start".into(),
+                ));
+            }
+        }
+        self.time_range = Some(TimeRange { start, end });
+        Ok(self)
+    }
+
+    /// Name the speaker on each channel in per-channel transcription, e.g.
+    /// `["Agent", "Customer"]` for a call recorded agent-left, customer-right.
+    /// Segments from a named channel get the name as their `speaker_id`.
+    /// Transcribing fails with [`Error::InvalidOption`] unless the channel
+    /// mode is [`ChannelMode::PerChannel`].
+    pub fn channel_speakers<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.channel_speakers = names.into_iter().map(|n| n.as_ref().trim().to_string()).collect();
+        self
+    }
+
+    /// Name given to `channel` by [`channel_speakers`](Self::channel_speakers).
+    pub(crate) fn channel_speaker(&self, channel: u16) -> Option<&str> {
+        self.channel_speakers
+            .get(channel as usize)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+    }
+
     pub fn cache_dir(mut self, dir: PathBuf) -> Self {
         self.cache_dir = Some(dir);
         self
     }
 
+    /// Expect the model file to have this SHA-256 digest (64 hex characters).
+    pub fn model_sha256(mut self, hex: &str) -> Result<Self, Error> {
+        self.model_sha256 = Some(parse_sha256(hex)?);
+        Ok(self)
+    }
+
+    /// Verify cached models against their checksum on every load.
+    pub fn verify_model(mut self, enabled: bool) -> Self {
+        self.verify_model = enabled;
+        self
+    }
+
+    /// Download whisper models from `url` instead of HuggingFace. The model
+    /// file name (e.g. `ggml-small.bin`) is appended to it.
+    pub fn model_base_url(mut self, url: &str) -> Result<Self, Error> {
+        self.model_base_url = Some(parse_base_url(url)?);
+        Ok(self)
+    }
+
+    /// Fail instead of downloading models that are not cached.
+    pub fn offline(mut self, enabled: bool) -> Self {
+        self.offline = enabled;
+        self
+    }
+
     pub fn audio_processing(mut self, ap: AudioProcessing) -> Self {
         self.audio_processing = ap;
         self
@@ -400,11 +1451,112 @@ impl TranscribeOptions {
         self
     }
 
+    /// Expect the speaker model to have this SHA-256 digest (64 hex characters).
+    pub fn speaker_model_sha256(mut self, hex: &str) -> Result<Self, Error> {
+        self.speaker_model_sha256 = Some(parse_sha256(hex)?);
+        Ok(self)
+    }
+
+    /// Download the speaker model from `url` instead of GitHub.
+    pub fn speaker_model_base_url(mut self, url: &str) -> Result<Self, Error> {
+        self.speaker_model_base_url = Some(parse_base_url(url)?);
+        Ok(self)
+    }
+
     pub fn speaker_threshold(mut self, threshold: f32) -> Self {
         self.speaker_threshold = threshold;
         self
     }
 
+    pub fn speaker_diarization(mut self, enabled: bool) -> Self {
+        self.speaker_diarization = enabled;
+        self
+    }
+
+    /// Fix the number of speakers found by diarization. Must be at least 1.
+    pub fn num_speakers(mut self, n: usize) -> Result<Self, Error> {
+        if n == 0 {
+            return Err(Error::InvalidOption(
+                "number of speakers must be at least 1".into(),
+            ));
+        }
+        self.num_speakers = Some(n);
+        Ok(self)
+    }
+
+    /// Report progress to `callback` (decoding, whisper, speaker identification).
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Publish progress on a `tokio::sync::watch` channel.
+    pub fn progress_sender(self, tx: tokio::sync::watch::Sender<Progress>) -> Self {
+        self.on_progress(move |p| {
+            tx.send_replace(p.clone());
+        })
+    }
+
+    /// Abort the transcription when `token` is cancelled.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub(crate) fn report(&self, progress: Progress) {
+        if let Some(cb) = &self.progress {
+            cb.call(&progress);
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(|t| t.is_cancelled())
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Check that the settings fit together (each setter only checks its own value).
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.language_per_window && !self.language.is_auto() {
+            return Err(Error::InvalidOption(
+                "language_per_window needs the language set to auto".into(),
+            ));
+        }
+        if !self.channel_speakers.is_empty()
+            && self.audio_processing.channels != ChannelMode::PerChannel
+        {
+            return Err(Error::InvalidOption(
+                "channel_speakers needs per-channel transcription".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// How to fetch the whisper model.
+    pub fn model_fetch(&self) -> FetchOptions {
+        FetchOptions {
+            base_url: self.model_base_url.clone(),
+            offline: self.offline,
+            sha256: self.model_sha256.clone(),
+            verify_cached: self.verify_model,
+        }
+    }
+
+    /// How to fetch the speaker model.
+    pub fn speaker_model_fetch(&self) -> FetchOptions {
+        FetchOptions {
+            base_url: self.speaker_model_base_url.clone(),
+            offline: self.offline,
+            sha256: self.speaker_model_sha256.clone(),
+            verify_cached: self.verify_model,
+        }
+    }
+
     /// Resolve the cache directory, defaulting to ~/.cache/transcriber/models.
     pub fn resolve_cache_dir(&self) -> PathBuf {
         self.cache_dir.clone().unwrap_or_else(|| {
@@ -416,6 +1568,26 @@ impl TranscribeOptions {
     }
 }
 
+fn parse_sha256(hex: &str) -> Result<String, Error> {
+    let hex = hex.trim();
+    if !crate::model::is_sha256_hex(hex) {
+        return Err(Error::InvalidOption(format!(
+            "invalid SHA-256 digest \"{hex}\" (expected 64 hex characters)"
+        )));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+fn parse_base_url(url: &str) -> Result<String, Error> {
+    let url = url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(Error::InvalidOption(format!(
+            "invalid base URL \"{url}\" (expected http:// or https://)"
+        )));
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -450,6 +1622,12 @@ mod tests {
         assert_eq!(lang.code(), Some("en"));
     }
 
+    #[test]
+    fn test_language_name() {
+        assert_eq!(Language::new("de").unwrap().name(), Some("german"));
+        assert_eq!(Language::Auto.name(), None);
+    }
+
     #[test]
     fn test_language_auto_string() {
         let lang = Language::new("auto").unwrap();
@@ -535,6 +1713,56 @@ mod tests {
         assert_eq!(Model::Custom(PathBuf::from("/tmp/model.bin")).name(), "custom");
     }
 
+    #[test]
+    fn test_model_quantized_names() {
+        let model = Model::parse_name("large-v3-turbo-q5_0").unwrap();
+        assert_eq!(model.quantization(), Quantization::Q5_0);
+        assert_eq!(model.filename(), "ggml-large-v3-turbo-q5_0.bin");
+        assert_eq!(model.name(), "large-v3-turbo-q5_0");
+        let Model::Quantized(q) = &model else { panic!("expected a quantized model") };
+        assert_eq!(q.base(), &Model::LargeV3Turbo);
+
+        assert_eq!(
+            Model::parse_name("tiny.en-q5_1").unwrap().filename(),
+            "ggml-tiny.en-q5_1.bin"
+        );
+        // Combinations whisper.cpp doesn't publish
+        assert!(Model::parse_name("large-v3-q8_0").is_none());
+        assert!(Model::parse_name("tiny-q5_0").is_none());
+        assert!(Model::parse_name("tiny-f16").is_none());
+        assert!(Model::parse_name("tiny-q5_1-q8_0").is_none());
+        assert!(Model::parse_name("distil-large-v3-q5_0").is_none());
+    }
+
+    #[test]
+    fn test_model_quantized_builder() {
+        let model = Model::Small.quantized(Quantization::Q8_0).unwrap();
+        assert_eq!(model.name(), "small-q8_0");
+        // Re-quantizing replaces the quantization; F16 goes back to the base
+        let model = model.quantized(Quantization::Q5_1).unwrap();
+        assert_eq!(model.name(), "small-q5_1");
+        assert_eq!(model.quantized(Quantization::F16).unwrap(), Model::Small);
+        assert!(Model::LargeV3.quantized(Quantization::Q8_0).is_err());
+        assert!(Model::Custom(PathBuf::from("m.bin")).quantized(Quantization::Q5_0).is_err());
+    }
+
+    #[test]
+    fn test_model_available_roundtrip() {
+        let models = Model::available();
+        assert_eq!(models.len(), 31);
+        for model in models {
+            assert_eq!(Model::parse_name(&model.name()), Some(model.clone()));
+            assert!(model.size_mb().is_some());
+        }
+        assert!(Model::Small.quantized(Quantization::Q5_1).unwrap().size_mb() < Model::Small.size_mb());
+    }
+
+    #[test]
+    fn test_model_distil_filenames() {
+        assert_eq!(Model::DistilLargeV3.filename(), "ggml-distil-large-v3.bin");
+        assert_eq!(Model::parse_name("distil-medium.en"), Some(Model::DistilMediumEn));
+    }
+
     #[test]
     fn test_model_custom_filename() {
         let model = Model::Custom(PathBuf::from("/path/to/my-model.ggml"));
@@ -564,6 +1792,9 @@ mod tests {
         assert!(!ap.trim_silence);
         assert_eq!(ap.silence_threshold_db, -40.0);
         assert_eq!(ap.silence_pad_ms, 50);
+        assert!(!ap.disk_backed);
+        assert_eq!(ap.conditioning, Conditioning::Speech);
+        assert_eq!(ap.channels, ChannelMode::Mix);
     }
 
     #[test]
@@ -579,16 +1810,160 @@ mod tests {
         let ap = AudioProcessing::new()
             .dc_offset_removal(true)
             .silence_threshold_db(-30.0)
-            .silence_pad_ms(100);
+            .silence_pad_ms(100)
+            .disk_backed(true);
         assert!(ap.dc_offset_removal);
+        assert!(ap.disk_backed);
         assert!(!ap.normalize);
         assert!(!ap.trim_silence);
         assert_eq!(ap.silence_threshold_db, -30.0);
         assert_eq!(ap.silence_pad_ms, 100);
     }
 
+    #[test]
+    fn test_audio_backend_default_ffmpeg() {
+        assert_eq!(AudioProcessing::default().backend, AudioBackend::Ffmpeg);
+        let ap = AudioProcessing::new().backend(AudioBackend::Native);
+        assert_eq!(ap.backend, AudioBackend::Native);
+    }
+
+    #[test]
+    fn test_conditioning_presets() {
+        assert_eq!(Conditioning::parse_name("none").unwrap(), Conditioning::None);
+        assert_eq!(Conditioning::parse_name("speech-default").unwrap(), Conditioning::Speech);
+        assert_eq!(Conditioning::parse_name("Telephony").unwrap(), Conditioning::Telephony);
+        assert_eq!(
+            Conditioning::parse_name("music-bed-suppression").unwrap(),
+            Conditioning::MusicBedSuppression
+        );
+        assert!(Conditioning::parse_name("podcast").is_err());
+
+        assert_eq!(Conditioning::None.filter().unwrap(), None);
+        for preset in [
+            Conditioning::Speech,
+            Conditioning::Telephony,
+            Conditioning::MusicBedSuppression,
+        ] {
+            // The presets must pass the same check as custom chains
+            validate_filter(preset.filter().unwrap().unwrap()).unwrap();
+        }
+        let ap = AudioProcessing::new().conditioning(Conditioning::Telephony);
+        assert_eq!(ap.conditioning, Conditioning::Telephony);
+    }
+
+    #[test]
+    fn test_conditioning_custom() {
+        let chain = "highpass=f=80,volume=2,equalizer=f=1000:t=q:w=1:g=-3";
+        assert_eq!(Conditioning::custom(chain).unwrap().filter().unwrap(), Some(chain));
+        assert_eq!(Conditioning::custom("loudnorm").unwrap().filter().unwrap(), Some("loudnorm"));
+
+        for bad in [
+            "",
+            " ",
+            // Extra ffmpeg arguments
+            "volume=2 -y /tmp/out.wav",
+            "-i /etc/passwd",
+            // Filters that read files or open sockets
+            "amovie=/etc/passwd",
+            "volume=1,sendcmd=f=cmds.txt",
+            "azmq",
+            // Graph syntax, quoting and escapes
+            "volume=1;[in]anull",
+            "[0:a]volume=1",
+            "volume='2'",
+            "volume=1\\,2",
+            "volume=1\n",
+            "highpass=f=80,,volume=2",
+        ] {
+            assert!(
+                matches!(Conditioning::custom(bad), Err(Error::InvalidOption(_))),
+                "{bad:?} should be rejected"
+            );
+        }
+        // Constructed directly, the chain is still checked before use
+        assert!(Conditioning::Custom("amovie=x".into()).filter().is_err());
+    }
+
+    #[test]
+    fn test_channel_mode_parse_name() {
+        assert_eq!(ChannelMode::parse_name("mix").unwrap(), ChannelMode::Mix);
+        assert_eq!(ChannelMode::parse_name("Per-Channel").unwrap(), ChannelMode::PerChannel);
+        assert_eq!(ChannelMode::parse_name("1").unwrap(), ChannelMode::Channel(1));
+        assert!(matches!(ChannelMode::parse_name("left"), Err(Error::InvalidOption(_))));
+        assert!(ChannelMode::parse_name("-1").is_err());
+        let ap = AudioProcessing::new().channels(ChannelMode::PerChannel);
+        assert_eq!(ap.channels, ChannelMode::PerChannel);
+    }
+
+    #[test]
+    fn test_options_channel_speakers() {
+        let opts = TranscribeOptions::new().channel_speakers(["Agent ", "", "Supervisor"]);
+        assert_eq!(opts.channel_speaker(0), Some("Agent"));
+        assert_eq!(opts.channel_speaker(1), None);
+        assert_eq!(opts.channel_speaker(2), Some("Supervisor"));
+        assert_eq!(opts.channel_speaker(3), None);
+        assert_eq!(TranscribeOptions::default().channel_speaker(0), None);
+    }
+
+    #[test]
+    fn test_audio_backend_parse_name() {
+        assert_eq!(AudioBackend::parse_name("ffmpeg").unwrap(), AudioBackend::Ffmpeg);
+        assert_eq!(AudioBackend::parse_name("Native").unwrap(), AudioBackend::Native);
+        assert!(matches!(
+            AudioBackend::parse_name("gstreamer"),
+            Err(Error::InvalidOption(_))
+        ));
+        assert_eq!(AudioBackend::Native.to_string(), "native");
+    }
+
+    // --- StreamOptions tests ---
+
+    #[test]
+    fn test_stream_options_defaults_valid() {
+        let so = StreamOptions::default();
+        assert_eq!(so.window_secs, 15.0);
+        assert_eq!(so.sample_rate, 16_000);
+        assert!(so.validate().is_ok());
+    }
+
+    #[test]
+    fn test_stream_options_bounds() {
+        assert!(StreamOptions::new().window_secs(1.0).is_err());
+        assert!(StreamOptions::new().window_secs(31.0).is_err());
+        assert!(StreamOptions::new().step_secs(0.0).is_err());
+        assert!(StreamOptions::new().overlap_secs(-1.0).is_err());
+        assert!(StreamOptions::new().sample_rate(0).is_err());
+        assert!(StreamOptions::new().channels(0).is_err());
+    }
+
+    #[test]
+    fn test_stream_options_validate_combination() {
+        let so = StreamOptions::new().window_secs(4.0).unwrap().step_secs(5.0).unwrap();
+        assert!(matches!(so.validate(), Err(Error::InvalidOption(_))));
+        let so = StreamOptions::new().window_secs(4.0).unwrap().overlap_secs(2.0).unwrap();
+        assert!(so.validate().is_err());
+    }
+
     // --- TranscribeOptions tests ---
 
+    #[test]
+    fn test_options_cancellation() {
+        let token = CancellationToken::new();
+        let opts = TranscribeOptions::new().cancellation_token(token.clone());
+        assert!(opts.check_cancelled().is_ok());
+        token.cancel();
+        assert!(matches!(opts.check_cancelled(), Err(Error::Cancelled)));
+        assert!(TranscribeOptions::default().check_cancelled().is_ok());
+    }
+
+    #[test]
+    fn test_options_progress_sender() {
+        let (tx, rx) = tokio::sync::watch::channel(Progress::default());
+        let opts = TranscribeOptions::new().progress_sender(tx);
+        opts.report(Progress::new(crate::progress::Stage::Transcribing, 0.25));
+        assert_eq!(rx.borrow().fraction, 0.25);
+    }
+
     #[test]
     fn test_options_defaults() {
         let opts = TranscribeOptions::default();
@@ -598,11 +1973,28 @@ mod tests {
         assert!(opts.vad_model_path.is_none());
         assert_eq!(opts.temperature, 0.0);
         assert!(!opts.translate);
+        assert!(!opts.language_per_window);
         assert!(!opts.word_timestamps);
         assert!(opts.beam_size.is_none());
         assert!(opts.n_threads.is_none());
     }
 
+    #[test]
+    fn test_language_per_window_needs_auto() {
+        let per_window = TranscribeOptions::new().language_per_window(true);
+        assert!(per_window.validate().is_ok());
+        let german = per_window.language("de").unwrap();
+        assert!(matches!(german.validate(), Err(Error::InvalidOption(_))));
+    }
+
+    #[test]
+    fn test_channel_speakers_need_per_channel() {
+        let named = TranscribeOptions::new().channel_speakers(["Agent", "Customer"]);
+        assert!(matches!(named.validate(), Err(Error::InvalidOption(_))));
+        let per_channel = AudioProcessing::new().channels(ChannelMode::PerChannel);
+        assert!(named.audio_processing(per_channel).validate().is_ok());
+    }
+
     #[test]
     fn test_options_builder_chain() {
         let opts = TranscribeOptions::new()
@@ -650,6 +2042,128 @@ mod tests {
         assert!(TranscribeOptions::new().beam_size(0).is_err());
     }
 
+    #[test]
+    fn test_decoder_options_defaults() {
+        let decoder = TranscribeOptions::new().decoder;
+        assert_eq!(decoder, DecoderOptions::new());
+        assert_eq!(decoder.entropy_threshold, 2.4);
+        assert_eq!(decoder.logprob_threshold, -1.0);
+        assert_eq!(decoder.temperature_increment, 0.2);
+        assert_eq!(decoder.no_speech_threshold, 0.6);
+        assert!(decoder.suppress_nst);
+        assert_eq!(decoder.best_of, 5);
+        assert_eq!(decoder.max_len, None);
+        assert!(!decoder.split_on_word);
+        assert_eq!(decoder.n_max_text_ctx, 0);
+    }
+
+    #[test]
+    fn test_decoder_options_validation() {
+        let decoder = DecoderOptions::new()
+            .entropy_threshold(2.8)
+            .and_then(|d| d.logprob_threshold(-0.5))
+            .and_then(|d| d.temperature_increment(0.0))
+            .and_then(|d| d.no_speech_threshold(0.4))
+            .and_then(|d| d.best_of(3))
+            .and_then(|d| d.max_len(42))
+            .unwrap()
+            .split_on_word(true)
+            .n_max_text_ctx(64);
+        assert_eq!(decoder.best_of, 3);
+        assert_eq!(decoder.max_len, Some(42));
+        let opts = TranscribeOptions::new().decoder(decoder.clone());
+        assert_eq!(opts.decoder, decoder);
+
+        assert!(DecoderOptions::new().entropy_threshold(0.0).is_err());
+        assert!(DecoderOptions::new().entropy_threshold(f32::NAN).is_err());
+        assert!(DecoderOptions::new().logprob_threshold(0.5).is_err());
+        assert!(DecoderOptions::new().temperature_increment(1.5).is_err());
+        assert!(DecoderOptions::new().no_speech_threshold(-0.1).is_err());
+        assert!(DecoderOptions::new().best_of(0).is_err());
+        assert!(DecoderOptions::new().max_len(0).is_err());
+    }
+
+    #[test]
+    fn test_language_detection_validation() {
+        let detection = LanguageDetection::new()
+            .window_secs(10.0)
+            .and_then(|d| d.windows(3))
+            .unwrap()
+            .spread(true);
+        assert_eq!(detection.windows, 3);
+        assert_eq!(TranscribeOptions::new().language_detection, LanguageDetection::new());
+        assert!(LanguageDetection::new().window_secs(0.5).is_err());
+        assert!(LanguageDetection::new().window_secs(31.0).is_err());
+        assert!(LanguageDetection::new().windows(0).is_err());
+    }
+
+    #[test]
+    fn test_chunk_options_validation() {
+        let chunking = ChunkOptions::new()
+            .chunk_secs(120.0)
+            .and_then(|c| c.search_secs(5.0))
+            .and_then(|c| c.overlap_secs(1.0))
+            .and_then(|c| c.workers(8))
+            .unwrap();
+        assert!(chunking.validate().is_ok());
+        assert!(ChunkOptions::new().validate().is_ok());
+        assert_eq!(TranscribeOptions::new().chunking, None);
+        assert_eq!(
+            TranscribeOptions::new().chunking(chunking.clone()).chunking,
+            Some(chunking)
+        );
+
+        assert!(ChunkOptions::new().chunk_secs(10.0).is_err());
+        assert!(ChunkOptions::new().search_secs(-1.0).is_err());
+        assert!(ChunkOptions::new().overlap_secs(31.0).is_err());
+        assert!(ChunkOptions::new().workers(0).is_err());
+        let too_wide = ChunkOptions::new().chunk_secs(30.0).unwrap().search_secs(15.0).unwrap();
+        assert!(too_wide.validate().is_err());
+    }
+
+    #[test]
+    fn test_options_time_range() {
+        assert_eq!(TranscribeOptions::new().time_range, None);
+        let opts = TranscribeOptions::new().time_range(4200.0, Some(5100.0)).unwrap();
+        assert_eq!(opts.time_range, Some(TimeRange { start: 4200.0, end: Some(5100.0) }));
+        assert!(TranscribeOptions::new().time_range(10.0, None).is_ok());
+        assert!(TranscribeOptions::new().time_range(-1.0, None).is_err());
+        assert!(TranscribeOptions::new().time_range(10.0, Some(10.0)).is_err());
+        assert!(TranscribeOptions::new().time_range(f64::NAN, None).is_err());
+    }
+
+    #[test]
+    fn test_time_range_samples() {
+        let range = TimeRange { start: 1.0, end: Some(2.5) };
+        assert_eq!(range.samples(16_000, 160_000).unwrap(), 16_000..40_000);
+        // Cut off at the end of the audio
+        assert_eq!(range.samples(16_000, 30_000).unwrap(), 16_000..30_000);
+        assert!(range.samples(16_000, 16_000).is_err());
+        let open = TimeRange { start: 1.0, end: None };
+        assert_eq!(open.samples(16_000, 20_000).unwrap(), 16_000..20_000);
+
+        assert_eq!(range.duration_within(60.0).unwrap(), 1.5);
+        assert_eq!(open.duration_within(60.0).unwrap(), 59.0);
+        assert!(open.duration_within(0.5).is_err());
+    }
+
+    #[test]
+    fn test_options_initial_prompt() {
+        let opts = TranscribeOptions::new().initial_prompt("  Quarterly ACME review. ").unwrap();
+        assert_eq!(opts.initial_prompt.as_deref(), Some("Quarterly ACME review."));
+        assert_eq!(TranscribeOptions::new().initial_prompt(" ").unwrap().initial_prompt, None);
+        assert!(TranscribeOptions::new().initial_prompt("a\0b").is_err());
+    }
+
+    #[test]
+    fn test_options_vocabulary() {
+        let opts = TranscribeOptions::new()
+            .vocabulary(["Kubernetes", " gRPC ", "", "Kubernetes", "ACME"])
+            .unwrap();
+        assert_eq!(opts.vocabulary, vec!["Kubernetes", "gRPC", "ACME"]);
+        assert!(TranscribeOptions::new().vocabulary(["a\0b"]).is_err());
+    }
+
     #[test]
     fn test_options_n_threads_validation() {
         assert!(TranscribeOptions::new().n_threads(1).is_ok());
@@ -657,6 +2171,38 @@ mod tests {
         assert!(TranscribeOptions::new().n_threads(0).is_err());
     }
 
+    #[test]
+    fn test_options_num_speakers_validation() {
+        let opts = TranscribeOptions::new().num_speakers(2).unwrap();
+        assert_eq!(opts.num_speakers, Some(2));
+        assert!(TranscribeOptions::new().num_speakers(0).is_err());
+        assert_eq!(TranscribeOptions::new().num_speakers, None);
+    }
+
+    #[test]
+    fn test_options_model_sha256_validation() {
+        let hash = "AB".repeat(32);
+        let opts = TranscribeOptions::new().model_sha256(&hash).unwrap();
+        assert_eq!(opts.model_sha256.as_deref(), Some("ab".repeat(32).as_str()));
+        assert!(TranscribeOptions::new().model_sha256("abc").is_err());
+        assert!(TranscribeOptions::new().model_sha256(&"zz".repeat(32)).is_err());
+        assert!(TranscribeOptions::new().speaker_model_sha256("").is_err());
+    }
+
+    #[test]
+    fn test_options_model_base_url_validation() {
+        let opts = TranscribeOptions::new()
+            .model_base_url("http://mirror.internal/whisper/")
+            .unwrap()
+            .offline(true);
+        let fetch = opts.model_fetch();
+        assert_eq!(fetch.base_url.as_deref(), Some("http://mirror.internal/whisper"));
+        assert!(fetch.offline);
+        assert!(opts.speaker_model_fetch().base_url.is_none());
+        assert!(TranscribeOptions::new().model_base_url("mirror.internal").is_err());
+        assert!(TranscribeOptions::new().speaker_model_base_url("ftp://x").is_err());
+    }
+
     #[test]
     fn test_options_resolve_cache_dir_default() {
         let opts = TranscribeOptions::default();
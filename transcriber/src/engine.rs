@@ -0,0 +1,669 @@
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use whisper_rs::{WhisperContext, WhisperState};
+
+use crate::audio::SampleSource;
+use crate::config::{
+    AudioProcessing, ChannelMode, Language, LanguageDetection, StreamOptions, TimeRange,
+    TranscribeOptions,
+};
+use crate::error::{Error, Result};
+use crate::model::ModelInfo;
+use crate::progress::{Progress, Stage};
+use crate::stream::{StreamInput, TranscriptStream};
+use crate::types::Transcript;
+#[cfg(feature = "speaker-id")]
+use crate::types::SpeakerIdSummary;
+use crate::{audio, channels, chunked, model, stream, transcribe};
+
+/// A loaded whisper model, reusable across many transcriptions.
+///
+/// Loading a model (especially large-v3) takes far longer than transcribing a
+/// short clip, so build one `Transcriber` up front and call
+/// [`transcribe_file`](Self::transcribe_file) or
+/// [`transcribe_samples`](Self::transcribe_samples) as often as needed.
+/// Each call runs on its own [`WhisperState`], so a single `Transcriber` can be
+/// cloned cheaply and used from several tasks at once.
+///
+/// ```rust,no_run
+/// # #[tokio::main]
+/// # async fn main() -> transcriber::Result<()> {
+/// use transcriber::{Model, TranscribeOptions, Transcriber};
+///
+/// let engine = Transcriber::new(TranscribeOptions::new().model(Model::Small)).await?;
+/// for path in ["a.wav", "b.wav", "c.wav"] {
+///     let transcript = engine.transcribe_file(path).await?;
+///     println!("{}", transcript.text());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Transcriber {
+    ctx: Arc<WhisperContext>,
+    info: ModelInfo,
+    options: TranscribeOptions,
+}
+
+impl Transcriber {
+    /// Load the whisper model selected by `options`, downloading it first if
+    /// it is not cached yet.
+    ///
+    /// Model and GPU settings are fixed once the model is loaded; all other
+    /// options apply to every transcription made with this instance.
+    ///
+    /// Fails early if the file is not a whisper model or can't honour the
+    /// options, e.g. a non-English language with an English-only model.
+    pub async fn new(options: TranscribeOptions) -> Result<Self> {
+        options.validate()?;
+        let cache_dir = options.resolve_cache_dir();
+        let model_path =
+            model::ensure_model_with(&options.model, &cache_dir, &options.model_fetch()).await?;
+        let info = model::read_model_info(&model_path)?;
+        info.check_options(&options)?;
+
+        // Loading the model is blocking and can take several seconds
+        let ctx = tokio::task::spawn_blocking({
+            let options = options.clone();
+            move || transcribe::load_context(&model_path, &options)
+        })
+        .await
+        .map_err(|e| Error::Model(format!("model loading task failed: {e}")))??;
+
+        Ok(Self {
+            ctx: Arc::new(ctx),
+            info,
+            options,
+        })
+    }
+
+    /// The options this transcriber was built with.
+    pub fn options(&self) -> &TranscribeOptions {
+        &self.options
+    }
+
+    /// Header information of the loaded model.
+    pub fn model_info(&self) -> &ModelInfo {
+        &self.info
+    }
+
+    /// Share the loaded model under different options (e.g. another language
+    /// per request). Model and GPU settings in `options` are ignored, since
+    /// the model is already loaded. Options that contradict each other or
+    /// that the model can't honour are reported when transcribing.
+    pub fn with_options(&self, options: TranscribeOptions) -> Self {
+        Self {
+            ctx: Arc::clone(&self.ctx),
+            info: self.info,
+            options: TranscribeOptions {
+                model: self.options.model.clone(),
+                gpu: self.options.gpu,
+                gpu_device: self.options.gpu_device,
+                ..options
+            },
+        }
+    }
+
+    /// Reject options that contradict each other or that the model can't honour.
+    fn check_options(&self) -> Result<()> {
+        self.options.validate()?;
+        self.info.check_options(&self.options)
+    }
+
+    /// Create a fresh whisper state on the loaded model.
+    ///
+    /// States are cheap compared to the model itself and hold the per-run
+    /// decoder buffers. Use this for driving whisper-rs directly.
+    pub fn create_state(&self) -> Result<WhisperState> {
+        Ok(self.ctx.create_state()?)
+    }
+
+    /// Transcribe a local audio/video file.
+    ///
+    /// With [`AudioProcessing::disk_backed`] the decoded audio is kept in a
+    /// temporary file and transcribed chunk by chunk, so memory use does not
+    /// grow with the length of the recording.
+    pub async fn transcribe_file(&self, path: impl AsRef<Path>) -> Result<Transcript> {
+        let path = path.as_ref().to_path_buf();
+        let count_channels = {
+            let path = path.clone();
+            move || audio::count_channels(&path)
+        };
+
+        if self.options.audio_processing.disk_backed {
+            return self
+                .transcribe_input(count_channels, move |processing, range| {
+                    audio::load_audio_to_disk(&path, processing, range)
+                })
+                .await;
+        }
+
+        // Load and process audio (blocking ffmpeg subprocess)
+        self.transcribe_input(count_channels, move |processing, range| {
+            audio::load_audio_range(&path, processing, range)
+        })
+        .await
+    }
+
+    /// Transcribe an encoded audio/video file held in memory (e.g. an upload).
+    ///
+    /// The format is detected from the content, and the configured
+    /// [`AudioProcessing`](crate::AudioProcessing) is applied as for files.
+    pub async fn transcribe_bytes(&self, data: impl Into<Vec<u8>>) -> Result<Transcript> {
+        let data = Arc::new(data.into());
+        let count_channels = {
+            let data = Arc::clone(&data);
+            move || audio::count_channels_bytes(&data)
+        };
+        self.transcribe_input(count_channels, move |processing, range| {
+            // Only copied when decoding more than one channel
+            let data = Arc::try_unwrap(data).unwrap_or_else(|data| data.as_ref().clone());
+            audio::load_audio_bytes(data, processing, range)
+        })
+        .await
+    }
+
+    /// Transcribe an encoded audio/video stream read to the end from `reader`.
+    ///
+    /// The reader is drained on a blocking thread, so it may do synchronous I/O.
+    pub async fn transcribe_reader(
+        &self,
+        mut reader: impl Read + Send + 'static,
+    ) -> Result<Transcript> {
+        if self.options.audio_processing.channels == ChannelMode::PerChannel {
+            // The channels are counted before decoding, so read it all first
+            let data = tokio::task::spawn_blocking(move || {
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data).map(|_| data)
+            })
+            .await
+            .map_err(|e| Error::Transcription(format!("audio loading task failed: {e}")))??;
+            return self.transcribe_bytes(data).await;
+        }
+
+        let samples = self
+            .load_audio(self.options.audio_processing.clone(), move |processing, range| {
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                audio::load_audio_bytes(data, processing, range)
+            })
+            .await?;
+
+        self.transcribe_owned(samples).await
+    }
+
+    /// Transcribe decoded PCM at any sample rate and channel count.
+    ///
+    /// Multi-channel `samples` are interleaved. They are downmixed to mono
+    /// (or split as [`AudioProcessing::channels`] says), resampled to 16kHz,
+    /// and run through the configured
+    /// [`AudioProcessing`](crate::AudioProcessing) steps.
+    pub async fn transcribe_pcm(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Transcript> {
+        let pcm: Arc<[f32]> = samples.into();
+        self.transcribe_input(
+            move || Ok(channels.max(1)),
+            move |processing, range| {
+                // Cut whole frames before converting
+                let pcm = match range {
+                    Some(range) => {
+                        let channels = channels.max(1) as usize;
+                        let frames = range.samples(sample_rate.max(1), pcm.len() / channels)?;
+                        &pcm[frames.start * channels..frames.end * channels]
+                    }
+                    None => &pcm[..],
+                };
+                audio::load_pcm(pcm, sample_rate, channels, processing)
+            },
+        )
+        .await
+    }
+
+    /// Transcribe audio samples that are already decoded.
+    /// Samples must be 16kHz mono f32.
+    pub async fn transcribe_samples(&self, samples: &[f32]) -> Result<Transcript> {
+        let samples = self.cut_samples(samples)?;
+        let mut transcript = self.transcribe_owned(samples).await?;
+        // Mono, so per-channel mode has just channel 0
+        if self.options.audio_processing.channels == ChannelMode::PerChannel {
+            channels::label(&mut transcript, 0, &self.options);
+        }
+        Ok(transcript)
+    }
+
+    /// Detect the spoken language of a local audio/video file without
+    /// transcribing it.
+    ///
+    /// Returns every language whisper knows with its probability, most likely
+    /// first. The windows examined are set by
+    /// [`TranscribeOptions::language_detection`]; by default the first 30
+    /// seconds. Only those windows are decoded, not the whole recording.
+    /// Fails for English-only models. In per-channel mode the channels are
+    /// mixed, giving one answer for the whole input.
+    pub async fn detect_language(&self, path: impl AsRef<Path>) -> Result<Vec<(Language, f32)>> {
+        let path = path.as_ref().to_path_buf();
+        let mut processing = self.options.audio_processing.clone();
+        if processing.channels == ChannelMode::PerChannel {
+            processing.channels = ChannelMode::Mix;
+        }
+        self.check_options()?;
+        self.options.check_cancelled()?;
+        self.options.report(Progress::new(Stage::DecodingAudio, 0.0));
+
+        let detection = self.options.language_detection.clone();
+        let range = self.options.time_range;
+        let (samples, windows) = tokio::task::spawn_blocking(move || {
+            load_detection_windows(&path, &processing, range, &detection)
+        })
+        .await
+        .map_err(|e| Error::Transcription(format!("audio loading task failed: {e}")))??;
+
+        self.options.check_cancelled()?;
+        self.options.report(Progress::new(Stage::DecodingAudio, 1.0));
+        self.detect_language_owned(samples, windows).await
+    }
+
+    /// Detect the spoken language of decoded 16kHz mono samples.
+    pub async fn detect_language_samples(&self, samples: &[f32]) -> Result<Vec<(Language, f32)>> {
+        let samples = self.cut_samples(samples)?;
+        let windows = transcribe::detection_windows(samples.len(), &self.options.language_detection);
+        self.detect_language_owned(samples, windows).await
+    }
+
+    /// Copy the configured time range of decoded 16kHz mono samples.
+    fn cut_samples(&self, samples: &[f32]) -> Result<Vec<f32>> {
+        if let ChannelMode::Channel(n @ 1..) = self.options.audio_processing.channels {
+            return Err(audio::missing_channel(n, 1));
+        }
+        audio::cut(samples.to_vec(), self.options.time_range)
+    }
+
+    /// Detect the language of the `windows` (sample ranges) of `samples`.
+    async fn detect_language_owned(
+        &self,
+        samples: Vec<f32>,
+        windows: Vec<Range<usize>>,
+    ) -> Result<Vec<(Language, f32)>> {
+        if !self.info.is_multilingual() {
+            return Err(Error::InvalidOption(format!(
+                "model {} is English-only and cannot detect languages",
+                self.options.model.name()
+            )));
+        }
+        let ctx = Arc::clone(&self.ctx);
+        let options = self.options.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut state = ctx.create_state()?;
+            let windows: Vec<&[f32]> = windows.into_iter().map(|w| &samples[w]).collect();
+            transcribe::detect_language_windows(&mut state, &windows, &options)
+        })
+        .await
+        .map_err(|e| Error::Transcription(format!("language detection task failed: {e}")))?
+    }
+
+    /// Start a live transcription session.
+    ///
+    /// Push audio chunks (e.g. from a microphone) into the returned
+    /// [`StreamInput`] and read provisional and final segments from the
+    /// [`TranscriptStream`]. Whisper runs on a dedicated thread, so this can be
+    /// called from async or sync code. Speaker identification is not applied.
+    ///
+    /// ```rust,no_run
+    /// # async fn run(engine: transcriber::Transcriber, mic: Vec<Vec<f32>>) -> transcriber::Result<()> {
+    /// use transcriber::{StreamEvent, StreamOptions};
+    ///
+    /// let (input, mut events) = engine.stream(StreamOptions::new().sample_rate(48_000)?)?;
+    /// std::thread::spawn(move || {
+    ///     for chunk in mic {
+    ///         if input.push(&chunk).is_err() {
+    ///             break;
+    ///         }
+    ///     }
+    /// });
+    /// while let Some(event) = events.next_event().await {
+    ///     match event? {
+    ///         StreamEvent::Final(seg) => println!("[{:.1}s] {}", seg.start, seg.text),
+    ///         StreamEvent::Provisional(segs) => eprintln!("… {} pending", segs.len()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream(&self, stream_options: StreamOptions) -> Result<(StreamInput, TranscriptStream)> {
+        self.check_options()?;
+        stream::start(Arc::clone(&self.ctx), self.options.clone(), stream_options)
+    }
+
+    /// Transcribe from a URL (downloads audio first, then transcribes).
+    #[cfg(feature = "download")]
+    pub async fn transcribe_url(&self, url: &str) -> Result<Transcript> {
+        // Create a unique temp directory per invocation so concurrent runs
+        // (even within the same process) don't collide.
+        let tmp_dir = std::env::temp_dir().join(format!(
+            "transcriber-{}-{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+        ));
+        let _cleanup = TempDirGuard(&tmp_dir);
+
+        self.check_options()?;
+        self.options.check_cancelled()?;
+        self.options.report(Progress::new(Stage::Downloading, 0.0));
+        let download_result = crate::download::download_audio(url, &tmp_dir).await?;
+        self.options.report(Progress::new(Stage::Downloading, 1.0));
+
+        let mut transcript = self.transcribe_file(&download_result.audio_path).await?;
+
+        // Attach source metadata
+        transcript.source_url = Some(url.to_string());
+        transcript.source_title = download_result.title;
+
+        Ok(transcript)
+    }
+
+    /// Load an input and transcribe it, or in [`ChannelMode::PerChannel`]
+    /// mode load and transcribe each of its `count_channels()` channels in turn
+    /// and interleave the results.
+    async fn transcribe_input<A: SampleSource + Send + 'static>(
+        &self,
+        count_channels: impl FnOnce() -> Result<u16> + Send + 'static,
+        load: impl FnOnce(&AudioProcessing, Option<TimeRange>) -> Result<A> + Clone + Send + 'static,
+    ) -> Result<Transcript> {
+        let processing = &self.options.audio_processing;
+        if processing.channels != ChannelMode::PerChannel {
+            // May share the input with `load`, which can then take it over
+            drop(count_channels);
+            let audio = self.load_audio(processing.clone(), load).await?;
+            return self.transcribe_owned(audio).await;
+        }
+
+        if self.options.speaker_identification || self.options.speaker_diarization {
+            return Err(Error::InvalidOption(
+                "per-channel transcription names speakers by channel and cannot be combined \
+                 with speaker identification or diarization"
+                    .into(),
+            ));
+        }
+        self.check_options()?;
+        let count = tokio::task::spawn_blocking(count_channels)
+            .await
+            .map_err(|e| Error::Transcription(format!("audio loading task failed: {e}")))??;
+
+        let mut transcripts = Vec::with_capacity(count as usize);
+        let mut segments = 0;
+        for channel in 0..count {
+            let processing = AudioProcessing {
+                channels: ChannelMode::Channel(channel),
+                ..processing.clone()
+            };
+            let mut engine = self.clone();
+            engine.options.progress = self
+                .options
+                .progress
+                .as_ref()
+                .map(|p| channels::progress(p, channel, count, segments));
+            let audio = engine.load_audio(processing, load.clone()).await?;
+            let mut transcript = engine.transcribe_owned(audio).await?;
+            channels::label(&mut transcript, channel, &self.options);
+            segments += transcript.segments.len();
+            transcripts.push(transcript);
+        }
+
+        let transcript = channels::interleave(transcripts, &self.options);
+        self.options.report(Progress {
+            segments: transcript.segments.len(),
+            position_secs: transcript.duration,
+            duration_secs: Some(transcript.duration),
+            ..Progress::new(Stage::Done, 1.0)
+        });
+        Ok(transcript)
+    }
+
+    /// Run a blocking audio loader with `processing` and the configured time
+    /// range, reporting the decoding stage and honouring cancellation around it.
+    async fn load_audio<A: SampleSource + Send + 'static>(
+        &self,
+        processing: AudioProcessing,
+        load: impl FnOnce(&AudioProcessing, Option<TimeRange>) -> Result<A> + Send + 'static,
+    ) -> Result<A> {
+        self.check_options()?;
+        self.options.check_cancelled()?;
+        self.options.report(Progress::new(Stage::DecodingAudio, 0.0));
+
+        let range = self.options.time_range;
+        let samples = tokio::task::spawn_blocking(move || load(&processing, range))
+            .await
+            .map_err(|e| Error::Transcription(format!("audio loading task failed: {e}")))??;
+
+        self.options.check_cancelled()?;
+        self.options.report(Progress {
+            duration_secs: Some(samples.len() as f64 / audio::WHISPER_SAMPLE_RATE as f64),
+            ..Progress::new(Stage::DecodingAudio, 1.0)
+        });
+        Ok(samples)
+    }
+
+    /// Run whisper (and speaker identification, if enabled) over decoded samples.
+    async fn transcribe_owned<A: SampleSource + Send + 'static>(
+        &self,
+        samples: A,
+    ) -> Result<Transcript> {
+        self.check_options()?;
+        // Transcribe (blocking CPU-intensive whisper inference). The samples are
+        // handed back from the task so speaker identification can reuse them.
+        let ctx = Arc::clone(&self.ctx);
+        let options = self.options.clone();
+        #[allow(unused_variables)]
+        let (mut transcript, samples) = tokio::task::spawn_blocking(move || {
+            let prompt = transcribe::prompt_tokens(&ctx, &options)?;
+            let transcript = match (&options.chunking, samples.as_slice()) {
+                (None, Some(samples)) => {
+                    let mut state = ctx.create_state()?;
+                    transcribe::transcribe_pass(&mut state, samples, &prompt, &options)?
+                }
+                // Audio on disk is only ever read a chunk at a time
+                (chunking, _) => {
+                    let chunking = chunking.clone().unwrap_or_default();
+                    chunked::transcribe(&ctx, &samples, &prompt, &options, &chunking)?
+                }
+            };
+            Ok::<_, Error>((transcript, samples))
+        })
+        .await
+        .map_err(|e| Error::Transcription(format!("transcription task failed: {e}")))??;
+
+        // Speaker identification / diarization pass (if enabled)
+        #[cfg(feature = "speaker-id")]
+        if self.options.speaker_identification || self.options.speaker_diarization {
+            self.options.check_cancelled()?;
+            let summary =
+                run_speaker_identification(&mut transcript, samples, &self.options).await?;
+            transcript.speaker_summary = Some(Box::new(SpeakerIdSummary {
+                identified: summary.identified,
+                unknown: summary.unknown,
+                skipped: summary.skipped,
+                merged: summary.merged,
+                smoothed: summary.smoothed,
+                speakers: summary.speakers,
+                unknown_clusters: summary.unknown_clusters.iter().map(|c| {
+                    (c.segment_count, c.total_duration, c.representative_start, c.representative_end)
+                }).collect(),
+            }));
+        }
+
+        // Back from the decoded range to time in the whole input
+        if let Some(range) = &self.options.time_range {
+            for seg in &mut transcript.segments {
+                seg.shift(range.start);
+            }
+            if let Some(summary) = &mut transcript.speaker_summary {
+                for cluster in &mut summary.unknown_clusters {
+                    cluster.2 += range.start;
+                    cluster.3 += range.start;
+                }
+            }
+        }
+
+        self.options.report(Progress {
+            segments: transcript.segments.len(),
+            position_secs: transcript.duration,
+            duration_secs: Some(transcript.duration),
+            ..Progress::new(Stage::Done, 1.0)
+        });
+
+        Ok(transcript)
+    }
+}
+
+/// Run speaker identification on a completed transcript, or diarization
+/// when `speaker_diarization` is set.
+///
+/// Returns a [`speaker::SpeakerSummary`](crate::speaker::SpeakerSummary) with
+/// identification statistics, including unknown cluster suggestions for
+/// potential enrollment.
+#[cfg(feature = "speaker-id")]
+async fn run_speaker_identification<A: SampleSource + Send + 'static>(
+    transcript: &mut Transcript,
+    samples: A,
+    options: &TranscribeOptions,
+) -> Result<crate::speaker::SpeakerSummary> {
+    use crate::speaker;
+
+    let cache_dir = options.resolve_cache_dir();
+    let fetch = options.speaker_model_fetch();
+    let model_path = match &options.speaker_model_path {
+        Some(p) => {
+            if let Some(expected) = &fetch.sha256 {
+                model::verify_sha256(p, expected).await?;
+            }
+            p.clone()
+        }
+        None => speaker::ensure_speaker_model_with(&cache_dir, &fetch).await?,
+    };
+    let profiles_path = options
+        .speaker_profiles_path
+        .clone()
+        .unwrap_or_else(speaker::default_profiles_path);
+
+    let threshold = options.speaker_threshold;
+    let mut segments = std::mem::take(&mut transcript.segments);
+    let duration = transcript.duration;
+    let options = options.clone();
+
+    // Speaker ID is CPU/GPU bound — run in blocking context
+    let (segments, summary) = tokio::task::spawn_blocking(
+        move || -> Result<(Vec<crate::types::Segment>, speaker::SpeakerSummary)> {
+            let mut identifier = speaker::SpeakerIdentifier::new(
+                &model_path,
+                &profiles_path,
+                threshold,
+                &[speaker::ExecutionProvider::Cpu],
+            )?;
+            let mut progress = |done: usize, total: usize| {
+                options.report(Progress {
+                    segments: total,
+                    position_secs: duration,
+                    duration_secs: Some(duration),
+                    ..Progress::new(Stage::IdentifyingSpeakers, done as f32 / total.max(1) as f32)
+                });
+                !options.is_cancelled()
+            };
+            let summary = if options.speaker_diarization {
+                let diarization = speaker::DiarizationOptions {
+                    num_speakers: options.num_speakers,
+                    match_profiles: options.speaker_identification,
+                };
+                identifier.diarize_segments_from(
+                    &mut segments,
+                    &samples,
+                    &diarization,
+                    progress,
+                )?
+            } else {
+                // With word timestamps, first cut segments where the speaker changes
+                if options.word_timestamps {
+                    segments =
+                        identifier.split_speaker_turns_from(segments, &samples, &mut progress)?;
+                }
+                identifier.identify_segments_from(&mut segments, &samples, progress)?
+            };
+            Ok((segments, summary))
+        },
+    )
+    .await
+    .map_err(|e| Error::Transcription(format!("speaker identification task failed: {e}")))??;
+
+    transcript.segments = segments;
+    Ok(summary)
+}
+
+/// Decode the windows of `range` of `path` that language detection listens
+/// to. Returns their samples back to back with each window's range in them.
+///
+/// Windows taken from the start need only that stretch decoded. Spread
+/// windows are decoded one by one at positions worked out from the duration;
+/// if ffprobe can't tell the duration, the whole range is decoded instead.
+fn load_detection_windows(
+    path: &Path,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+    detection: &LanguageDetection,
+) -> Result<(Vec<f32>, Vec<Range<usize>>)> {
+    let rate = audio::WHISPER_SAMPLE_RATE as f64;
+    let start = range.map_or(0.0, |r| r.start);
+    let end = range.and_then(|r| r.end);
+
+    // A single window always starts at the beginning
+    let spread = detection.spread && detection.windows > 1;
+    let duration = if spread { audio::probe_duration(path) } else { None };
+    let Some(duration) = duration else {
+        let end = if spread {
+            end
+        } else {
+            let stretch = f64::from(detection.window_secs) * f64::from(detection.windows);
+            Some(end.map_or(start + stretch, |end| end.min(start + stretch)))
+        };
+        let samples = audio::load_audio_range(path, processing, Some(TimeRange { start, end }))?;
+        let windows = transcribe::detection_windows(samples.len(), detection);
+        return Ok((samples, windows));
+    };
+
+    let length = TimeRange { start, end }.duration_within(duration)?;
+    let mut samples = Vec::new();
+    let mut windows = Vec::new();
+    for window in transcribe::detection_windows((length * rate) as usize, detection) {
+        let range = TimeRange {
+            start: start + window.start as f64 / rate,
+            end: Some(start + window.end as f64 / rate),
+        };
+        let decoded = audio::load_audio_range(path, processing, Some(range))?;
+        windows.push(samples.len()..samples.len() + decoded.len());
+        samples.extend(decoded);
+    }
+    Ok((samples, windows))
+}
+
+/// RAII guard that removes an entire temp directory when dropped.
+#[cfg(feature = "download")]
+struct TempDirGuard<'a>(&'a Path);
+
+#[cfg(feature = "download")]
+impl Drop for TempDirGuard<'_> {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            if let Err(e) = std::fs::remove_dir_all(self.0) {
+                tracing::warn!(path = %self.0.display(), error = %e, "failed to clean up temp dir");
+            }
+        }
+    }
+}
@@ -12,6 +12,16 @@ pub enum Error {
     #[error("model download failed: {0}")]
     ModelDownload(String),
 
+    #[error("{path} is not cached and offline mode is enabled")]
+    Offline { path: PathBuf },
+
+    #[error("checksum mismatch for {path}: expected SHA-256 {expected}, got {actual}")]
+    ModelChecksum {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
     #[error("audio decoding error: {0}")]
     AudioDecode(String),
 
@@ -27,6 +37,9 @@ pub enum Error {
     #[error("transcription error: {0}")]
     Transcription(String),
 
+    #[error("transcription cancelled")]
+    Cancelled,
+
     #[error("whisper error: {0}")]
     Whisper(#[from] whisper_rs::WhisperError),
 
@@ -72,6 +85,30 @@ mod tests {
         assert!(e.to_string().contains("/tmp/model.bin"));
     }
 
+    #[test]
+    fn test_error_display_model_checksum() {
+        let e = Error::ModelChecksum {
+            path: PathBuf::from("/tmp/ggml-tiny.bin"),
+            expected: "aa".repeat(32),
+            actual: "bb".repeat(32),
+        };
+        let msg = e.to_string();
+        assert!(msg.contains("/tmp/ggml-tiny.bin"));
+        assert!(msg.contains(&"aa".repeat(32)));
+        assert!(msg.contains(&"bb".repeat(32)));
+    }
+
+    #[test]
+    fn test_error_display_offline() {
+        let e = Error::Offline {
+            path: PathBuf::from("/cache/ggml-tiny.bin"),
+        };
+        assert_eq!(
+            e.to_string(),
+            "/cache/ggml-tiny.bin is not cached and offline mode is enabled"
+        );
+    }
+
     #[test]
     fn test_error_display_audio_not_found() {
         let e = Error::AudioNotFound {
@@ -103,6 +140,11 @@ mod tests {
         assert!(matches!(e, Error::Json(_)));
     }
 
+    #[test]
+    fn test_error_display_cancelled() {
+        assert_eq!(Error::Cancelled.to_string(), "transcription cancelled");
+    }
+
     #[test]
     fn test_error_debug_impl() {
         let e = Error::AudioDecode("test error".into());
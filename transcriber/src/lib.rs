@@ -20,23 +20,38 @@
 //! # }
 //! ```
 //!
+//! The free functions load the whisper model on every call. When transcribing
+//! many files, build a [`Transcriber`] once and reuse it.
+//!
 //! See the [README](https://github.com/claymore666/transcriber) for full documentation,
 //! feature flags, and CLI usage.
 
 pub(crate) mod audio;
+pub mod cache;
+pub(crate) mod channels;
+pub(crate) mod chunked;
 pub mod config;
 #[cfg(feature = "download")]
 pub(crate) mod download;
+pub(crate) mod engine;
 pub mod error;
 pub mod model;
+pub mod progress;
 #[cfg(feature = "speaker-id")]
 pub mod speaker;
+pub(crate) mod stream;
 pub(crate) mod transcribe;
 pub mod types;
 
-pub use config::{AudioProcessing, Language, Model, TranscribeOptions};
+pub use config::{
+    AudioBackend, AudioProcessing, ChannelMode, ChunkOptions, Conditioning, DecoderOptions, Language, LanguageDetection, Model, Quantization, QuantizedModel, StreamOptions,
+    TimeRange, TranscribeOptions,
+};
+pub use engine::Transcriber;
 pub use error::{Error, Result};
-pub use types::{Segment, SpeakerIdSummary, Transcript, Word};
+pub use progress::{CancellationToken, Progress, Stage};
+pub use stream::{StreamEvent, StreamInput, TranscriptStream};
+pub use types::{LanguageShare, Segment, SpeakerIdSummary, Transcript, Word};
 
 /// Test-only access to audio loading (not part of the public API).
 #[doc(hidden)]
@@ -49,58 +64,37 @@ pub fn __test_load_audio(
 
 use std::path::Path;
 
+/// Decode an audio/video file to the 16 kHz mono samples whisper and speaker
+/// enrollment take, with `processing` applied. Only `range` of the file is
+/// decoded, if given; ffmpeg seeks straight to its start.
+///
+/// This blocks until the file is decoded, so call it from
+/// `tokio::task::spawn_blocking` in async code.
+pub fn load_audio_range(
+    path: impl AsRef<Path>,
+    processing: &AudioProcessing,
+    range: Option<TimeRange>,
+) -> Result<Vec<f32>> {
+    audio::load_audio_range(path.as_ref(), processing, range)
+}
+
 /// Transcribe a local audio/video file with default options.
 pub async fn transcribe_file(path: impl AsRef<Path>) -> Result<Transcript> {
     transcribe_file_with_options(path, &TranscribeOptions::default()).await
 }
 
 /// Transcribe a local audio/video file with custom options.
+///
+/// Loads the model for this call only — use [`Transcriber`] to keep it loaded
+/// across many files.
 pub async fn transcribe_file_with_options(
     path: impl AsRef<Path>,
     options: &TranscribeOptions,
 ) -> Result<Transcript> {
-    let path = path.as_ref().to_path_buf();
-
-    // Ensure model is available
-    let cache_dir = options.resolve_cache_dir();
-    let model_path = model::ensure_model(&options.model, &cache_dir).await?;
-
-    // Load and process audio (blocking ffmpeg subprocess)
-    let processing = options.audio_processing.clone();
-    let samples = tokio::task::spawn_blocking({
-        let path = path.clone();
-        move || audio::load_audio(&path, &processing)
-    })
-    .await
-    .map_err(|e| Error::Transcription(format!("audio loading task failed: {e}")))??;
-
-    // Transcribe (blocking CPU-intensive whisper inference)
-    let options_clone = options.clone();
-    let samples_clone = samples.clone();
-    #[allow(unused_mut)]
-    let mut transcript = tokio::task::spawn_blocking(move || {
-        transcribe::transcribe_samples(&samples_clone, &model_path, &options_clone)
-    })
-    .await
-    .map_err(|e| Error::Transcription(format!("transcription task failed: {e}")))??;
-
-    // Speaker identification pass (if enabled)
-    #[cfg(feature = "speaker-id")]
-    if options.speaker_identification {
-        let summary = run_speaker_identification(&mut transcript, &samples, options).await?;
-        transcript.speaker_summary = Some(Box::new(SpeakerIdSummary {
-            identified: summary.identified,
-            unknown: summary.unknown,
-            skipped: summary.skipped,
-            merged: summary.merged,
-            smoothed: summary.smoothed,
-            unknown_clusters: summary.unknown_clusters.iter().map(|c| {
-                (c.segment_count, c.total_duration, c.representative_start, c.representative_end)
-            }).collect(),
-        }));
-    }
-
-    Ok(transcript)
+    Transcriber::new(options.clone())
+        .await?
+        .transcribe_file(path)
+        .await
 }
 
 /// Transcribe from a URL (downloads audio first, then transcribes).
@@ -115,121 +109,71 @@ pub async fn transcribe_with_options(
     url: &str,
     options: &TranscribeOptions,
 ) -> Result<Transcript> {
-    // Create a unique temp directory per invocation so concurrent runs
-    // (even within the same process) don't collide.
-    let tmp_dir = std::env::temp_dir().join(format!(
-        "transcriber-{}-{}",
-        std::process::id(),
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos()
-    ));
-    let _cleanup = TempDirGuard(&tmp_dir);
-
-    let download_result = download::download_audio(url, &tmp_dir).await?;
-
-    // Ensure model is available
-    let cache_dir = options.resolve_cache_dir();
-    let model_path = model::ensure_model(&options.model, &cache_dir).await?;
-
-    // Load and process audio (blocking ffmpeg subprocess)
-    let processing = options.audio_processing.clone();
-    let audio_path = download_result.audio_path.clone();
-    let samples = tokio::task::spawn_blocking(move || {
-        audio::load_audio(&audio_path, &processing)
-    })
-    .await
-    .map_err(|e| Error::Transcription(format!("audio loading task failed: {e}")))??;
-
-    // Transcribe (blocking CPU-intensive whisper inference)
-    let options_clone = options.clone();
-    let samples_clone = samples.clone();
-    #[allow(unused_mut)]
-    let mut transcript = tokio::task::spawn_blocking(move || {
-        transcribe::transcribe_samples(&samples_clone, &model_path, &options_clone)
-    })
-    .await
-    .map_err(|e| Error::Transcription(format!("transcription task failed: {e}")))??;
-
-    // Speaker identification pass (if enabled)
-    #[cfg(feature = "speaker-id")]
-    if options.speaker_identification {
-        let summary = run_speaker_identification(&mut transcript, &samples, options).await?;
-        transcript.speaker_summary = Some(Box::new(SpeakerIdSummary {
-            identified: summary.identified,
-            unknown: summary.unknown,
-            skipped: summary.skipped,
-            merged: summary.merged,
-            smoothed: summary.smoothed,
-            unknown_clusters: summary.unknown_clusters.iter().map(|c| {
-                (c.segment_count, c.total_duration, c.representative_start, c.representative_end)
-            }).collect(),
-        }));
-    }
-
-    // Attach source metadata
-    transcript.source_url = Some(url.to_string());
-    transcript.source_title = download_result.title;
-
-    Ok(transcript)
+    Transcriber::new(options.clone())
+        .await?
+        .transcribe_url(url)
+        .await
 }
 
-/// Run speaker identification on a completed transcript.
-///
-/// Returns a [`speaker::SpeakerSummary`] with identification statistics,
-/// including unknown cluster suggestions for potential enrollment.
-#[cfg(feature = "speaker-id")]
-async fn run_speaker_identification(
-    transcript: &mut Transcript,
-    samples: &[f32],
+/// Transcribe an encoded audio/video file held in memory with default options.
+pub async fn transcribe_bytes(data: impl Into<Vec<u8>>) -> Result<Transcript> {
+    transcribe_bytes_with_options(data, &TranscribeOptions::default()).await
+}
+
+/// Transcribe an encoded audio/video file held in memory with custom options.
+pub async fn transcribe_bytes_with_options(
+    data: impl Into<Vec<u8>>,
     options: &TranscribeOptions,
-) -> Result<speaker::SpeakerSummary> {
-    let cache_dir = options.resolve_cache_dir();
-    let model_path = match &options.speaker_model_path {
-        Some(p) => p.clone(),
-        None => speaker::ensure_speaker_model(&cache_dir).await?,
-    };
-    let profiles_path = options
-        .speaker_profiles_path
-        .clone()
-        .unwrap_or_else(speaker::default_profiles_path);
-
-    let threshold = options.speaker_threshold;
-    let samples = samples.to_vec();
-    let mut segments = std::mem::take(&mut transcript.segments);
-
-    // Speaker ID is CPU/GPU bound — run in blocking context
-    let (segments, summary) = tokio::task::spawn_blocking(
-        move || -> Result<(Vec<types::Segment>, speaker::SpeakerSummary)> {
-            let mut identifier = speaker::SpeakerIdentifier::new(
-                &model_path,
-                &profiles_path,
-                threshold,
-                &[speaker::ExecutionProvider::Cpu],
-            )?;
-            let summary = identifier.identify_segments(&mut segments, &samples)?;
-            Ok((segments, summary))
-        },
-    )
-    .await
-    .map_err(|e| Error::Transcription(format!("speaker identification task failed: {e}")))??;
-
-    transcript.segments = segments;
-    Ok(summary)
+) -> Result<Transcript> {
+    Transcriber::new(options.clone())
+        .await?
+        .transcribe_bytes(data)
+        .await
 }
 
-/// RAII guard that removes an entire temp directory when dropped.
-#[cfg(feature = "download")]
-struct TempDirGuard<'a>(&'a std::path::Path);
+/// Transcribe an encoded audio/video stream with default options.
+pub async fn transcribe_reader(reader: impl std::io::Read + Send + 'static) -> Result<Transcript> {
+    transcribe_reader_with_options(reader, &TranscribeOptions::default()).await
+}
 
-#[cfg(feature = "download")]
-impl Drop for TempDirGuard<'_> {
-    fn drop(&mut self) {
-        if self.0.exists() {
-            if let Err(e) = std::fs::remove_dir_all(self.0) {
-                tracing::warn!(path = %self.0.display(), error = %e, "failed to clean up temp dir");
-            }
-        }
-    }
+/// Transcribe an encoded audio/video stream with custom options.
+pub async fn transcribe_reader_with_options(
+    reader: impl std::io::Read + Send + 'static,
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    Transcriber::new(options.clone())
+        .await?
+        .transcribe_reader(reader)
+        .await
+}
+
+/// Transcribe decoded (interleaved) PCM with default options.
+pub async fn transcribe_pcm(samples: &[f32], sample_rate: u32, channels: u16) -> Result<Transcript> {
+    transcribe_pcm_with_options(samples, sample_rate, channels, &TranscribeOptions::default()).await
+}
+
+/// Transcribe decoded (interleaved) PCM with custom options.
+pub async fn transcribe_pcm_with_options(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    Transcriber::new(options.clone())
+        .await?
+        .transcribe_pcm(samples, sample_rate, channels)
+        .await
+}
+
+/// Detect the spoken language of a local audio/video file without
+/// transcribing it. Returns every language with its probability, most likely
+/// first; `options.language_detection` chooses the audio examined.
+pub async fn detect_language(
+    path: impl AsRef<Path>,
+    options: &TranscribeOptions,
+) -> Result<Vec<(Language, f32)>> {
+    Transcriber::new(options.clone())
+        .await?
+        .detect_language(path)
+        .await
 }
@@ -1,10 +1,15 @@
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use fs4::fs_std::FileExt;
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use tracing::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
 
-use crate::config::Model;
+use crate::cache;
+use crate::config::{Model, Quantization, TranscribeOptions};
 use crate::error::{Error, Result};
 
 const HUGGINGFACE_BASE: &str =
@@ -13,58 +18,226 @@ const HUGGINGFACE_BASE: &str =
 /// Maximum model file size (5 GB). The largest whisper model (large-v3) is ~2.9 GB.
 const MAX_MODEL_BYTES: u64 = 5_000_000_000;
 
+/// SHA-256 digests of the model files published in the whisper.cpp
+/// repository on HuggingFace, keyed by file name. Downloads, `verify_model`
+/// and `cache verify` check against this table. The speaker model is looked
+/// up by its file name as well, but has no entry yet, so it is only checked
+/// against a digest passed in [`FetchOptions::sha256`].
+const KNOWN_SHA256: &[(&str, &str)] = &[
+    ("ggml-tiny.bin", "be07e048e1e599ad46341c8d2a135645097a538221678b7acdd1b1919c6e1b21"),
+    ("ggml-tiny.en.bin", "921e4cf8686fdd993dcd081a5da5b6c365bfde1162e72b08d75ac75289920b1f"),
+    ("ggml-base.bin", "60ed5bc3dd14eea856493d334349b405782ddcaf0028d4b5df4088345fba2efe"),
+    ("ggml-base.en.bin", "a03779c86df3323075f5e796cb2ce5029f00ec8869eee3fdfb897afe36c6d002"),
+    ("ggml-small.bin", "1be3a9b2063867b937e64e2ec7483364a79917e157fa98c5d94b5c1fffea987b"),
+    ("ggml-small.en.bin", "c6138d6d58ecc8322097e0f987c32f1be8bb0a18532a3f88f734d1bbf9c41e5d"),
+    ("ggml-medium.bin", "6c14d5adee5f86394037b4e4e8b59f1673b6cee10e3cf0b11bbdbee79c156208"),
+    ("ggml-medium.en.bin", "cc37e93478338ec7700281a7ac30a10128929eb8f427dda2e865faa8f6da4356"),
+    ("ggml-large-v2.bin", "9a423fe4d40c82774b6af34115b8b935f34152246eb19e80e376071d3f999487"),
+    ("ggml-large-v3.bin", "64d182b440b98d5203c4f9bd541544d84c605196c4f7b845dfa11fb23594d1e2"),
+    ("ggml-large-v3-turbo.bin", "1fc70f774d38eb169993ac391eea357ef47c88757ef72ee5943879b7e8e2bc69"),
+];
+
+/// The built-in SHA-256 digest of a model, if one is known.
+/// Always `None` for [`Model::Custom`].
+pub fn known_sha256(model: &Model) -> Option<&'static str> {
+    if matches!(model, Model::Custom(_)) {
+        return None;
+    }
+    known_sha256_for_file(&model.filename())
+}
+
+/// The built-in SHA-256 digest for a cached file name.
+pub(crate) fn known_sha256_for_file(filename: &str) -> Option<&'static str> {
+    KNOWN_SHA256
+        .iter()
+        .find(|(name, _)| *name == filename)
+        .map(|(_, hash)| *hash)
+}
+
+/// Environment variable overriding the base URL whisper models are
+/// downloaded from (the file name is appended to it).
+pub const MODEL_BASE_URL_ENV: &str = "TRANSCRIBER_MODEL_BASE_URL";
+
+/// Environment variable overriding the base URL of the speaker model.
+pub const SPEAKER_MODEL_BASE_URL_ENV: &str = "TRANSCRIBER_SPEAKER_MODEL_BASE_URL";
+
+/// Environment variable that enables offline mode when set to `1` or `true`.
+pub const OFFLINE_ENV: &str = "TRANSCRIBER_OFFLINE";
+
+/// Where a model is downloaded from and how it is checked.
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Base URL the model file name is appended to. Falls back to the
+    /// family's environment variable, then to the public download location.
+    pub base_url: Option<String>,
+    /// Never touch the network: a model that isn't cached is an error.
+    /// Also enabled by [`OFFLINE_ENV`].
+    pub offline: bool,
+    /// Expected SHA-256, overriding the built-in digest (see [`known_sha256`]).
+    /// The only way to verify a [`Model::Custom`] file, which is then checked
+    /// on every call.
+    pub sha256: Option<String>,
+    /// Hash files found in the cache too, instead of trusting them.
+    pub verify_cached: bool,
+}
+
+impl FetchOptions {
+    /// The base URL to use: the option, then `env_var`. `None` means the
+    /// model's public download location.
+    pub(crate) fn resolve_base_url(&self, env_var: &str) -> Option<String> {
+        self.base_url
+            .clone()
+            .or_else(|| std::env::var(env_var).ok().filter(|v| !v.trim().is_empty()))
+            .map(|url| url.trim_end_matches('/').to_string())
+    }
+
+    /// Whether downloads are disabled, by the option or [`OFFLINE_ENV`].
+    pub(crate) fn is_offline(&self) -> bool {
+        self.offline
+            || std::env::var(OFFLINE_ENV)
+                .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true"))
+    }
+}
+
 /// Ensure a model is available locally, downloading if necessary.
 /// Returns the path to the model file.
+///
+/// Downloads are verified against the built-in digest; files already in the
+/// cache are trusted. Use [`ensure_model_with`] to verify them as well, or to
+/// change where models are downloaded from.
 pub async fn ensure_model(model: &Model, cache_dir: &Path) -> Result<PathBuf> {
+    ensure_model_with(model, cache_dir, &FetchOptions::default()).await
+}
+
+/// Like [`ensure_model`], with control over the download source, offline
+/// mode and checksum verification.
+pub async fn ensure_model_with(
+    model: &Model,
+    cache_dir: &Path,
+    fetch: &FetchOptions,
+) -> Result<PathBuf> {
     match model {
         Model::Custom(path) => {
-            if path.exists() {
-                Ok(path.clone())
-            } else {
-                Err(Error::ModelNotFound { path: path.clone() })
+            if !path.exists() {
+                return Err(Error::ModelNotFound { path: path.clone() });
             }
+            if let Some(expected) = &fetch.sha256 {
+                verify_sha256(path, expected).await?;
+            }
+            Ok(path.clone())
         }
         _ => {
             let filename = model.filename();
             let model_path = cache_dir.join(&filename);
+            let expected = fetch.sha256.as_deref().or_else(|| known_sha256(model));
 
             if model_path.exists() {
+                if let (true, Some(expected)) = (fetch.verify_cached, expected) {
+                    let actual = hash_file(&model_path).await?;
+                    check_sha256(&model_path, expected, &actual)?;
+                    cache::record_check(&model_path, &actual, expected).await;
+                }
                 info!(path = %model_path.display(), "model already cached");
+                cache::record_use(&model_path).await;
                 return Ok(model_path);
             }
 
+            if fetch.is_offline() {
+                return Err(Error::Offline { path: model_path });
+            }
+
             std::fs::create_dir_all(cache_dir).map_err(|e| {
                 Error::Model(format!("failed to create cache dir {}: {e}", cache_dir.display()))
             })?;
 
-            let url = format!("{HUGGINGFACE_BASE}/{filename}");
+            let url = match fetch.resolve_base_url(MODEL_BASE_URL_ENV) {
+                Some(base) => format!("{base}/{filename}"),
+                None => default_url(model),
+            };
             info!(%url, "downloading model");
-            download_model(&url, &model_path).await?;
+            download_model(&url, &model_path, expected).await?;
+            cache::record_use(&model_path).await;
 
             Ok(model_path)
         }
     }
 }
 
-async fn download_model(url: &str, dest: &Path) -> Result<()> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .send()
-        .await?
-        .error_for_status()
-        .map_err(|e| Error::ModelDownload(format!("HTTP error: {e}")))?;
+/// Public download location of a model. The distilled models live in
+/// distil-whisper's repositories, everything else in whisper.cpp's.
+fn default_url(model: &Model) -> String {
+    let filename = model.filename();
+    let repo = match model {
+        Model::DistilMediumEn => "distil-whisper/distil-medium.en",
+        Model::DistilLargeV2 => "distil-whisper/distil-large-v2",
+        Model::DistilLargeV3 => "distil-whisper/distil-large-v3-ggml",
+        _ => return format!("{HUGGINGFACE_BASE}/{filename}"),
+    };
+    format!("https://huggingface.co/{repo}/resolve/main/{filename}")
+}
 
-    let total_size = response.content_length().unwrap_or(0);
+/// Hex-encoded SHA-256 digest of a file.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
-    // Reject obviously wrong Content-Length before downloading
-    if total_size > MAX_MODEL_BYTES {
-        return Err(Error::ModelDownload(format!(
-            "model file too large ({total_size} bytes, max {MAX_MODEL_BYTES})"
-        )));
+/// Hash `path` on a blocking thread and compare it with `expected`
+/// (hex, case-insensitive).
+pub async fn verify_sha256(path: &Path, expected: &str) -> Result<()> {
+    let actual = hash_file(path).await?;
+    check_sha256(path, expected, &actual)
+}
+
+/// [`sha256_file`] on a blocking thread.
+pub(crate) async fn hash_file(path: &Path) -> Result<String> {
+    info!(path = %path.display(), "verifying model checksum");
+    tokio::task::spawn_blocking({
+        let path = path.to_path_buf();
+        move || sha256_file(&path)
+    })
+    .await
+    .map_err(|e| Error::Model(format!("checksum task failed: {e}")))?
+}
+
+pub(crate) fn check_sha256(path: &Path, expected: &str, actual: &str) -> Result<()> {
+    if actual.eq_ignore_ascii_case(expected) {
+        return Ok(());
     }
+    Err(Error::ModelChecksum {
+        path: path.to_path_buf(),
+        expected: expected.to_ascii_lowercase(),
+        actual: actual.to_string(),
+    })
+}
+
+/// Whether `s` looks like a hex-encoded SHA-256 digest.
+pub(crate) fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
 
-    let pb = ProgressBar::new(total_size);
+/// Download `url` to `dest`.
+///
+/// Data goes to `<dest>.part`, which survives failures so the next attempt
+/// (or the next run) can resume it with a `Range` request. Transient failures
+/// are retried with exponential backoff, and an exclusive lock on
+/// `<dest>.lock` stops concurrent processes from downloading the same file.
+/// `dest` only appears once the size (and `expected_sha256`, if given) checks
+/// out.
+pub(crate) async fn download_model(
+    url: &str,
+    dest: &Path,
+    expected_sha256: Option<&str>,
+) -> Result<()> {
+    let _lock = lock_download(dest).await?;
+    if dest.exists() {
+        info!(path = %dest.display(), "model downloaded by another process");
+        return Ok(());
+    }
+
+    let pb = ProgressBar::new(0);
     pb.set_style(
         ProgressStyle::default_bar()
             .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
@@ -78,73 +251,499 @@ async fn download_model(url: &str, dest: &Path) -> Result<()> {
             .unwrap_or_default()
     ));
 
-    // Write to a unique temp file to avoid concurrent download corruption.
-    // PartFileGuard ensures the .part file is cleaned up on any error.
-    let tmp_path = dest.with_extension(format!("bin.part.{}", std::process::id()));
-    let mut _part_guard = PartFileGuard { path: &tmp_path, armed: true };
-    let mut file = std::fs::File::create(&tmp_path)?;
-    let mut stream = response.bytes_stream();
-    let mut downloaded: u64 = 0;
-
-    use std::io::Write;
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        downloaded += chunk.len() as u64;
-        if downloaded > MAX_MODEL_BYTES {
-            return Err(Error::ModelDownload(format!(
-                "download exceeded max size ({MAX_MODEL_BYTES} bytes)"
-            )));
+    let part = sidecar(dest, ".part");
+    let client = reqwest::Client::new();
+    let mut attempt = 1;
+    loop {
+        match fetch(&client, url, dest, &pb).await {
+            Ok(()) => break,
+            Err(Failure::Transient(e)) if attempt < MAX_ATTEMPTS => {
+                let delay = retry_delay(attempt);
+                warn!(attempt, error = %e, "download interrupted, retrying in {}s", delay.as_secs());
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(Failure::Transient(e) | Failure::Fatal(e)) => return Err(e),
         }
-        file.write_all(&chunk)?;
-        pb.set_position(downloaded);
     }
 
-    file.flush()?;
-    drop(file);
-
-    // Verify the download before moving into cache
-    let file_size = std::fs::metadata(&tmp_path)?.len();
-    if file_size < 1_000_000 {
+    // Verify the download before moving into cache. A complete but bad file
+    // can't be resumed, so it is discarded.
+    let file_size = std::fs::metadata(&part)?.len();
+    if file_size < MIN_MODEL_BYTES {
+        discard_partial(dest);
         return Err(Error::ModelDownload(format!(
             "downloaded file too small ({file_size} bytes) — likely an error page"
         )));
     }
 
-    if total_size > 0 && file_size != total_size {
-        return Err(Error::ModelDownload(format!(
-            "file size mismatch (expected {total_size} bytes, got {file_size}) — download may be corrupt"
-        )));
-    }
+    let checked = match expected_sha256 {
+        Some(expected) => {
+            let actual = hash_file(&part).await?;
+            if let Err(e) = check_sha256(dest, expected, &actual) {
+                discard_partial(dest);
+                return Err(e);
+            }
+            Some((actual, expected))
+        }
+        None => None,
+    };
 
-    // All checks passed — move into cache (disarm the cleanup guard)
-    std::fs::rename(&tmp_path, dest)?;
-    _part_guard.disarm();
+    // All checks passed — move into cache
+    std::fs::rename(&part, dest)?;
+    std::fs::remove_file(sidecar(dest, ".part.json")).ok();
+    match checked {
+        Some((actual, expected)) => cache::record_check(dest, &actual, expected).await,
+        None => warn!(
+            path = %dest.display(),
+            "no known SHA-256 for this model, download not verified; pin one with --model-sha256"
+        ),
+    }
     pb.finish_with_message("Download complete");
 
     info!(path = %dest.display(), size = file_size, "model saved");
     Ok(())
 }
 
-/// RAII guard that removes a .part file on drop unless disarmed.
-struct PartFileGuard<'a> {
-    path: &'a Path,
-    armed: bool,
+/// Attempts per download before giving up on transient failures.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled for each one after it.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Anything smaller than this is an error page, not a model.
+const MIN_MODEL_BYTES: u64 = 1_000_000;
+
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY * 2u32.saturating_pow(attempt.saturating_sub(1))
 }
 
-impl<'a> PartFileGuard<'a> {
-    fn disarm(&mut self) {
-        self.armed = false;
-    }
+/// Validators of a partial download, stored in `<dest>.part.json` so a later
+/// run can tell whether the server still has the same file before resuming.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct PartMeta {
+    etag: Option<String>,
+    total: Option<u64>,
+}
+
+enum Failure {
+    /// Worth retrying: network errors, timeouts, 408/429/5xx responses.
+    Transient(Error),
+    Fatal(Error),
 }
 
-impl Drop for PartFileGuard<'_> {
+/// `dest` with `suffix` appended to its file name.
+fn sidecar(dest: &Path, suffix: &str) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    dest.with_file_name(name)
+}
+
+fn discard_partial(dest: &Path) {
+    std::fs::remove_file(sidecar(dest, ".part")).ok();
+    std::fs::remove_file(sidecar(dest, ".part.json")).ok();
+}
+
+/// How often to log while waiting for another process's download.
+const LOCK_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Give up waiting once the other process's `.part` file hasn't grown for
+/// this long: it has most likely hung.
+const LOCK_STALL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Exclusive lock on `<dest>.lock`. The lock file is deleted when the guard
+/// is dropped, so finished downloads leave nothing behind.
+struct DownloadLock {
+    file: std::fs::File,
+    path: PathBuf,
+}
+
+impl Drop for DownloadLock {
     fn drop(&mut self) {
-        if self.armed && self.path.exists() {
-            std::fs::remove_file(self.path).ok();
+        // Delete while still holding the lock; waiters notice and reopen
+        std::fs::remove_file(&self.path).ok();
+        FileExt::unlock(&self.file).ok();
+    }
+}
+
+/// Take an exclusive lock on `<dest>.lock`, waiting for any other process
+/// that holds it. Fails if that process stops making progress for
+/// [`LOCK_STALL_TIMEOUT`].
+async fn lock_download(dest: &Path) -> Result<DownloadLock> {
+    let path = sidecar(dest, ".lock");
+    let part = sidecar(dest, ".part");
+    let part_len = || std::fs::metadata(&part).map(|m| m.len()).ok();
+
+    let mut waiting_since: Option<tokio::time::Instant> = None;
+    let mut last_logged = tokio::time::Instant::now();
+    let mut progress = (part_len(), tokio::time::Instant::now());
+    loop {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        if file.try_lock_exclusive()? {
+            // The previous holder may have deleted the file we locked
+            if is_same_file(&file, &path) {
+                return Ok(DownloadLock { file, path });
+            }
+            continue;
+        }
+
+        let now = tokio::time::Instant::now();
+        let since = *waiting_since.get_or_insert_with(|| {
+            info!(path = %dest.display(), "waiting for another process to finish downloading");
+            now
+        });
+        let len = part_len();
+        if len != progress.0 {
+            progress = (len, now);
+        } else if now - progress.1 >= LOCK_STALL_TIMEOUT {
+            return Err(Error::ModelDownload(format!(
+                "another process holding {} made no progress for {}s; \
+                 stop it or delete the lock file",
+                path.display(),
+                LOCK_STALL_TIMEOUT.as_secs()
+            )));
+        }
+        if now - last_logged >= LOCK_LOG_INTERVAL {
+            info!(
+                path = %dest.display(),
+                waited_secs = (now - since).as_secs(),
+                downloaded = len.unwrap_or(0),
+                "still waiting for another process to finish downloading"
+            );
+            last_logged = now;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Whether `file` is still the file at `path`, i.e. it wasn't deleted (and
+/// maybe recreated) after it was opened.
+fn is_same_file(file: &std::fs::File, path: &Path) -> bool {
+    let (Ok(opened), Ok(current)) = (file.metadata(), std::fs::metadata(path)) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        opened.dev() == current.dev() && opened.ino() == current.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        // Open files can't be deleted on Windows
+        let _ = (opened, current);
+        true
+    }
+}
+
+/// One request for whatever is still missing from `<dest>.part`.
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    pb: &ProgressBar,
+) -> std::result::Result<(), Failure> {
+    use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, RANGE};
+    use reqwest::StatusCode;
+
+    let part = sidecar(dest, ".part");
+    let meta_path = sidecar(dest, ".part.json");
+    let meta: Option<PartMeta> = std::fs::read(&meta_path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .filter(|m: &PartMeta| m.etag.is_some() || m.total.is_some());
+    // Without validators there is no telling what a leftover part file holds
+    let mut offset = match &meta {
+        Some(_) => std::fs::metadata(&part).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+    // Already complete (the last run stopped before verifying it): asking
+    // for the bytes after the end would only get a 416 and a restart
+    if offset > 0 && meta.as_ref().and_then(|m| m.total) == Some(offset) {
+        info!(offset, "partial download already complete");
+        pb.set_length(offset);
+        pb.set_position(offset);
+        return Ok(());
+    }
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+        // If-Range only works with strong validators
+        if let Some(etag) = meta.as_ref().and_then(|m| m.etag.as_deref()) {
+            if !etag.starts_with("W/") {
+                request = request.header(IF_RANGE, etag);
+            }
+        }
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| Failure::Transient(e.into()))?;
+
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        discard_partial(dest);
+        return Err(Failure::Transient(Error::ModelDownload(
+            "server rejected the resume range, restarting".into(),
+        )));
+    }
+    if !status.is_success() {
+        let err = Error::ModelDownload(format!("HTTP error: {status} for url ({url})"));
+        let transient = status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS;
+        return Err(if transient { Failure::Transient(err) } else { Failure::Fatal(err) });
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let resumed = offset > 0 && status == StatusCode::PARTIAL_CONTENT;
+    let total = if resumed {
+        let meta = meta.unwrap_or_default();
+        let range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_content_range);
+        let same_file = meta.etag.is_none() || etag.is_none() || meta.etag == etag;
+        match range {
+            Some((start, total))
+                if start == offset
+                    && same_file
+                    && (meta.total.is_none() || total.is_none() || meta.total == total) =>
+            {
+                info!(offset, "resuming download");
+                total.or(meta.total)
+            }
+            _ => {
+                discard_partial(dest);
+                return Err(Failure::Transient(Error::ModelDownload(
+                    "server file changed since the partial download, restarting".into(),
+                )));
+            }
+        }
+    } else {
+        offset = 0;
+        response.content_length()
+    };
+
+    // Reject obviously wrong Content-Length before downloading
+    if let Some(total) = total.filter(|&t| t > MAX_MODEL_BYTES) {
+        return Err(Failure::Fatal(Error::ModelDownload(format!(
+            "model file too large ({total} bytes, max {MAX_MODEL_BYTES})"
+        ))));
+    }
+
+    let fatal = |e: std::io::Error| Failure::Fatal(e.into());
+    let mut file = if resumed {
+        std::fs::OpenOptions::new().append(true).open(&part).map_err(fatal)?
+    } else {
+        let meta = PartMeta { etag, total };
+        let json = serde_json::to_vec(&meta).map_err(|e| Failure::Fatal(e.into()))?;
+        std::fs::write(&meta_path, json).map_err(fatal)?;
+        std::fs::File::create(&part).map_err(fatal)?
+    };
+
+    pb.set_length(total.unwrap_or(0));
+    pb.set_position(offset);
+
+    use std::io::Write;
+    let mut downloaded = offset;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| Failure::Transient(e.into()))?;
+        downloaded += chunk.len() as u64;
+        if downloaded > MAX_MODEL_BYTES || total.is_some_and(|t| downloaded > t) {
+            drop(file);
+            discard_partial(dest);
+            return Err(Failure::Fatal(Error::ModelDownload(format!(
+                "download exceeded expected size ({} bytes)",
+                total.unwrap_or(MAX_MODEL_BYTES)
+            ))));
         }
+        file.write_all(&chunk).map_err(fatal)?;
+        pb.set_position(downloaded);
+    }
+    file.flush().map_err(fatal)?;
+
+    match total {
+        Some(total) if downloaded < total => Err(Failure::Transient(Error::ModelDownload(format!(
+            "connection closed after {downloaded} of {total} bytes"
+        )))),
+        _ => Ok(()),
     }
 }
 
+/// Parse `bytes <start>-<end>/<total>` into the start offset and the total
+/// size (`None` when the server sends `*`).
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    if end < start {
+        return None;
+    }
+    let total = match total.trim() {
+        "*" => None,
+        t => Some(t.parse().ok()?),
+    };
+    Some((start, total))
+}
+
+/// `ggml` magic at the start of whisper.cpp model files.
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+
+/// whisper.cpp stores the quantization format version in `ftype` scaled by this.
+const QNT_VERSION_FACTOR: u32 = 1000;
+
+/// English-only models have 51864 tokens, multilingual ones at least 51865.
+const MULTILINGUAL_VOCAB: u32 = 51_865;
+
+/// Hyperparameters from the header of a whisper ggml model file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ModelInfo {
+    pub n_vocab: u32,
+    pub n_audio_ctx: u32,
+    pub n_audio_state: u32,
+    pub n_audio_head: u32,
+    pub n_audio_layer: u32,
+    pub n_text_ctx: u32,
+    pub n_text_state: u32,
+    pub n_text_head: u32,
+    pub n_text_layer: u32,
+    pub n_mels: u32,
+    /// ggml weight type code (0 = f32, 1 = f16, 8 = q5_0, …).
+    pub ftype: u32,
+    /// Version of the quantization format (0 for unquantized files).
+    pub quantization_version: u32,
+}
+
+impl ModelInfo {
+    /// Whether the model can transcribe languages other than English.
+    pub fn is_multilingual(&self) -> bool {
+        self.n_vocab >= MULTILINGUAL_VOCAB
+    }
+
+    /// Model size judged by encoder depth ("tiny" … "large").
+    pub fn size_name(&self) -> &'static str {
+        match self.n_audio_layer {
+            4 => "tiny",
+            6 => "base",
+            12 => "small",
+            24 => "medium",
+            32 => "large",
+            _ => "unknown",
+        }
+    }
+
+    /// Name of the weight type, e.g. `f16` or `q5_0`.
+    pub fn weight_type(&self) -> &'static str {
+        match self.ftype {
+            0 => "f32",
+            1 => "f16",
+            2 => "q4_0",
+            3 => "q4_1",
+            7 => "q8_0",
+            8 => "q5_0",
+            9 => "q5_1",
+            10 => "q2_k",
+            11 => "q3_k",
+            12 => "q4_k",
+            13 => "q5_k",
+            14 => "q6_k",
+            _ => "unknown",
+        }
+    }
+
+    /// The weight type as a [`Quantization`], if it is one of the published ones.
+    pub fn quantization(&self) -> Option<Quantization> {
+        Quantization::parse_name(self.weight_type())
+    }
+
+    /// Reject options this model can't honour: an English-only model can
+    /// neither transcribe another language nor translate.
+    pub fn check_options(&self, options: &TranscribeOptions) -> Result<()> {
+        if self.is_multilingual() {
+            return Ok(());
+        }
+        if let Some(code) = options.language.code().filter(|c| *c != "en") {
+            return Err(Error::InvalidOption(format!(
+                "model {} is English-only and cannot transcribe language \"{code}\"",
+                options.model.name()
+            )));
+        }
+        if options.translate {
+            return Err(Error::InvalidOption(format!(
+                "model {} is English-only and cannot translate",
+                options.model.name()
+            )));
+        }
+        if options.language_per_window {
+            return Err(Error::InvalidOption(format!(
+                "model {} is English-only and cannot detect languages",
+                options.model.name()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Read the header of a whisper ggml model file.
+pub fn read_model_info(path: &Path) -> Result<ModelInfo> {
+    use std::io::Read;
+
+    let mut header = [0u8; 48];
+    let mut file = std::fs::File::open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Error::ModelNotFound {
+            path: path.to_path_buf(),
+        },
+        _ => e.into(),
+    })?;
+    let read = file.read(&mut header)?;
+    parse_model_header(&header[..read])
+        .map_err(|e| Error::Model(format!("{}: {e}", path.display())))
+}
+
+fn parse_model_header(bytes: &[u8]) -> std::result::Result<ModelInfo, String> {
+    if bytes.len() < 48 {
+        return Err("file too short for a whisper model header".into());
+    }
+    let field = |i: usize| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+    match field(0) {
+        GGML_MAGIC => {}
+        0x4655_4747 => return Err("GGUF files are not supported, use a whisper.cpp ggml model".into()),
+        _ => return Err("not a whisper ggml model (bad magic)".into()),
+    }
+
+    let ftype = field(11);
+    let info = ModelInfo {
+        n_vocab: field(1),
+        n_audio_ctx: field(2),
+        n_audio_state: field(3),
+        n_audio_head: field(4),
+        n_audio_layer: field(5),
+        n_text_ctx: field(6),
+        n_text_state: field(7),
+        n_text_head: field(8),
+        n_text_layer: field(9),
+        n_mels: field(10),
+        ftype: ftype % QNT_VERSION_FACTOR,
+        quantization_version: ftype / QNT_VERSION_FACTOR,
+    };
+    if info.n_vocab == 0 || info.n_audio_layer == 0 || info.n_text_layer == 0 {
+        return Err("invalid model header".into());
+    }
+    Ok(info)
+}
+
 /// List all cached models.
 pub fn list_cached_models(cache_dir: &Path) -> Vec<PathBuf> {
     let Ok(entries) = std::fs::read_dir(cache_dir) else {
@@ -242,4 +841,371 @@ mod tests {
 
         fs::remove_dir_all(&tmp).ok();
     }
+
+    #[test]
+    fn test_known_sha256_covers_builtin_models() {
+        for name in [
+            "tiny", "tiny.en", "base", "base.en", "small", "small.en", "medium", "medium.en",
+            "large-v2", "large-v3", "large-v3-turbo",
+        ] {
+            let model = Model::parse_name(name).unwrap();
+            let hash = known_sha256(&model).unwrap_or_else(|| panic!("no digest for {name}"));
+            assert!(is_sha256_hex(hash), "bad digest for {name}");
+        }
+        assert!(known_sha256(&Model::Custom(PathBuf::from("x.bin"))).is_none());
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let tmp = std::env::temp_dir().join("transcriber_test_sha256.bin");
+        fs::write(&tmp, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&tmp).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        fs::remove_file(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_ensure_model_custom_checksum() {
+        let tmp = std::env::temp_dir().join("transcriber_test_custom_checksum.bin");
+        fs::write(&tmp, b"abc").unwrap();
+        let model = Model::Custom(tmp.clone());
+
+        let good = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        let fetch = FetchOptions { sha256: Some(good.into()), ..Default::default() };
+        let result = ensure_model_with(&model, Path::new("/unused"), &fetch).await;
+        assert_eq!(result.unwrap(), tmp);
+
+        let bad = "00".repeat(32);
+        let fetch = FetchOptions { sha256: Some(bad.clone()), ..Default::default() };
+        let result = ensure_model_with(&model, Path::new("/unused"), &fetch).await;
+        match result.unwrap_err() {
+            Error::ModelChecksum { path, expected, actual } => {
+                assert_eq!(path, tmp);
+                assert_eq!(expected, bad);
+                assert!(actual.starts_with("ba7816bf"));
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+
+        fs::remove_file(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_ensure_model_verifies_cache_on_request() {
+        let tmp = std::env::temp_dir().join("transcriber_test_model_cache_verify");
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).unwrap();
+        fs::write(tmp.join("ggml-tiny.bin"), b"truncated model").unwrap();
+
+        // Cached files are trusted unless verification is requested
+        assert!(ensure_model(&Model::Tiny, &tmp).await.is_ok());
+        let fetch = FetchOptions { verify_cached: true, ..Default::default() };
+        let result = ensure_model_with(&Model::Tiny, &tmp, &fetch).await;
+        assert!(matches!(result, Err(Error::ModelChecksum { .. })));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(parse_content_range("bytes 100-199/1000"), Some((100, Some(1000))));
+        assert_eq!(parse_content_range("bytes 0-9/*"), Some((0, None)));
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(parse_content_range("bytes 10-5/1000"), None);
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        assert_eq!(retry_delay(1), RETRY_BASE_DELAY);
+        assert_eq!(retry_delay(2), RETRY_BASE_DELAY * 2);
+        assert_eq!(retry_delay(4), RETRY_BASE_DELAY * 8);
+    }
+
+    #[test]
+    fn test_default_url() {
+        assert_eq!(
+            default_url(&Model::parse_name("small-q5_1").unwrap()),
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small-q5_1.bin"
+        );
+        assert_eq!(
+            default_url(&Model::DistilLargeV3),
+            "https://huggingface.co/distil-whisper/distil-large-v3-ggml/resolve/main/ggml-distil-large-v3.bin"
+        );
+    }
+
+    #[test]
+    fn test_sidecar_names() {
+        let dest = Path::new("/cache/ggml-tiny.bin");
+        assert_eq!(sidecar(dest, ".part"), PathBuf::from("/cache/ggml-tiny.bin.part"));
+        assert_eq!(sidecar(dest, ".lock"), PathBuf::from("/cache/ggml-tiny.bin.lock"));
+    }
+
+    /// Serve `body` at every path over HTTP with `etag`, honouring `Range` and
+    /// `If-Range`. Returns the base URL and the request heads seen (request
+    /// line and headers, lowercased), one entry per request.
+    fn serve_file(
+        body: Vec<u8>,
+        etag: &'static str,
+    ) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = seen.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut headers = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                    headers.push_str(&line.to_ascii_lowercase());
+                }
+                log.lock().unwrap().push(headers.clone());
+
+                let header = |name: &str| {
+                    headers
+                        .lines()
+                        .find_map(|l| l.strip_prefix(name))
+                        .map(|v| v.trim().to_string())
+                };
+                let start = header("range:")
+                    .and_then(|r| r.strip_prefix("bytes=")?.trim_end_matches('-').parse().ok())
+                    .filter(|_| header("if-range:").is_none_or(|v| v == etag));
+                let response = match start {
+                    Some(start) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {start}-{}/{}\r\n",
+                        body.len() - start,
+                        body.len() - 1,
+                        body.len()
+                    ),
+                    None => format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n", body.len()),
+                };
+                let from = start.unwrap_or(0);
+                stream
+                    .write_all(format!("{response}ETag: {etag}\r\nConnection: close\r\n\r\n").as_bytes())
+                    .unwrap();
+                stream.write_all(&body[from..]).unwrap();
+            }
+        });
+        (url, seen)
+    }
+
+    fn test_body() -> Vec<u8> {
+        (0..1_200_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_partial_file() {
+        let body = test_body();
+        let (url, seen) = serve_file(body.clone(), "\"v1\"");
+        let tmp = std::env::temp_dir().join("transcriber_test_download_resume");
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).unwrap();
+        let dest = tmp.join("ggml-test.bin");
+
+        // A previous run stopped after 500 kB
+        fs::write(sidecar(&dest, ".part"), &body[..500_000]).unwrap();
+        let meta = PartMeta { etag: Some("\"v1\"".into()), total: Some(body.len() as u64) };
+        fs::write(sidecar(&dest, ".part.json"), serde_json::to_vec(&meta).unwrap()).unwrap();
+
+        let url = format!("{url}/ggml-test.bin");
+        download_model(&url, &dest, Some(&sha256_hex(&body))).await.unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert!(!sidecar(&dest, ".part").exists());
+        assert!(!sidecar(&dest, ".part.json").exists());
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert!(seen[0].contains("range: bytes=500000-"));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_download_complete_part_skips_request() {
+        let body = test_body();
+        let (url, seen) = serve_file(body.clone(), "\"v1\"");
+        let tmp = std::env::temp_dir().join("transcriber_test_download_complete");
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).unwrap();
+        let dest = tmp.join("ggml-test.bin");
+
+        // A previous run got every byte but stopped before verifying
+        fs::write(sidecar(&dest, ".part"), &body).unwrap();
+        let meta = PartMeta { etag: Some("\"v1\"".into()), total: Some(body.len() as u64) };
+        fs::write(sidecar(&dest, ".part.json"), serde_json::to_vec(&meta).unwrap()).unwrap();
+
+        let url = format!("{url}/ggml-test.bin");
+        download_model(&url, &dest, Some(&sha256_hex(&body))).await.unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert!(seen.lock().unwrap().is_empty());
+        assert!(!sidecar(&dest, ".lock").exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_download_restarts_when_file_changed() {
+        let body = test_body();
+        let (url, _) = serve_file(body.clone(), "\"v2\"");
+        let tmp = std::env::temp_dir().join("transcriber_test_download_changed");
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).unwrap();
+        let dest = tmp.join("ggml-test.bin");
+
+        // Partial data from an older version of the file
+        fs::write(sidecar(&dest, ".part"), vec![7u8; 500_000]).unwrap();
+        let meta = PartMeta { etag: Some("\"v1\"".into()), total: Some(body.len() as u64) };
+        fs::write(sidecar(&dest, ".part.json"), serde_json::to_vec(&meta).unwrap()).unwrap();
+
+        let url = format!("{url}/ggml-test.bin");
+        download_model(&url, &dest, Some(&sha256_hex(&body))).await.unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_download_checksum_mismatch_discards_file() {
+        let body = test_body();
+        let (url, _) = serve_file(body, "\"v1\"");
+        let tmp = std::env::temp_dir().join("transcriber_test_download_checksum");
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).unwrap();
+        let dest = tmp.join("ggml-test.bin");
+
+        let url = format!("{url}/ggml-test.bin");
+        let result = download_model(&url, &dest, Some(&"00".repeat(32))).await;
+        assert!(matches!(result, Err(Error::ModelChecksum { .. })));
+        assert!(!dest.exists());
+        assert!(!sidecar(&dest, ".part").exists());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_ensure_model_from_mirror() {
+        let body = test_body();
+        let (url, seen) = serve_file(body.clone(), "\"v1\"");
+        let tmp = std::env::temp_dir().join("transcriber_test_model_mirror");
+        let _ = fs::remove_dir_all(&tmp);
+
+        let fetch = FetchOptions {
+            base_url: Some(format!("{url}/models/whisper/")),
+            sha256: Some(sha256_hex(&body)),
+            ..Default::default()
+        };
+        let path = ensure_model_with(&Model::Base, &tmp, &fetch).await.unwrap();
+        assert_eq!(path, tmp.join("ggml-base.bin"));
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert!(seen.lock().unwrap()[0].starts_with("get /models/whisper/ggml-base.bin "));
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[tokio::test]
+    async fn test_ensure_model_offline() {
+        let tmp = std::env::temp_dir().join("transcriber_test_model_offline");
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).unwrap();
+
+        // The base URL would fail to resolve; offline mode never tries it
+        let fetch = FetchOptions {
+            base_url: Some("http://unreachable.invalid".into()),
+            offline: true,
+            ..Default::default()
+        };
+        let result = ensure_model_with(&Model::Small, &tmp, &fetch).await;
+        match result {
+            Err(Error::Offline { path }) => assert_eq!(path, tmp.join("ggml-small.bin")),
+            other => panic!("expected offline error, got {other:?}"),
+        }
+
+        // Cached models are still usable
+        fs::write(tmp.join("ggml-small.bin"), b"cached").unwrap();
+        assert!(ensure_model_with(&Model::Small, &tmp, &fetch).await.is_ok());
+
+        fs::remove_dir_all(&tmp).ok();
+    }
+
+    /// A ggml header with the given vocabulary, layers and raw `ftype`.
+    fn header(n_vocab: u32, n_audio_layer: u32, ftype: u32) -> Vec<u8> {
+        [GGML_MAGIC, n_vocab, 1500, 384, 6, n_audio_layer, 448, 384, 6, 4, 80, ftype]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_model_header() {
+        let info = parse_model_header(&header(51865, 4, 1)).unwrap();
+        assert!(info.is_multilingual());
+        assert_eq!(info.size_name(), "tiny");
+        assert_eq!(info.weight_type(), "f16");
+        assert_eq!(info.quantization(), Some(Quantization::F16));
+        assert_eq!(info.n_mels, 80);
+
+        // Quantized files carry the format version in ftype
+        let info = parse_model_header(&header(51864, 32, 2008)).unwrap();
+        assert!(!info.is_multilingual());
+        assert_eq!(info.size_name(), "large");
+        assert_eq!(info.weight_type(), "q5_0");
+        assert_eq!(info.quantization_version, 2);
+    }
+
+    #[test]
+    fn test_parse_model_header_rejects_other_files() {
+        assert!(parse_model_header(b"short").is_err());
+        let mut gguf = header(51865, 4, 1);
+        gguf[..4].copy_from_slice(b"GGUF");
+        assert!(parse_model_header(&gguf).unwrap_err().contains("GGUF"));
+        assert!(parse_model_header(&[0u8; 48]).unwrap_err().contains("magic"));
+    }
+
+    #[test]
+    fn test_read_model_info() {
+        let tmp = std::env::temp_dir().join("transcriber_test_model_info.bin");
+        let mut data = header(51864, 6, 1);
+        data.extend_from_slice(&[0u8; 100]);
+        fs::write(&tmp, data).unwrap();
+        let info = read_model_info(&tmp).unwrap();
+        assert_eq!(info.size_name(), "base");
+        fs::remove_file(&tmp).ok();
+
+        let missing = read_model_info(Path::new("/nonexistent/ggml.bin"));
+        assert!(matches!(missing, Err(Error::ModelNotFound { .. })));
+    }
+
+    #[test]
+    fn test_english_only_model_rejects_other_languages() {
+        let english_only = parse_model_header(&header(51864, 4, 1)).unwrap();
+        let opts = TranscribeOptions::new().model(Model::TinyEn);
+        assert!(english_only.check_options(&opts).is_ok());
+        assert!(english_only.check_options(&opts.clone().language("en").unwrap()).is_ok());
+
+        let german = opts.clone().language("de").unwrap();
+        let err = english_only.check_options(&german).unwrap_err();
+        assert!(err.to_string().contains("tiny.en is English-only"));
+        assert!(english_only.check_options(&opts.clone().translate(true)).is_err());
+
+        let per_window = opts.clone().language_per_window(true);
+        assert!(english_only.check_options(&per_window).is_err());
+
+        let multilingual = parse_model_header(&header(51865, 4, 1)).unwrap();
+        assert!(multilingual.check_options(&per_window).is_ok());
+        assert!(multilingual.check_options(&german.translate(true)).is_ok());
+    }
 }
@@ -0,0 +1,125 @@
+//! Progress reporting and cancellation for long transcriptions.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Pipeline stage a [`Progress`] update refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Fetching audio from a URL.
+    Downloading,
+    /// Decoding and resampling the input.
+    DecodingAudio,
+    /// Running whisper.
+    Transcribing,
+    /// Matching segments against enrolled speakers, or diarizing them.
+    IdentifyingSpeakers,
+    /// Finished; the transcript is about to be returned.
+    Done,
+}
+
+/// A progress update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub stage: Stage,
+    /// Completion of the current stage, from 0.0 to 1.0.
+    pub fraction: f32,
+    /// Segments transcribed so far.
+    pub segments: usize,
+    /// End of the latest transcribed segment, in seconds of audio.
+    pub position_secs: f64,
+    /// Total audio duration in seconds, once the audio is decoded.
+    pub duration_secs: Option<f64>,
+}
+
+impl Progress {
+    pub(crate) fn new(stage: Stage, fraction: f32) -> Self {
+        Self {
+            stage,
+            fraction,
+            segments: 0,
+            position_secs: 0.0,
+            duration_secs: None,
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new(Stage::DecodingAudio, 0.0)
+    }
+}
+
+/// Callback invoked with every [`Progress`] update. Cheap to clone.
+///
+/// Called from the thread doing the work, so keep it short (update a counter,
+/// send on a channel).
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, progress: &Progress) {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Token for aborting a running transcription.
+///
+/// Clone it into [`TranscribeOptions::cancellation_token`](crate::TranscribeOptions::cancellation_token)
+/// and call [`cancel`](Self::cancel) from anywhere; the transcription stops at
+/// the next checkpoint (whisper checks between compute steps) and returns
+/// [`Error::Cancelled`](crate::Error::Cancelled).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation. Affects every clone of this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_cancellation_token_shared() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_progress_callback_invoked() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let cb = ProgressCallback::new({
+            let seen = Arc::clone(&seen);
+            move |p| seen.lock().unwrap().push(p.stage)
+        });
+        cb.clone().call(&Progress::new(Stage::Transcribing, 0.5));
+        assert_eq!(*seen.lock().unwrap(), vec![Stage::Transcribing]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use crate::audio::WHISPER_SAMPLE_RATE;
 
 /// Minimum segment duration in seconds for reliable speaker embedding.
@@ -9,12 +11,18 @@ pub const MIN_SEGMENT_DURATION: f64 = 1.0;
 /// Returns a slice of the audio buffer corresponding to `[start, end)` in seconds.
 /// Returns `None` if the resulting slice is too short for reliable embedding.
 pub fn extract_slice(audio: &[f32], start: f64, end: f64) -> Option<&[f32]> {
+    slice_range(audio.len(), start, end).map(|range| &audio[range])
+}
+
+/// Sample range of `[start, end)` seconds within audio of `len` samples, as
+/// used by [`extract_slice`].
+pub fn slice_range(len: usize, start: f64, end: f64) -> Option<Range<usize>> {
     let sample_rate = WHISPER_SAMPLE_RATE as f64;
     let start_sample = (start * sample_rate) as usize;
     let end_sample = (end * sample_rate) as usize;
 
-    let start_sample = start_sample.min(audio.len());
-    let end_sample = end_sample.min(audio.len());
+    let start_sample = start_sample.min(len);
+    let end_sample = end_sample.min(len);
 
     if end_sample <= start_sample {
         return None;
@@ -25,7 +33,7 @@ pub fn extract_slice(audio: &[f32], start: f64, end: f64) -> Option<&[f32]> {
         return None;
     }
 
-    Some(&audio[start_sample..end_sample])
+    Some(start_sample..end_sample)
 }
 
 #[cfg(test)]
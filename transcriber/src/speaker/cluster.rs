@@ -0,0 +1,211 @@
+//! Agglomerative clustering of speaker embeddings.
+//!
+//! Average linkage on cosine similarity, computed with the nearest-neighbour
+//! chain algorithm: O(n²) time and memory for the full dendrogram, which is
+//! then cut either at a fixed cluster count or at a similarity threshold.
+
+use super::profile::cosine_similarity;
+
+/// Where to cut the dendrogram.
+#[derive(Debug, Clone, Copy)]
+pub enum ClusterStop {
+    /// Merge until exactly this many clusters remain (or fewer items exist).
+    Count(usize),
+    /// Merge while the average similarity between clusters is at least this.
+    Threshold(f32),
+}
+
+/// Cluster embeddings, returning a cluster index per embedding.
+///
+/// Cluster indices are dense (`0..k`) and numbered by first occurrence, so
+/// the first embedding is always in cluster 0.
+pub fn agglomerative(embeddings: &[Vec<f32>], stop: ClusterStop) -> Vec<usize> {
+    let n = embeddings.len();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let merges = merge_sequence(embeddings);
+
+    // Replay merges from most to least similar with union-find
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut clusters = n;
+    for &(a, b, sim) in &merges {
+        let keep_going = match stop {
+            ClusterStop::Count(k) => clusters > k.max(1),
+            ClusterStop::Threshold(t) => sim >= t,
+        };
+        if !keep_going {
+            break;
+        }
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        if ra != rb {
+            parent[rb] = ra;
+            clusters -= 1;
+        }
+    }
+
+    // Renumber roots by first occurrence
+    let mut label_of_root = vec![usize::MAX; n];
+    let mut next = 0;
+    (0..n)
+        .map(|i| {
+            let root = find(&mut parent, i);
+            if label_of_root[root] == usize::MAX {
+                label_of_root[root] = next;
+                next += 1;
+            }
+            label_of_root[root]
+        })
+        .collect()
+}
+
+/// Full average-linkage dendrogram as `(item_a, item_b, similarity)` merges,
+/// sorted from most to least similar. Items are representatives of the two
+/// clusters being joined.
+fn merge_sequence(embeddings: &[Vec<f32>]) -> Vec<(usize, usize, f32)> {
+    let n = embeddings.len();
+    let mut sim = vec![0.0f32; n * n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let s = cosine_similarity(&embeddings[i], &embeddings[j]);
+            sim[i * n + j] = s;
+            sim[j * n + i] = s;
+        }
+    }
+
+    let mut size = vec![1usize; n];
+    let mut active = vec![true; n];
+    let mut remaining = n;
+    let mut chain: Vec<usize> = Vec::with_capacity(n);
+    let mut merges = Vec::with_capacity(n.saturating_sub(1));
+
+    while remaining > 1 {
+        if chain.is_empty() {
+            chain.push(active.iter().position(|&a| a).expect("an active cluster"));
+        }
+
+        let a = *chain.last().unwrap();
+        let prev = chain.len().checked_sub(2).map(|i| chain[i]);
+
+        // Most similar active neighbour; ties go to the previous chain element
+        // so the chain is guaranteed to terminate.
+        let mut best = None;
+        let mut best_sim = f32::NEG_INFINITY;
+        for c in 0..n {
+            if c == a || !active[c] {
+                continue;
+            }
+            let s = sim[a * n + c];
+            if s > best_sim || (s == best_sim && Some(c) == prev) {
+                best_sim = s;
+                best = Some(c);
+            }
+        }
+        let b = best.expect("at least two active clusters");
+
+        if Some(b) == prev {
+            chain.pop();
+            chain.pop();
+
+            // Merge b into a (Lance–Williams update for average linkage)
+            let (na, nb) = (size[a] as f32, size[b] as f32);
+            for c in 0..n {
+                if !active[c] || c == a || c == b {
+                    continue;
+                }
+                let s = (na * sim[a * n + c] + nb * sim[b * n + c]) / (na + nb);
+                sim[a * n + c] = s;
+                sim[c * n + a] = s;
+            }
+            size[a] += size[b];
+            active[b] = false;
+            remaining -= 1;
+            merges.push((a, b, best_sim));
+        } else {
+            chain.push(b);
+        }
+    }
+
+    // Average linkage is reducible, so sorting the merges yields a valid order
+    merges.sort_by(|x, y| y.2.total_cmp(&x.2));
+    merges
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Mean of the L2-normalized members of each cluster.
+pub fn centroids(embeddings: &[Vec<f32>], labels: &[usize]) -> Vec<Vec<f32>> {
+    let k = labels.iter().copied().max().map_or(0, |m| m + 1);
+    let dim = embeddings.first().map_or(0, Vec::len);
+    let mut sums = vec![vec![0.0f32; dim]; k];
+    for (emb, &label) in embeddings.iter().zip(labels) {
+        let norm = emb.iter().map(|v| v * v).sum::<f32>().sqrt().max(f32::EPSILON);
+        for (s, v) in sums[label].iter_mut().zip(emb) {
+            *s += v / norm;
+        }
+    }
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three tight groups around orthogonal axes.
+    fn three_groups() -> Vec<Vec<f32>> {
+        vec![
+            vec![1.0, 0.1, 0.0],
+            vec![0.0, 1.0, 0.1],
+            vec![0.9, 0.0, 0.1],
+            vec![0.1, 0.0, 1.0],
+            vec![0.0, 0.9, 0.0],
+            vec![1.0, 0.0, 0.05],
+            vec![0.0, 0.1, 0.9],
+        ]
+    }
+
+    #[test]
+    fn test_threshold_finds_groups() {
+        let labels = agglomerative(&three_groups(), ClusterStop::Threshold(0.5));
+        assert_eq!(labels, vec![0, 1, 0, 2, 1, 0, 2]);
+    }
+
+    #[test]
+    fn test_count_forces_cluster_number() {
+        let labels = agglomerative(&three_groups(), ClusterStop::Count(2));
+        let k = labels.iter().max().unwrap() + 1;
+        assert_eq!(k, 2);
+        // Members of a tight group stay together
+        assert_eq!(labels[0], labels[2]);
+        assert_eq!(labels[0], labels[5]);
+
+        let labels = agglomerative(&three_groups(), ClusterStop::Count(1));
+        assert!(labels.iter().all(|&l| l == 0));
+    }
+
+    #[test]
+    fn test_count_larger_than_items() {
+        let labels = agglomerative(&three_groups()[..2], ClusterStop::Count(5));
+        assert_eq!(labels, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_empty_and_single() {
+        assert!(agglomerative(&[], ClusterStop::Count(2)).is_empty());
+        assert_eq!(agglomerative(&[vec![1.0, 0.0]], ClusterStop::Threshold(0.5)), vec![0]);
+    }
+
+    #[test]
+    fn test_centroids_normalized_mean() {
+        let embs = vec![vec![2.0, 0.0], vec![0.0, 3.0], vec![4.0, 0.0]];
+        let c = centroids(&embs, &[0, 1, 0]);
+        assert_eq!(c, vec![vec![2.0, 0.0], vec![0.0, 1.0]]);
+    }
+}
@@ -0,0 +1,396 @@
+//! Unsupervised speaker diarization.
+//!
+//! Speech is cut into short overlapping windows, each window is embedded and
+//! the embeddings are clustered. Every transcript segment then takes the
+//! cluster its windows voted for, labelled `SPEAKER_00`, `SPEAKER_01`, … in
+//! order of first appearance — or the name of an enrolled profile the cluster
+//! matches.
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::types::Segment;
+
+use super::audio_slice::MIN_SEGMENT_DURATION;
+use super::cluster::{self, ClusterStop};
+use super::profile::{cosine_similarity, SpeakerProfile};
+
+/// Length of an embedding window in seconds.
+pub const WINDOW_SECS: f64 = 1.5;
+
+/// Hop between consecutive windows within a segment.
+pub const HOP_SECS: f64 = 0.75;
+
+/// Average similarity at which clusters stop merging when the number of
+/// speakers is not given.
+pub const AUTO_THRESHOLD: f32 = 0.5;
+
+/// Upper bound on windows fed to the O(n²) clustering. Longer recordings are
+/// clustered on an even subsample; the remaining windows join the nearest
+/// cluster.
+pub const MAX_CLUSTER_WINDOWS: usize = 2_000;
+
+/// Options for [`SpeakerIdentifier::diarize_segments`](super::SpeakerIdentifier::diarize_segments).
+#[derive(Debug, Clone, Default)]
+pub struct DiarizationOptions {
+    /// Exact number of speakers, or `None` to estimate it.
+    pub num_speakers: Option<usize>,
+    /// Name clusters after enrolled profiles they match (at the identifier's
+    /// threshold). Unmatched clusters keep their `SPEAKER_NN` label.
+    pub match_profiles: bool,
+}
+
+/// An embedding window, in samples of the full recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    /// Index of the segment the window belongs to.
+    pub segment: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Lay out embedding windows over the speech segments.
+///
+/// Segments with high no-speech probability get no windows. Segments shorter
+/// than one window get a single window covering the segment, widened
+/// symmetrically to [`MIN_SEGMENT_DURATION`] if needed.
+pub fn plan_windows(segments: &[Segment], audio_len: usize) -> Vec<Window> {
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    let window = (WINDOW_SECS * rate) as usize;
+    let hop = (HOP_SECS * rate) as usize;
+    let min_len = (MIN_SEGMENT_DURATION * rate) as usize;
+
+    let mut windows = Vec::new();
+    for (i, seg) in segments.iter().enumerate() {
+        if seg.no_speech_probability > 0.6 {
+            continue;
+        }
+
+        let start = ((seg.start.max(0.0) * rate) as usize).min(audio_len);
+        let end = ((seg.end.max(0.0) * rate) as usize).min(audio_len);
+        if end <= start {
+            continue;
+        }
+
+        if end - start < window {
+            let (mut start, mut end) = (start, end);
+            if end - start < min_len {
+                let mid = (start + end) / 2;
+                end = (mid + min_len / 2).max(min_len).min(audio_len);
+                start = end.saturating_sub(min_len);
+            }
+            if end - start >= min_len {
+                windows.push(Window { segment: i, start, end });
+            }
+            continue;
+        }
+
+        let mut pos = start;
+        while pos + window <= end {
+            windows.push(Window { segment: i, start: pos, end: pos + window });
+            pos += hop;
+        }
+        // Cover the tail of the segment
+        if pos - hop + window < end {
+            windows.push(Window { segment: i, start: end - window, end });
+        }
+    }
+    windows
+}
+
+/// Cluster window embeddings into speakers.
+///
+/// Returns a cluster index per embedding, numbered by first occurrence. With
+/// an automatic speaker count, clusters holding only a sliver of the windows
+/// are folded into their nearest neighbour.
+pub fn cluster_windows(embeddings: &[Vec<f32>], num_speakers: Option<usize>) -> Vec<usize> {
+    if embeddings.is_empty() {
+        return Vec::new();
+    }
+
+    let stride = embeddings.len().div_ceil(MAX_CLUSTER_WINDOWS);
+    let sample: Vec<Vec<f32>> = embeddings.iter().step_by(stride).cloned().collect();
+
+    let stop = match num_speakers {
+        Some(k) => ClusterStop::Count(k),
+        None => ClusterStop::Threshold(AUTO_THRESHOLD),
+    };
+    let labels = cluster::agglomerative(&sample, stop);
+    let mut centroids = cluster::centroids(&sample, &labels);
+
+    if num_speakers.is_none() && centroids.len() > 1 {
+        let mut counts = vec![0usize; centroids.len()];
+        for &l in &labels {
+            counts[l] += 1;
+        }
+        let min_windows = (sample.len() / 50).max(2);
+        let largest = (0..counts.len()).max_by_key(|&c| counts[c]).unwrap_or(0);
+        let mut c = 0;
+        centroids.retain(|_| {
+            let keep = counts[c] >= min_windows || c == largest;
+            c += 1;
+            keep
+        });
+    }
+
+    // Assign every window (including those left out of the sample) to the
+    // nearest centroid, then renumber by first occurrence
+    let nearest: Vec<usize> = embeddings
+        .iter()
+        .map(|emb| {
+            (0..centroids.len())
+                .max_by(|&a, &b| {
+                    cosine_similarity(emb, &centroids[a]).total_cmp(&cosine_similarity(emb, &centroids[b]))
+                })
+                .unwrap_or(0)
+        })
+        .collect();
+    renumber(&nearest)
+}
+
+fn renumber(labels: &[usize]) -> Vec<usize> {
+    let mut map = std::collections::HashMap::new();
+    labels
+        .iter()
+        .map(|&l| {
+            let next = map.len();
+            *map.entry(l).or_insert(next)
+        })
+        .collect()
+}
+
+/// Pick each segment's cluster by majority vote of its windows, weighted by
+/// how much of each window lies inside the segment.
+pub fn segment_votes(
+    segments: &[Segment],
+    windows: &[Window],
+    labels: &[usize],
+    num_clusters: usize,
+) -> Vec<Option<usize>> {
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    let mut weights = vec![vec![0usize; num_clusters]; segments.len()];
+    for (w, &label) in windows.iter().zip(labels) {
+        let seg = &segments[w.segment];
+        let seg_start = (seg.start.max(0.0) * rate) as usize;
+        let seg_end = (seg.end.max(0.0) * rate) as usize;
+        let overlap = w.end.min(seg_end).saturating_sub(w.start.max(seg_start));
+        // Widened windows of very short segments still count
+        weights[w.segment][label] += overlap.max(1);
+    }
+
+    weights
+        .iter()
+        .map(|w| {
+            (0..num_clusters)
+                .filter(|&c| w[c] > 0)
+                .max_by_key(|&c| (w[c], std::cmp::Reverse(c)))
+        })
+        .collect()
+}
+
+/// Match cluster centroids to enrolled profiles, one-to-one.
+///
+/// Pairs are taken greedily from the most similar down; a cluster is only
+/// named after a profile when their similarity reaches `threshold`.
+pub fn match_profiles(
+    centroids: &[Vec<f32>],
+    profiles: &[SpeakerProfile],
+    threshold: f32,
+) -> Vec<Option<usize>> {
+    let mut pairs: Vec<(usize, usize, f32)> = Vec::new();
+    for (c, centroid) in centroids.iter().enumerate() {
+        for (p, profile) in profiles.iter().enumerate() {
+            let sim = cosine_similarity(centroid, &profile.centroid);
+            if sim >= threshold {
+                pairs.push((c, p, sim));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut matched = vec![None; centroids.len()];
+    let mut taken = vec![false; profiles.len()];
+    for (c, p, _) in pairs {
+        if matched[c].is_none() && !taken[p] {
+            matched[c] = Some(p);
+            taken[p] = true;
+        }
+    }
+    matched
+}
+
+/// Display name per cluster: the matched profile's short name, otherwise
+/// `SPEAKER_NN` numbered by first appearance in the transcript.
+pub fn cluster_names(
+    votes: &[Option<usize>],
+    matches: &[Option<usize>],
+    profiles: &[SpeakerProfile],
+) -> Vec<String> {
+    let mut names: Vec<Option<String>> = matches
+        .iter()
+        .map(|m| m.map(|p| profiles[p].short_name.clone()))
+        .collect();
+
+    let mut next = 0;
+    for &c in votes.iter().flatten() {
+        if names[c].is_none() {
+            names[c] = Some(format!("SPEAKER_{next:02}"));
+            next += 1;
+        }
+    }
+    // Clusters that won no segment keep a stable, unused label
+    names
+        .into_iter()
+        .map(|n| {
+            n.unwrap_or_else(|| {
+                next += 1;
+                format!("SPEAKER_{:02}", next - 1)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg(start: f64, end: f64) -> Segment {
+        Segment {
+            start,
+            end,
+            text: String::new(),
+            speaker_turn: false,
+            no_speech_probability: 0.0,
+            words: None,
+            speaker_id: None,
+            speaker_confidence: None,
+            language: None,
+            channel: None,
+        }
+    }
+
+    fn profile(name: &str, centroid: Vec<f32>) -> SpeakerProfile {
+        SpeakerProfile {
+            name: name.into(),
+            short_name: name.into(),
+            enrolled_at: String::new(),
+            embeddings: vec![centroid.clone()],
+            centroid,
+        }
+    }
+
+    const SR: usize = WHISPER_SAMPLE_RATE as usize;
+
+    #[test]
+    fn test_plan_windows_long_segment() {
+        // 4s segment: windows at 0, 0.75, 1.5, 2.25 and a tail at 2.5
+        let windows = plan_windows(&[seg(0.0, 4.0)], 10 * SR);
+        let starts: Vec<usize> = windows.iter().map(|w| w.start).collect();
+        assert_eq!(starts, vec![0, 12_000, 24_000, 36_000, 40_000]);
+        assert!(windows.iter().all(|w| w.end - w.start == 24_000));
+        assert_eq!(windows.last().unwrap().end, 4 * SR);
+    }
+
+    #[test]
+    fn test_plan_windows_exact_fit_has_no_tail() {
+        let windows = plan_windows(&[seg(0.0, 2.25)], 10 * SR);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[1].end, 36_000);
+    }
+
+    #[test]
+    fn test_plan_windows_short_segments() {
+        let windows = plan_windows(&[seg(2.0, 3.2), seg(5.0, 5.4)], 10 * SR);
+        assert_eq!(windows.len(), 2);
+        // Between one and 1.5 seconds: the segment itself
+        assert_eq!((windows[0].start, windows[0].end), (32_000, 51_200));
+        // Under a second: widened around the middle
+        assert_eq!(windows[1].segment, 1);
+        assert_eq!(windows[1].end - windows[1].start, SR);
+        assert_eq!(windows[1].start, 5 * SR + 3_200 - SR / 2);
+    }
+
+    #[test]
+    fn test_plan_windows_clamps_to_audio() {
+        // Short segment at the very start and one running past the end
+        let windows = plan_windows(&[seg(0.0, 0.2), seg(2.5, 9.0)], 3 * SR);
+        assert_eq!((windows[0].start, windows[0].end), (0, SR));
+        assert_eq!((windows[1].start, windows[1].end), (2 * SR, 3 * SR));
+    }
+
+    #[test]
+    fn test_plan_windows_skips_no_speech() {
+        let mut s = seg(0.0, 3.0);
+        s.no_speech_probability = 0.9;
+        assert!(plan_windows(&[s], 10 * SR).is_empty());
+        // Too little audio for even one window
+        assert!(plan_windows(&[seg(0.0, 0.5)], SR / 2).is_empty());
+    }
+
+    #[test]
+    fn test_cluster_windows_auto_and_fixed() {
+        let a = vec![1.0, 0.0, 0.0];
+        let b = vec![0.0, 1.0, 0.0];
+        let embs = vec![a.clone(), a.clone(), b.clone(), b.clone(), a.clone(), b.clone()];
+        assert_eq!(cluster_windows(&embs, None), vec![0, 0, 1, 1, 0, 1]);
+        assert_eq!(cluster_windows(&embs, Some(1)), vec![0; 6]);
+        assert!(cluster_windows(&[], None).is_empty());
+    }
+
+    #[test]
+    fn test_cluster_windows_folds_outliers() {
+        let mut embs = vec![vec![1.0, 0.0, 0.0]; 5];
+        embs.extend(vec![vec![0.0, 1.0, 0.0]; 5]);
+        // A single stray window becomes its own cluster at the threshold...
+        embs.push(vec![0.1, 0.0, 1.0]);
+        let labels = cluster_windows(&embs, None);
+        // ...and is folded into the nearest real speaker
+        assert_eq!(labels[10], 0);
+        assert_eq!(labels.iter().max(), Some(&1));
+        // An explicit count keeps it separate
+        assert_eq!(cluster_windows(&embs, Some(3))[10], 2);
+    }
+
+    #[test]
+    fn test_cluster_windows_subsamples_long_input() {
+        let n = MAX_CLUSTER_WINDOWS * 2 + 3;
+        let embs: Vec<Vec<f32>> = (0..n)
+            .map(|i| if (i / 10) % 2 == 0 { vec![1.0, 0.05] } else { vec![0.05, 1.0] })
+            .collect();
+        let labels = cluster_windows(&embs, None);
+        assert_eq!(labels.len(), n);
+        for (i, &l) in labels.iter().enumerate() {
+            assert_eq!(l, (i / 10) % 2);
+        }
+    }
+
+    #[test]
+    fn test_segment_votes_weighted() {
+        let segments = vec![seg(0.0, 3.0), seg(3.0, 4.0), seg(4.0, 5.0)];
+        let windows = vec![
+            Window { segment: 0, start: 0, end: 24_000 },
+            Window { segment: 0, start: 12_000, end: 36_000 },
+            Window { segment: 0, start: 24_000, end: 48_000 },
+            Window { segment: 1, start: 48_000, end: 64_000 },
+        ];
+        let votes = segment_votes(&segments, &windows, &[1, 0, 1], 2);
+        assert_eq!(votes[..1], [Some(1)]);
+        let votes = segment_votes(&segments, &windows, &[1, 0, 1, 0], 2);
+        assert_eq!(votes, vec![Some(1), Some(0), None]);
+    }
+
+    #[test]
+    fn test_match_profiles_one_to_one() {
+        let centroids = vec![vec![1.0, 0.0], vec![0.9, 0.1], vec![0.0, 1.0]];
+        let profiles = vec![profile("alice", vec![1.0, 0.0]), profile("bob", vec![-1.0, 0.0])];
+        // Both first clusters resemble alice; only the closer one gets her
+        assert_eq!(match_profiles(&centroids, &profiles, 0.6), vec![Some(0), None, None]);
+        assert_eq!(match_profiles(&centroids, &[], 0.6), vec![None; 3]);
+    }
+
+    #[test]
+    fn test_cluster_names_first_appearance() {
+        let profiles = vec![profile("alice", vec![1.0])];
+        let votes = vec![Some(2), None, Some(0), Some(2), Some(1)];
+        let names = cluster_names(&votes, &[None, Some(0), None, None], &profiles);
+        assert_eq!(names, vec!["SPEAKER_01", "alice", "SPEAKER_00", "SPEAKER_02"]);
+    }
+}
@@ -1,20 +1,27 @@
 //! Speaker identification via voice embeddings.
 //!
 //! Uses a wespeaker ONNX model to extract speaker embeddings from audio segments,
-//! then matches them against enrolled speaker profiles using cosine similarity.
+//! then matches them against enrolled speaker profiles using cosine similarity,
+//! or clusters them into anonymous speakers (see [`diarize`]).
 
 pub mod audio_slice;
+pub mod cluster;
+pub mod diarize;
 pub mod embedding;
 pub mod profile;
+pub mod turns;
 
 use std::path::{Path, PathBuf};
 
 use ort::session::Session;
 use tracing::{debug, info, warn};
 
+use crate::audio::SampleSource;
 use crate::error::{Error, Result};
+use crate::model::{FetchOptions, SPEAKER_MODEL_BASE_URL_ENV};
 use crate::types::Segment;
 
+pub use diarize::DiarizationOptions;
 pub use profile::{cosine_similarity, ProfileStore, SpeakerProfile};
 
 /// GPU backend for speaker embedding inference.
@@ -36,7 +43,8 @@ pub struct SpeakerMatch {
 ///
 /// Holds a loaded ONNX model session and enrolled speaker profiles.
 /// Use [`SpeakerIdentifier::new`] to load from disk, then
-/// [`SpeakerIdentifier::identify_segments`] to annotate transcript segments.
+/// [`SpeakerIdentifier::identify_segments`] or
+/// [`SpeakerIdentifier::diarize_segments`] to annotate transcript segments.
 pub struct SpeakerIdentifier {
     session: Session,
     profiles: ProfileStore,
@@ -125,6 +133,29 @@ impl SpeakerIdentifier {
         &mut self,
         segments: &mut [Segment],
         full_audio: &[f32],
+    ) -> Result<SpeakerSummary> {
+        self.identify_segments_with_progress(segments, full_audio, |_, _| true)
+    }
+
+    /// Like [`identify_segments`](Self::identify_segments), calling
+    /// `progress(done, total)` after each segment. Returning `false` from the
+    /// callback stops early with [`Error::Cancelled`](crate::Error::Cancelled).
+    pub fn identify_segments_with_progress(
+        &mut self,
+        segments: &mut [Segment],
+        full_audio: &[f32],
+        progress: impl FnMut(usize, usize) -> bool,
+    ) -> Result<SpeakerSummary> {
+        self.identify_segments_from(segments, full_audio, progress)
+    }
+
+    /// [`identify_segments_with_progress`](Self::identify_segments_with_progress)
+    /// on audio that may be kept on disk.
+    pub(crate) fn identify_segments_from<A: SampleSource + ?Sized>(
+        &mut self,
+        segments: &mut [Segment],
+        audio: &A,
+        mut progress: impl FnMut(usize, usize) -> bool,
     ) -> Result<SpeakerSummary> {
         if self.profiles.profiles.is_empty() {
             warn!("no speaker profiles enrolled — skipping identification");
@@ -138,7 +169,12 @@ impl SpeakerIdentifier {
         // Collect embeddings for re-enrollment suggestions
         let mut embeddings: Vec<Option<Vec<f32>>> = Vec::with_capacity(segments.len());
 
-        for seg in segments.iter_mut() {
+        let total = segments.len();
+        for (i, seg) in segments.iter_mut().enumerate() {
+            if !progress(i, total) {
+                return Err(Error::Cancelled);
+            }
+
             // Skip segments with high no-speech probability
             if seg.no_speech_probability > 0.6 {
                 skipped += 1;
@@ -147,8 +183,8 @@ impl SpeakerIdentifier {
             }
 
             // Extract audio slice for this segment
-            let slice = match audio_slice::extract_slice(full_audio, seg.start, seg.end) {
-                Some(s) => s,
+            let slice = match audio_slice::slice_range(audio.len(), seg.start, seg.end) {
+                Some(range) => audio.read(range)?,
                 None => {
                     skipped += 1;
                     embeddings.push(None);
@@ -157,7 +193,7 @@ impl SpeakerIdentifier {
             };
 
             // Compute embedding and match
-            match self.embed(slice) {
+            match self.embed(&slice) {
                 Ok(emb) => {
                     let m = self.identify(&emb);
                     seg.speaker_id = Some(m.name);
@@ -182,6 +218,7 @@ impl SpeakerIdentifier {
             }
         }
 
+        progress(total, total);
         info!(identified, unknown, skipped, "speaker identification complete");
 
         // Post-processing: merge short segments, then smooth
@@ -197,9 +234,243 @@ impl SpeakerIdentifier {
             skipped,
             merged,
             smoothed,
+            speakers: count_speakers(segments),
             unknown_clusters,
         })
     }
+
+    /// Split segments where the speaker changes mid-segment.
+    ///
+    /// Only segments with word timestamps that are long enough to hold two
+    /// turns are examined: their sliding windows are matched against the
+    /// enrolled profiles and the segment is cut at the word boundary closest
+    /// to each change (see [`turns`]). Every examined segment is pre-labelled
+    /// with its windows' majority speaker, so pieces too short for
+    /// [`identify_segments`](Self::identify_segments) keep a label; run that
+    /// afterwards to label the rest.
+    ///
+    /// `progress(done, total)` is called after each embedded window; returning
+    /// `false` stops early with [`Error::Cancelled`](crate::Error::Cancelled).
+    pub fn split_speaker_turns(
+        &mut self,
+        segments: Vec<Segment>,
+        full_audio: &[f32],
+        progress: impl FnMut(usize, usize) -> bool,
+    ) -> Result<Vec<Segment>> {
+        self.split_speaker_turns_from(segments, full_audio, progress)
+    }
+
+    /// [`split_speaker_turns`](Self::split_speaker_turns) on audio that may be
+    /// kept on disk.
+    pub(crate) fn split_speaker_turns_from<A: SampleSource + ?Sized>(
+        &mut self,
+        segments: Vec<Segment>,
+        audio: &A,
+        mut progress: impl FnMut(usize, usize) -> bool,
+    ) -> Result<Vec<Segment>> {
+        if self.profiles.profiles.is_empty() {
+            return Ok(segments);
+        }
+
+        let mut per_segment = vec![0usize; segments.len()];
+        let windows = diarize::plan_windows(&segments, audio.len());
+        for w in &windows {
+            per_segment[w.segment] += 1;
+        }
+        let windows: Vec<_> = windows
+            .into_iter()
+            .filter(|w| {
+                segments[w.segment].words.is_some()
+                    && per_segment[w.segment] >= 2 * turns::MIN_TURN_WINDOWS
+            })
+            .collect();
+        if windows.is_empty() {
+            return Ok(segments);
+        }
+
+        let total = windows.len();
+        let mut embedded = Vec::with_capacity(total);
+        let mut matches = Vec::with_capacity(total);
+        for (i, window) in windows.into_iter().enumerate() {
+            if !progress(i, total) {
+                return Err(Error::Cancelled);
+            }
+            match self.embed(&audio.read(window.start..window.end)?) {
+                Ok(emb) => {
+                    matches.push(self.identify(&emb));
+                    embedded.push(window);
+                }
+                Err(e) => debug!(
+                    start = window.start,
+                    end = window.end,
+                    error = %e,
+                    "failed to embed window, skipping"
+                ),
+            }
+        }
+        progress(total, total);
+
+        let before = segments.len();
+        let names: Vec<&str> = matches.iter().map(|m| m.name.as_str()).collect();
+        let (mut segments, window_segments) = turns::split_segments(segments, &embedded, &names);
+        debug!(added = segments.len() - before, "split segments at speaker changes");
+
+        // Majority label per piece, with the mean confidence of its windows
+        let mut votes: Vec<Vec<(&str, f32, usize)>> = vec![Vec::new(); segments.len()];
+        for (m, &seg) in matches.iter().zip(&window_segments) {
+            match votes[seg].iter_mut().find(|v| v.0 == m.name) {
+                Some(v) => {
+                    v.1 += m.confidence;
+                    v.2 += 1;
+                }
+                None => votes[seg].push((&m.name, m.confidence, 1)),
+            }
+        }
+        for (seg, vote) in segments.iter_mut().zip(votes) {
+            if let Some(&(name, conf, n)) = vote.iter().max_by_key(|v| v.2) {
+                seg.speaker_id = Some(name.to_string());
+                seg.speaker_confidence = Some(conf / n as f32);
+            }
+        }
+
+        Ok(segments)
+    }
+
+    /// Diarize segments without requiring enrolled profiles.
+    ///
+    /// Speech is embedded in sliding windows which are clustered into
+    /// speakers; each segment is labelled `SPEAKER_00`, `SPEAKER_01`, … by the
+    /// majority of its windows. With [`DiarizationOptions::match_profiles`],
+    /// clusters matching an enrolled profile are named after it instead and
+    /// counted as identified.
+    ///
+    /// `speaker_confidence` is the similarity of the segment to its cluster
+    /// centroid (or to the matched profile).
+    ///
+    /// Segments carrying word timestamps are first split where their windows
+    /// change cluster (see [`turns`]), so each resulting segment has a single
+    /// speaker.
+    pub fn diarize_segments(
+        &mut self,
+        segments: &mut Vec<Segment>,
+        full_audio: &[f32],
+        options: &DiarizationOptions,
+    ) -> Result<SpeakerSummary> {
+        self.diarize_segments_with_progress(segments, full_audio, options, |_, _| true)
+    }
+
+    /// Like [`diarize_segments`](Self::diarize_segments), calling
+    /// `progress(done, total)` after each embedded window. Returning `false`
+    /// from the callback stops early with [`Error::Cancelled`](crate::Error::Cancelled).
+    pub fn diarize_segments_with_progress(
+        &mut self,
+        segments: &mut Vec<Segment>,
+        full_audio: &[f32],
+        options: &DiarizationOptions,
+        progress: impl FnMut(usize, usize) -> bool,
+    ) -> Result<SpeakerSummary> {
+        self.diarize_segments_from(segments, full_audio, options, progress)
+    }
+
+    /// [`diarize_segments_with_progress`](Self::diarize_segments_with_progress)
+    /// on audio that may be kept on disk.
+    pub(crate) fn diarize_segments_from<A: SampleSource + ?Sized>(
+        &mut self,
+        segments: &mut Vec<Segment>,
+        audio: &A,
+        options: &DiarizationOptions,
+        mut progress: impl FnMut(usize, usize) -> bool,
+    ) -> Result<SpeakerSummary> {
+        let windows = diarize::plan_windows(segments, audio.len());
+
+        let total = windows.len();
+        let mut embedded = Vec::with_capacity(total);
+        let mut embeddings = Vec::with_capacity(total);
+        for (i, window) in windows.into_iter().enumerate() {
+            if !progress(i, total) {
+                return Err(Error::Cancelled);
+            }
+            match self.embed(&audio.read(window.start..window.end)?) {
+                Ok(emb) => {
+                    embedded.push(window);
+                    embeddings.push(emb);
+                }
+                Err(e) => debug!(
+                    start = window.start,
+                    end = window.end,
+                    error = %e,
+                    "failed to embed window, skipping"
+                ),
+            }
+        }
+        progress(total, total);
+
+        let labels = diarize::cluster_windows(&embeddings, options.num_speakers);
+
+        if segments.iter().any(|s| s.words.is_some()) {
+            let before = segments.len();
+            let (split, window_segments) =
+                turns::split_segments(std::mem::take(segments), &embedded, &labels);
+            for (window, segment) in embedded.iter_mut().zip(window_segments) {
+                window.segment = segment;
+            }
+            debug!(added = split.len() - before, "split segments at speaker changes");
+            *segments = split;
+        }
+
+        let centroids = cluster::centroids(&embeddings, &labels);
+        let votes = diarize::segment_votes(segments, &embedded, &labels, centroids.len());
+
+        let profiles = &self.profiles.profiles;
+        let matches = if options.match_profiles {
+            diarize::match_profiles(&centroids, profiles, self.threshold)
+        } else {
+            vec![None; centroids.len()]
+        };
+        let names = diarize::cluster_names(&votes, &matches, profiles);
+
+        // Per-segment embedding: sum of the segment's normalized windows
+        let window_segments: Vec<usize> = embedded.iter().map(|w| w.segment).collect();
+        let segment_embeddings = cluster::centroids(&embeddings, &window_segments);
+
+        let mut identified = 0u32;
+        let mut unknown = 0u32;
+        let mut skipped = 0u32;
+        for (i, seg) in segments.iter_mut().enumerate() {
+            let Some(c) = votes[i] else {
+                seg.speaker_id = None;
+                seg.speaker_confidence = None;
+                skipped += 1;
+                continue;
+            };
+            let reference = match matches[c] {
+                Some(p) => {
+                    identified += 1;
+                    &profiles[p].centroid
+                }
+                None => {
+                    unknown += 1;
+                    &centroids[c]
+                }
+            };
+            seg.speaker_id = Some(names[c].clone());
+            seg.speaker_confidence = Some(cosine_similarity(&segment_embeddings[i], reference));
+        }
+
+        let merged = merge_short_segments(segments);
+        let speakers = count_speakers(segments);
+        info!(speakers, identified, unknown, skipped, "speaker diarization complete");
+
+        Ok(SpeakerSummary {
+            identified,
+            unknown,
+            skipped,
+            merged,
+            smoothed: 0,
+            speakers,
+            unknown_clusters: Vec::new(),
+        })
+    }
 }
 
 /// Statistics from speaker identification, including post-processing counts.
@@ -215,6 +486,8 @@ pub struct SpeakerSummary {
     pub merged: u32,
     /// Segments reassigned by temporal smoothing.
     pub smoothed: u32,
+    /// Distinct speakers in the labelled segments (excluding "Unknown").
+    pub speakers: u32,
     /// Clusters of unknown segments that may be the same unregistered speaker.
     pub unknown_clusters: Vec<UnknownCluster>,
 }
@@ -282,6 +555,18 @@ fn merge_short_segments(segments: &mut [Segment]) -> u32 {
     merged
 }
 
+/// Number of distinct speaker labels, not counting "Unknown".
+fn count_speakers(segments: &[Segment]) -> u32 {
+    let mut names: Vec<&str> = segments
+        .iter()
+        .filter_map(|s| s.speaker_id.as_deref())
+        .filter(|&n| n != "Unknown")
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names.len() as u32
+}
+
 /// Maximum duration in seconds for a segment to be eligible for smoothing
 /// when it has a low-confidence attribution that differs from its neighbors.
 const SMOOTHING_MAX_DURATION: f64 = 2.0;
@@ -468,7 +753,7 @@ fn cluster_unknowns(
     }
 
     // Sort by segment count descending
-    result.sort_by(|a, b| b.segment_count.cmp(&a.segment_count));
+    result.sort_by_key(|c| std::cmp::Reverse(c.segment_count));
 
     if !result.is_empty() {
         info!(
@@ -497,42 +782,51 @@ pub fn default_profiles_path() -> PathBuf {
         .join("speakers.json")
 }
 
+/// Default location of the wespeaker model.
+const SPEAKER_MODEL_BASE: &str =
+    "https://github.com/thewh1teagle/pyannote-rs/releases/download/v0.1.0";
+
+const SPEAKER_MODEL_FILE: &str = "wespeaker_en_voxceleb_CAM++.onnx";
+
 /// Download the wespeaker model if not already cached.
 pub async fn ensure_speaker_model(cache_dir: &Path) -> Result<PathBuf> {
-    let model_path = cache_dir.join("wespeaker_en_voxceleb_CAM++.onnx");
+    ensure_speaker_model_with(cache_dir, &FetchOptions::default()).await
+}
+
+/// Like [`ensure_speaker_model`], with control over the download source,
+/// offline mode and checksum verification (the base URL falls back to
+/// [`SPEAKER_MODEL_BASE_URL_ENV`]). Without `fetch.sha256` the download is
+/// checked against the built-in digest table, which has no entry for the
+/// speaker model yet: pin it with `fetch.sha256` to have it verified.
+pub async fn ensure_speaker_model_with(cache_dir: &Path, fetch: &FetchOptions) -> Result<PathBuf> {
+    let model_path = cache_dir.join(SPEAKER_MODEL_FILE);
+    let expected = fetch
+        .sha256
+        .as_deref()
+        .or_else(|| crate::model::known_sha256_for_file(SPEAKER_MODEL_FILE));
     if model_path.exists() {
+        if let (true, Some(expected)) = (fetch.verify_cached, expected) {
+            let actual = crate::model::hash_file(&model_path).await?;
+            crate::model::check_sha256(&model_path, expected, &actual)?;
+            crate::cache::record_check(&model_path, &actual, expected).await;
+        }
         info!(path = %model_path.display(), "speaker model already cached");
+        crate::cache::record_use(&model_path).await;
         return Ok(model_path);
     }
 
-    let url = "https://github.com/thewh1teagle/pyannote-rs/releases/download/v0.1.0/wespeaker_en_voxceleb_CAM++.onnx";
-    info!(%url, "downloading speaker embedding model");
-
-    std::fs::create_dir_all(cache_dir)?;
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .send()
-        .await?
-        .error_for_status()
-        .map_err(|e| Error::SpeakerId(format!("failed to download speaker model: {e}")))?;
-
-    let bytes = response.bytes().await?;
-
-    if bytes.len() < 1_000_000 {
-        return Err(Error::SpeakerId(format!(
-            "downloaded speaker model too small ({} bytes) — likely an error page",
-            bytes.len()
-        )));
+    if fetch.is_offline() {
+        return Err(Error::Offline { path: model_path });
     }
 
-    std::fs::write(&model_path, &bytes)?;
-    info!(
-        path = %model_path.display(),
-        size = bytes.len(),
-        "speaker model saved"
-    );
+    let base = fetch
+        .resolve_base_url(SPEAKER_MODEL_BASE_URL_ENV)
+        .unwrap_or_else(|| SPEAKER_MODEL_BASE.to_string());
+    let url = format!("{base}/{SPEAKER_MODEL_FILE}");
+    info!(%url, "downloading speaker embedding model");
+    std::fs::create_dir_all(cache_dir)?;
+    crate::model::download_model(&url, &model_path, expected).await?;
+    crate::cache::record_use(&model_path).await;
 
     Ok(model_path)
 }
@@ -551,6 +845,8 @@ mod tests {
             words: None,
             speaker_id: speaker.map(|s| s.to_string()),
             speaker_confidence: conf,
+            language: None,
+            channel: None,
         }
     }
 
@@ -992,4 +1288,29 @@ mod tests {
         assert!(clusters[0].representative_end > clusters[0].representative_start);
         assert!(clusters[0].total_duration > 30.0);
     }
+
+    #[test]
+    fn test_count_speakers_ignores_unknown_and_none() {
+        let segments = vec![
+            make_segment(0.0, 2.0, Some("SPEAKER_00"), Some(0.9)),
+            make_segment(2.0, 4.0, Some("Unknown"), Some(0.3)),
+            make_segment(4.0, 6.0, Some("Alice"), Some(0.8)),
+            make_segment(6.0, 8.0, None, None),
+            make_segment(8.0, 9.0, Some("SPEAKER_00"), Some(0.7)),
+        ];
+        assert_eq!(count_speakers(&segments), 2);
+        assert_eq!(count_speakers(&[]), 0);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_speaker_model_offline() {
+        let tmp = std::env::temp_dir().join("transcriber_test_speaker_offline");
+        let _ = std::fs::remove_dir_all(&tmp);
+        let fetch = FetchOptions {
+            offline: true,
+            ..Default::default()
+        };
+        let result = ensure_speaker_model_with(&tmp, &fetch).await;
+        assert!(matches!(result, Err(Error::Offline { .. })));
+    }
 }
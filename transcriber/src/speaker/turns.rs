@@ -0,0 +1,279 @@
+//! Splitting segments at speaker changes.
+//!
+//! Whisper segments follow the text, not the voices, so one segment can
+//! span a quick exchange between two people. Given per-window speaker labels
+//! (see [`diarize::plan_windows`](super::diarize::plan_windows)), a segment
+//! with word timestamps is cut at the word boundary closest to each change.
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::types::{Segment, Word};
+
+use super::diarize::Window;
+
+/// Consecutive windows that must agree before a label counts as a new
+/// speaker. Shorter runs are treated as noise and absorbed by a neighbour.
+pub const MIN_TURN_WINDOWS: usize = 2;
+
+/// Split segments wherever their windows change speaker.
+///
+/// `labels[i]` is the speaker of `windows[i]`; windows must be grouped by
+/// segment in ascending time order, as produced by `plan_windows`. Segments
+/// without word timestamps are left whole.
+///
+/// Returns the new segments together with the index of the new segment each
+/// window now belongs to. Every piece but the last of a split segment has
+/// `speaker_turn` set.
+pub fn split_segments<L: PartialEq>(
+    segments: Vec<Segment>,
+    windows: &[Window],
+    labels: &[L],
+) -> (Vec<Segment>, Vec<usize>) {
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    let mut out = Vec::with_capacity(segments.len());
+    let mut window_segments = vec![0; windows.len()];
+
+    let mut w = 0;
+    for (i, seg) in segments.into_iter().enumerate() {
+        let first = w;
+        while w < windows.len() && windows[w].segment == i {
+            w += 1;
+        }
+        let centers: Vec<f64> = windows[first..w]
+            .iter()
+            .map(|win| (win.start + win.end) as f64 / 2.0 / rate)
+            .collect();
+
+        let changes = change_points(&centers, &labels[first..w]);
+        let pieces = split_segment(seg, &changes);
+
+        // Pieces are contiguous in time; a window goes to the piece holding its centre
+        let base = out.len();
+        for (j, &center) in centers.iter().enumerate() {
+            let piece = pieces[1..].iter().take_while(|p| p.start <= center).count();
+            window_segments[first + j] = base + piece;
+        }
+        out.extend(pieces);
+    }
+
+    (out, window_segments)
+}
+
+/// Times (in seconds) at which the speaker changes within one segment.
+///
+/// Labels are smoothed first: runs shorter than [`MIN_TURN_WINDOWS`] join the
+/// preceding run (or the following one at the start). Each change is placed
+/// halfway between the centres of the last window before it and the first
+/// window after it.
+pub fn change_points<L: PartialEq>(centers: &[f64], labels: &[L]) -> Vec<f64> {
+    // Runs as (label index, first window, last window)
+    let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+    for i in 0..labels.len() {
+        match runs.last_mut() {
+            Some(run) if labels[run.0] == labels[i] => run.2 = i,
+            _ => runs.push((i, i, i)),
+        }
+    }
+
+    let mut merged: Vec<(usize, usize, usize)> = Vec::new();
+    for run in runs {
+        let short = run.2 - run.1 + 1 < MIN_TURN_WINDOWS;
+        match merged.last_mut() {
+            // Absorb a short run, or join an equal-labelled run it now touches
+            Some(prev) if short || labels[prev.0] == labels[run.0] => prev.2 = run.2,
+            _ => merged.push(run),
+        }
+    }
+    // A short leading run belongs to the run after it
+    if merged.len() > 1 && merged[0].2 - merged[0].1 + 1 < MIN_TURN_WINDOWS {
+        merged[1].1 = merged[0].1;
+        merged.remove(0);
+    }
+
+    merged
+        .windows(2)
+        .map(|pair| (centers[pair[0].2] + centers[pair[1].1]) / 2.0)
+        .collect()
+}
+
+/// Cut a segment at the word boundaries closest to `times`.
+///
+/// Only boundaries before a token starting with whitespace are considered, so
+/// words made of several tokens stay together. Returns the segment unchanged
+/// when it has no usable word timestamps.
+pub fn split_segment(segment: Segment, times: &[f64]) -> Vec<Segment> {
+    let Some(words) = segment.words.as_deref() else {
+        return vec![segment];
+    };
+
+    // Candidate cut positions: split before word k, at the gap before it
+    let boundaries: Vec<(usize, f64)> = (1..words.len())
+        .filter(|&k| words[k].text.starts_with(char::is_whitespace))
+        .map(|k| (k, (words[k - 1].end + words[k].start) / 2.0))
+        .collect();
+
+    let mut cuts: Vec<usize> = times
+        .iter()
+        .filter_map(|&t| {
+            boundaries
+                .iter()
+                .min_by(|a, b| (a.1 - t).abs().total_cmp(&(b.1 - t).abs()))
+                .map(|&(k, _)| k)
+        })
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    if cuts.is_empty() {
+        return vec![segment];
+    }
+
+    let mut bounds = Vec::with_capacity(cuts.len() + 2);
+    bounds.push(0);
+    bounds.extend(cuts);
+    bounds.push(words.len());
+
+    let last = bounds.len() - 2;
+    bounds
+        .windows(2)
+        .enumerate()
+        .map(|(i, range)| {
+            let piece: Vec<Word> = words[range[0]..range[1]].to_vec();
+            Segment {
+                start: if i == 0 { segment.start } else { piece[0].start },
+                end: if i == last { segment.end } else { piece[piece.len() - 1].end },
+                text: piece.iter().map(|w| w.text.as_str()).collect(),
+                speaker_turn: if i == last { segment.speaker_turn } else { true },
+                no_speech_probability: segment.no_speech_probability,
+                words: Some(piece),
+                speaker_id: None,
+                speaker_confidence: None,
+                language: segment.language.clone(),
+                channel: segment.channel,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start: f64, end: f64) -> Word {
+        Word {
+            text: text.into(),
+            start,
+            end,
+            probability: 0.9,
+        }
+    }
+
+    fn segment(start: f64, end: f64, words: Option<Vec<Word>>) -> Segment {
+        let text = words
+            .as_ref()
+            .map(|w| w.iter().map(|w| w.text.as_str()).collect())
+            .unwrap_or_default();
+        Segment {
+            start,
+            end,
+            text,
+            speaker_turn: false,
+            no_speech_probability: 0.1,
+            words,
+            speaker_id: None,
+            speaker_confidence: None,
+            language: None,
+            channel: None,
+        }
+    }
+
+    /// "Are you coming? Yes I am." with the answer starting at 2.0s.
+    fn exchange() -> Segment {
+        segment(
+            0.0,
+            4.0,
+            Some(vec![
+                word(" Are", 0.0, 0.4),
+                word(" you", 0.5, 0.9),
+                word(" com", 1.0, 1.3),
+                word("ing?", 1.3, 1.8),
+                word(" Yes", 2.1, 2.5),
+                word(" I", 2.6, 2.9),
+                word(" am.", 3.0, 3.6),
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_change_points_single_speaker() {
+        assert!(change_points(&[0.5, 1.0, 1.5], &["a", "a", "a"]).is_empty());
+        assert!(change_points::<&str>(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_change_points_between_runs() {
+        let centers = [0.75, 1.5, 2.25, 3.0];
+        assert_eq!(change_points(&centers, &["a", "a", "b", "b"]), vec![1.875]);
+    }
+
+    #[test]
+    fn test_change_points_ignores_flicker() {
+        let centers = [0.5, 1.0, 1.5, 2.0, 2.5, 3.0];
+        // A lone window in the middle or at either end is noise
+        assert!(change_points(&centers, &["a", "a", "b", "a", "a", "a"]).is_empty());
+        assert!(change_points(&centers, &["b", "a", "a", "a", "a", "a"]).is_empty());
+        assert!(change_points(&centers, &["a", "a", "a", "a", "a", "b"]).is_empty());
+        // A flicker inside a real change doesn't add another one
+        assert_eq!(
+            change_points(&centers, &["a", "a", "b", "a", "b", "b"]).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_split_segment_at_nearest_word_boundary() {
+        let pieces = split_segment(exchange(), &[2.2]);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].text, " Are you coming?");
+        assert_eq!((pieces[0].start, pieces[0].end), (0.0, 1.8));
+        assert!(pieces[0].speaker_turn);
+        assert_eq!(pieces[1].text, " Yes I am.");
+        assert_eq!((pieces[1].start, pieces[1].end), (2.1, 4.0));
+        assert!(!pieces[1].speaker_turn);
+        assert_eq!(pieces[1].words.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_split_segment_keeps_multi_token_words() {
+        // Closest gap to 1.3 is inside "coming"; the cut moves to a word start
+        let pieces = split_segment(exchange(), &[1.3]);
+        assert_eq!(pieces[0].text, " Are you");
+        assert_eq!(pieces[1].text, " coming? Yes I am.");
+    }
+
+    #[test]
+    fn test_split_segment_without_words_is_unchanged() {
+        let pieces = split_segment(segment(0.0, 4.0, None), &[2.0]);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(split_segment(exchange(), &[]).len(), 1);
+        // Two changes landing on the same boundary give one cut
+        assert_eq!(split_segment(exchange(), &[1.9, 2.0]).len(), 2);
+    }
+
+    #[test]
+    fn test_split_segments_remaps_windows() {
+        let sr = WHISPER_SAMPLE_RATE as usize;
+        let win = |segment, start: f64| Window {
+            segment,
+            start: (start * sr as f64) as usize,
+            end: ((start + 1.5) * sr as f64) as usize,
+        };
+        let segments = vec![exchange(), segment(4.0, 5.5, None)];
+        let windows = vec![win(0, 0.0), win(0, 0.75), win(0, 2.0), win(0, 2.5), win(1, 4.0)];
+        let labels = [0, 0, 1, 1, 1];
+
+        let (out, mapping) = split_segments(segments, &windows, &labels);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].text, " Are you coming?");
+        assert_eq!(out[1].text, " Yes I am.");
+        assert_eq!(mapping, vec![0, 0, 1, 1, 2]);
+    }
+}
@@ -0,0 +1,385 @@
+//! Streaming transcription over pushed audio chunks.
+//!
+//! A worker thread owns one [`WhisperState`] and keeps a buffer of the audio
+//! that has not been finalized yet. Every `step` seconds of new audio it runs
+//! whisper over (at most) `window` seconds of that buffer, emits the segments
+//! that are safely in the past as final, drops their audio, and reports the
+//! rest as provisional. Timestamps are relative to the start of the stream.
+
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::debug;
+use whisper_rs::{WhisperContext, WhisperState, WhisperTokenId};
+
+use crate::audio::{PcmConverter, WHISPER_SAMPLE_RATE};
+use crate::config::{StreamOptions, TranscribeOptions};
+use crate::error::{Error, Result};
+use crate::transcribe;
+use crate::types::Segment;
+
+/// whisper.cpp skips inputs shorter than one second; pad the final tail to this.
+const MIN_PASS_SAMPLES: usize = WHISPER_SAMPLE_RATE as usize * 11 / 10;
+
+/// An update from a streaming transcription.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Current hypothesis for the audio that has not been finalized yet.
+    /// Replaces the previous provisional segments (and may be empty).
+    Provisional(Vec<Segment>),
+    /// A segment that will not change anymore. Finals arrive in time order.
+    Final(Segment),
+}
+
+/// Sending half of a stream: push audio here.
+///
+/// Dropping it (or calling [`finish`](Self::finish)) ends the stream; the
+/// remaining audio is transcribed and finalized before the event stream closes.
+pub struct StreamInput {
+    tx: mpsc::Sender<Vec<f32>>,
+}
+
+impl StreamInput {
+    /// Queue interleaved samples at the configured sample rate and channel count.
+    ///
+    /// Never blocks. Fails once the stream worker has stopped (after an error,
+    /// or because the [`TranscriptStream`] was dropped).
+    pub fn push(&self, samples: &[f32]) -> Result<()> {
+        self.tx
+            .send(samples.to_vec())
+            .map_err(|_| Error::Transcription("stream has stopped".into()))
+    }
+
+    /// Signal the end of the audio.
+    pub fn finish(self) {}
+}
+
+/// Receiving half of a stream: transcription events in order.
+///
+/// Implements [`futures_util::Stream`]; ends after the input is finished and
+/// all audio has been finalized, or right after an error.
+pub struct TranscriptStream {
+    rx: UnboundedReceiver<Result<StreamEvent>>,
+}
+
+impl TranscriptStream {
+    /// Wait for the next event. Returns `None` once the stream is complete.
+    pub async fn next_event(&mut self) -> Option<Result<StreamEvent>> {
+        self.rx.recv().await
+    }
+}
+
+impl futures_util::Stream for TranscriptStream {
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Spawn the worker thread for a new stream.
+pub(crate) fn start(
+    ctx: Arc<WhisperContext>,
+    mut options: TranscribeOptions,
+    stream_options: StreamOptions,
+) -> Result<(StreamInput, TranscriptStream)> {
+    stream_options.validate()?;
+    let converter = PcmConverter::new(stream_options.sample_rate, stream_options.channels)?;
+    let state = ctx.create_state()?;
+    let prompt = transcribe::prompt_tokens(&ctx, &options)?;
+    // Per-pass progress is meaningless for an open-ended stream; cancellation
+    // still applies and ends the stream with `Error::Cancelled`.
+    options.progress = None;
+
+    let secs = |s: f32| (s * WHISPER_SAMPLE_RATE as f32) as usize;
+    let worker = Worker {
+        state,
+        prompt,
+        options,
+        converter,
+        window: secs(stream_options.window_secs),
+        step: secs(stream_options.step_secs).max(1),
+        overlap: secs(stream_options.overlap_secs),
+        buffer: Vec::new(),
+        buffer_start: 0,
+        covered: 0,
+    };
+
+    let (audio_tx, audio_rx) = mpsc::channel();
+    let (event_tx, event_rx) = unbounded_channel();
+
+    std::thread::Builder::new()
+        .name("transcriber-stream".into())
+        .spawn(move || worker.run(audio_rx, event_tx))?;
+
+    Ok((
+        StreamInput { tx: audio_tx },
+        TranscriptStream { rx: event_rx },
+    ))
+}
+
+struct Worker {
+    state: WhisperState,
+    /// Decoder prompt, applied to every pass.
+    prompt: Vec<WhisperTokenId>,
+    options: TranscribeOptions,
+    converter: PcmConverter,
+    /// Window, step and overlap lengths in 16kHz samples.
+    window: usize,
+    step: usize,
+    overlap: usize,
+    /// Audio not finalized yet (16kHz mono).
+    buffer: Vec<f32>,
+    /// Absolute sample index of `buffer[0]`.
+    buffer_start: u64,
+    /// Length of the buffer prefix the last pass decoded.
+    covered: usize,
+}
+
+impl Worker {
+    fn run(mut self, audio_rx: Receiver<Vec<f32>>, events: UnboundedSender<Result<StreamEvent>>) {
+        loop {
+            let mut closed = match audio_rx.recv() {
+                Ok(chunk) => {
+                    self.converter.push(&chunk, &mut self.buffer);
+                    false
+                }
+                Err(_) => true,
+            };
+            // Take everything already queued so a slow pass doesn't fall further behind
+            while !closed {
+                match audio_rx.try_recv() {
+                    Ok(chunk) => self.converter.push(&chunk, &mut self.buffer),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => closed = true,
+                }
+            }
+
+            if closed {
+                self.converter.finish(&mut self.buffer);
+            }
+
+            loop {
+                let backlog = self.buffer.len() - self.covered;
+                let ready = if closed {
+                    !self.buffer.is_empty()
+                } else {
+                    backlog >= self.step && self.buffer.len() >= MIN_PASS_SAMPLES
+                };
+                if !ready {
+                    break;
+                }
+
+                let last = closed && self.buffer.len() <= self.window;
+                match self.pass(last) {
+                    Ok(batch) => {
+                        for event in batch {
+                            if events.send(Ok(event)).is_err() {
+                                debug!("stream consumer dropped, stopping");
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let _ = events.send(Err(e));
+                        return;
+                    }
+                }
+            }
+
+            if closed {
+                return;
+            }
+        }
+    }
+
+    /// Decode the head of the buffer, finalize what is settled, and slide the window.
+    fn pass(&mut self, last: bool) -> Result<Vec<StreamEvent>> {
+        let len = self.buffer.len().min(self.window);
+        let full = self.buffer.len() >= self.window;
+
+        let transcript = if len < MIN_PASS_SAMPLES {
+            let mut padded = self.buffer[..len].to_vec();
+            padded.resize(MIN_PASS_SAMPLES, 0.0);
+            transcribe::transcribe_with_state(
+                &mut self.state,
+                &padded,
+                &self.prompt,
+                &self.options,
+            )?
+        } else {
+            transcribe::transcribe_with_state(
+                &mut self.state,
+                &self.buffer[..len],
+                &self.prompt,
+                &self.options,
+            )?
+        };
+
+        let offset = self.buffer_start as f64 / WHISPER_SAMPLE_RATE as f64;
+        let window_end = len as f64 / WHISPER_SAMPLE_RATE as f64;
+        let mut segments = transcript.segments;
+        for seg in &mut segments {
+            seg.start = seg.start.min(window_end);
+            seg.end = seg.end.min(window_end);
+        }
+
+        let plan = plan_pass(&segments, len, full, last, self.overlap);
+        debug!(
+            window_secs = window_end,
+            segments = segments.len(),
+            finalized = plan.finalize,
+            "stream pass"
+        );
+
+        for seg in &mut segments {
+            seg.shift(offset);
+        }
+
+        self.buffer.drain(..plan.cut);
+        self.buffer_start += plan.cut as u64;
+        self.covered = len - plan.cut;
+
+        let provisional = segments.split_off(plan.finalize);
+        let mut events: Vec<StreamEvent> = segments.into_iter().map(StreamEvent::Final).collect();
+        if !last {
+            events.push(StreamEvent::Provisional(provisional));
+        }
+        Ok(events)
+    }
+}
+
+/// What a pass finalizes, and how many buffer samples it consumes.
+#[derive(Debug, PartialEq)]
+struct PassPlan {
+    /// Number of leading segments that become final.
+    finalize: usize,
+    /// Buffer samples to drop from the front.
+    cut: usize,
+}
+
+/// Decide which of a pass's segments (timestamps relative to the window
+/// start, `len` samples long) are final.
+///
+/// A segment is final once it ends before the overlap tail and another segment
+/// follows it, i.e. whisper has seen where it stops. A full window finalizes
+/// everything before the tail — or, if one segment spans the whole window,
+/// everything — so the window can always slide.
+fn plan_pass(segments: &[Segment], len: usize, full: bool, last: bool, overlap: usize) -> PassPlan {
+    let to_samples = |t: f64| ((t * WHISPER_SAMPLE_RATE as f64).round() as usize).min(len);
+
+    if last {
+        return PassPlan {
+            finalize: segments.len(),
+            cut: len,
+        };
+    }
+
+    let horizon = len.saturating_sub(overlap);
+    let candidates = if full {
+        segments.len()
+    } else {
+        segments.len().saturating_sub(1)
+    };
+    let mut finalize = segments[..candidates]
+        .iter()
+        .take_while(|s| to_samples(s.end) <= horizon)
+        .count();
+    if full && finalize == 0 && !segments.is_empty() {
+        finalize = segments.len();
+    }
+
+    let mut cut = if finalize > 0 {
+        to_samples(segments[finalize - 1].end)
+    } else if segments.is_empty() {
+        // Nothing but silence so far: keep just the tail in case speech starts there
+        horizon
+    } else {
+        0
+    };
+    if full && cut == 0 {
+        // Finalized segments can all end at 0 (whisper emits empty ones there);
+        // a full window must still slide or the same pass repeats forever
+        cut = horizon.max(1);
+    }
+
+    PassPlan { finalize, cut }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: usize = WHISPER_SAMPLE_RATE as usize;
+
+    fn seg(start: f64, end: f64) -> Segment {
+        Segment {
+            start,
+            end,
+            text: format!("{start}-{end}"),
+            speaker_turn: false,
+            no_speech_probability: 0.0,
+            words: None,
+            speaker_id: None,
+            speaker_confidence: None,
+            language: None,
+            channel: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_keeps_last_segment_provisional() {
+        let segs = [seg(0.0, 2.0), seg(2.0, 4.0), seg(4.0, 5.0)];
+        let plan = plan_pass(&segs, 6 * SR, false, false, SR);
+        assert_eq!(plan, PassPlan { finalize: 2, cut: 4 * SR });
+    }
+
+    #[test]
+    fn test_plan_respects_overlap_tail() {
+        // Second segment ends inside the 1s tail, so only the first is final
+        let segs = [seg(0.0, 2.0), seg(2.0, 5.5), seg(5.5, 6.0)];
+        let plan = plan_pass(&segs, 6 * SR, false, false, SR);
+        assert_eq!(plan, PassPlan { finalize: 1, cut: 2 * SR });
+    }
+
+    #[test]
+    fn test_plan_full_window_forces_progress() {
+        let segs = [seg(0.0, 15.0)];
+        let plan = plan_pass(&segs, 15 * SR, true, false, SR);
+        assert_eq!(plan, PassPlan { finalize: 1, cut: 15 * SR });
+    }
+
+    #[test]
+    fn test_plan_full_window_finalizes_before_tail() {
+        let segs = [seg(0.0, 6.0), seg(6.0, 14.5)];
+        let plan = plan_pass(&segs, 15 * SR, true, false, SR);
+        assert_eq!(plan, PassPlan { finalize: 1, cut: 6 * SR });
+    }
+
+    #[test]
+    fn test_plan_full_window_zero_length_segment_still_slides() {
+        let segs = [seg(0.0, 0.0)];
+        let plan = plan_pass(&segs, 15 * SR, true, false, SR);
+        assert_eq!(plan, PassPlan { finalize: 1, cut: 14 * SR });
+        // Even with an overlap covering the whole window
+        let plan = plan_pass(&segs, 15 * SR, true, false, 15 * SR);
+        assert_eq!(plan, PassPlan { finalize: 1, cut: 1 });
+    }
+
+    #[test]
+    fn test_plan_silence_drops_all_but_tail() {
+        let plan = plan_pass(&[], 5 * SR, false, false, SR);
+        assert_eq!(plan, PassPlan { finalize: 0, cut: 4 * SR });
+    }
+
+    #[test]
+    fn test_plan_last_pass_finalizes_everything() {
+        let segs = [seg(0.0, 1.0), seg(1.0, 2.9)];
+        let plan = plan_pass(&segs, 3 * SR, false, true, SR);
+        assert_eq!(plan, PassPlan { finalize: 2, cut: 3 * SR });
+    }
+
+}
@@ -1,12 +1,17 @@
 use std::collections::VecDeque;
+use std::ops::Range;
 use std::path::Path;
 
 use tracing::{debug, info, warn};
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+    WhisperTokenId,
+};
 
-use crate::config::{Language, TranscribeOptions};
+use crate::config::{Language, LanguageDetection, TranscribeOptions};
 use crate::error::{Error, Result};
-use crate::types::{Segment, Transcript, Word};
+use crate::progress::{Progress, ProgressCallback, Stage};
+use crate::types::{LanguageShare, Segment, Transcript, Word};
 
 /// Window size for detecting hallucination loops via rolling text history.
 const HALLUCINATION_WINDOW: usize = 6;
@@ -14,13 +19,12 @@ const HALLUCINATION_WINDOW: usize = 6;
 /// If a single phrase appears this many times in the rolling window, it's a loop.
 const HALLUCINATION_THRESHOLD: usize = 3;
 
-/// Transcribe audio samples using whisper.cpp.
-/// Samples must be 16kHz mono f32.
-pub fn transcribe_samples(
-    samples: &[f32],
-    model_path: &Path,
-    options: &TranscribeOptions,
-) -> Result<Transcript> {
+/// Load a whisper model into a new context.
+///
+/// This is the expensive step (seconds for large models) — callers that
+/// transcribe more than once should keep the context and create a fresh
+/// state per run via [`transcribe_with_state`].
+pub fn load_context(model_path: &Path, options: &TranscribeOptions) -> Result<WhisperContext> {
     info!(model = %model_path.display(), "loading whisper model");
 
     let mut ctx_params = WhisperContextParameters::new();
@@ -37,15 +41,398 @@ pub fn transcribe_samples(
         ctx_params,
     )?;
 
-    let mut state = ctx.create_state()?;
+    Ok(ctx)
+}
+
+/// Tokenize the decoder prompt built from `initial_prompt` and `vocabulary`.
+///
+/// Whisper uses at most half of the text context as prompt and drops the
+/// start of anything longer, so the prompt is bounded here instead: the
+/// initial prompt is kept (its end, if it alone is too long) and vocabulary
+/// terms are added in order while they fit.
+pub fn prompt_tokens(ctx: &WhisperContext, options: &TranscribeOptions) -> Result<Vec<WhisperTokenId>> {
+    // Every token is at least one byte, so this always fits
+    let tokenize = |text: &str| ctx.tokenize(text, text.len() + 1);
+    let budget = usize::try_from(ctx.n_text_ctx() / 2).unwrap_or(0);
+
+    let Some(text) = build_prompt(
+        options.initial_prompt.as_deref(),
+        &options.vocabulary,
+        budget,
+        |text| Ok(tokenize(text)?.len()),
+    )?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut tokens = tokenize(&text)?;
+    if tokens.len() > budget {
+        warn!(tokens = tokens.len(), budget, "initial prompt too long, keeping its end");
+        tokens.drain(..tokens.len() - budget);
+    }
+    debug!(tokens = tokens.len(), prompt = %text, "decoder prompt");
+    Ok(tokens)
+}
+
+/// Join vocabulary and prompt into one text of at most `budget` tokens
+/// (as counted by `count`), dropping vocabulary terms that don't fit. An
+/// initial prompt is always included, even if it alone exceeds the budget.
+fn build_prompt(
+    initial_prompt: Option<&str>,
+    vocabulary: &[String],
+    budget: usize,
+    count: impl Fn(&str) -> Result<usize>,
+) -> Result<Option<String>> {
+    let join = |terms: &[String]| {
+        let glossary = (!terms.is_empty()).then(|| format!("{}.", terms.join(", ")));
+        match (glossary, initial_prompt) {
+            (Some(glossary), Some(prompt)) => Some(format!("{glossary} {prompt}")),
+            (glossary, prompt) => glossary.or_else(|| prompt.map(str::to_string)),
+        }
+    };
+
+    let mut used = 0;
+    while used < vocabulary.len() {
+        match join(&vocabulary[..=used]) {
+            Some(text) if count(&text)? <= budget => used += 1,
+            _ => break,
+        }
+    }
+    if used < vocabulary.len() {
+        warn!(
+            dropped = vocabulary.len() - used,
+            "vocabulary exceeds whisper's prompt budget, dropping the last terms"
+        );
+    }
+    Ok(join(&vocabulary[..used]))
+}
+
+/// Peak amplitude below which a detection window counts as silent (-60 dBFS).
+const SILENT_PEAK: f32 = 0.001;
+
+/// Whisper's 30-second window, the unit for per-window language detection
+/// and for re-applying the prompt.
+const WINDOW_SECS: usize = 30;
+
+/// Sample ranges of the windows language detection listens to in audio of
+/// `len` samples, as chosen by `detection`.
+pub(crate) fn detection_windows(len: usize, detection: &LanguageDetection) -> Vec<Range<usize>> {
+    let window = (detection.window_secs * crate::audio::WHISPER_SAMPLE_RATE as f32) as usize;
+    window_starts(len, window, detection.windows, detection.spread)
+        .into_iter()
+        .map(|start| start..(start + window).min(len))
+        .collect()
+}
+
+/// Run whisper's language detection on `windows` (see [`detection_windows`]),
+/// averaging their probabilities. Silent windows are skipped unless every
+/// window is silent. Returns all languages, most likely first.
+pub fn detect_language_windows(
+    state: &mut WhisperState,
+    windows: &[&[f32]],
+    options: &TranscribeOptions,
+) -> Result<Vec<(Language, f32)>> {
+    let threads = detection_threads(options);
+    let audible: Vec<&[f32]> = windows.iter().copied().filter(|w| !is_silent(w)).collect();
+    let windows = if audible.is_empty() { windows.to_vec() } else { audible };
+    if windows.is_empty() {
+        return Err(Error::Transcription("no audio to detect the language of".into()));
+    }
+
+    let mut sum: Vec<f32> = Vec::new();
+    for samples in &windows {
+        options.check_cancelled()?;
+        let probs = language_probabilities(state, samples, threads)?;
+        sum.resize(probs.len(), 0.0);
+        sum.iter_mut().zip(&probs).for_each(|(total, p)| *total += p);
+    }
+
+    let mut ranked: Vec<(Language, f32)> = sum
+        .into_iter()
+        .enumerate()
+        .filter_map(|(id, total)| {
+            Some((language_from_id(id as i32)?, total / windows.len() as f32))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    debug!(windows = windows.len(), best = %ranked[0].0, p = ranked[0].1, "detected language");
+    Ok(ranked)
+}
+
+/// Transcribe with the language detected separately for every 30-second
+/// window (see [`TranscribeOptions::language_per_window`]).
+///
+/// Neighbouring windows in the same language are transcribed together, so
+/// whisper only starts afresh where the language changes. Silent windows
+/// join the run before them.
+pub fn transcribe_per_window_language(
+    state: &mut WhisperState,
+    samples: &[f32],
+    prompt: &[WhisperTokenId],
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    let rate = crate::audio::WHISPER_SAMPLE_RATE as f64;
+    let threads = detection_threads(options);
+    let window = WINDOW_SECS * crate::audio::WHISPER_SAMPLE_RATE as usize;
+
+    let mut languages = Vec::new();
+    for chunk in samples.chunks(window) {
+        options.check_cancelled()?;
+        if is_silent(chunk) {
+            languages.push(None);
+            continue;
+        }
+        let probs = language_probabilities(state, chunk, threads)?;
+        let best = probs
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(id, _)| id as i32);
+        languages.push(best);
+    }
+
+    let duration = samples.len() as f64 / rate;
+    let mut segments: Vec<Segment> = Vec::new();
+    for (windows, language) in language_runs(&languages) {
+        let start = windows.start * window;
+        let end = (windows.end * window).min(samples.len());
+        let offset = start as f64 / rate;
+        info!(
+            from = offset,
+            to = end as f64 / rate,
+            language = language.and_then(whisper_rs::get_lang_str).unwrap_or("auto"),
+            "transcribing language run"
+        );
+
+        let mut run_options = options.clone();
+        run_options.language = language.and_then(language_from_id).unwrap_or(Language::Auto);
+        run_options.progress =
+            part_progress(options, offset, (end - start) as f64 / rate, duration, segments.len());
+
+        let part = transcribe_prompted(state, &samples[start..end], prompt, &run_options)?;
+        segments.extend(part.segments.into_iter().map(|mut seg| {
+            seg.shift(offset);
+            seg.language = Some(part.language.clone());
+            seg
+        }));
+    }
+
+    let shares = LanguageShare::from_segments(&segments);
+    Ok(Transcript {
+        segments,
+        language: shares.first().map_or_else(|| "unknown".into(), |s| s.language.clone()),
+        duration,
+        model: options.model.name().to_string(),
+        source_url: None,
+        source_title: None,
+        speaker_summary: None,
+        languages: shares,
+    })
+}
+
+/// Progress of a part of the audio, `len` seconds from `offset`, reported
+/// as progress through all of it. `done` segments came before the part.
+fn part_progress(
+    options: &TranscribeOptions,
+    offset: f64,
+    len: f64,
+    duration: f64,
+    done: usize,
+) -> Option<ProgressCallback> {
+    options.progress.clone().map(|cb| {
+        ProgressCallback::new(move |p: &Progress| {
+            cb.call(&Progress {
+                stage: p.stage,
+                fraction: ((offset + p.fraction as f64 * len) / duration) as f32,
+                segments: done + p.segments,
+                position_secs: offset + p.position_secs,
+                duration_secs: Some(duration),
+            })
+        })
+    })
+}
+
+/// Group consecutive windows with the same detected language. Silent windows
+/// (`None`) extend the run before them, or the one after at the start.
+fn language_runs(languages: &[Option<i32>]) -> Vec<(Range<usize>, Option<i32>)> {
+    let mut runs: Vec<(Range<usize>, Option<i32>)> = Vec::new();
+    for (i, &language) in languages.iter().enumerate() {
+        match runs.last_mut() {
+            Some((windows, run)) if language.is_none() || run.is_none() || *run == language => {
+                windows.end = i + 1;
+                *run = run.or(language);
+            }
+            _ => runs.push((i..i + 1, language)),
+        }
+    }
+    runs
+}
+
+/// Language probabilities of up to 30 seconds of audio, indexed by whisper
+/// language id.
+fn language_probabilities(
+    state: &mut WhisperState,
+    samples: &[f32],
+    threads: usize,
+) -> Result<Vec<f32>> {
+    state.pcm_to_mel(samples, threads)?;
+    let (_, probs) = state.lang_detect(0, threads)?;
+    Ok(probs)
+}
+
+fn language_from_id(id: i32) -> Option<Language> {
+    let code = whisper_rs::get_lang_str(id)?.to_string();
+    Some(Language::Code { code, id })
+}
+
+/// Threads for language detection, which runs outside `whisper_full` and so
+/// doesn't get whisper's own default.
+fn detection_threads(options: &TranscribeOptions) -> usize {
+    match options.n_threads {
+        Some(n) => n as usize,
+        None => std::thread::available_parallelism().map_or(4, |n| n.get().min(4)),
+    }
+}
+
+fn is_silent(samples: &[f32]) -> bool {
+    samples.iter().all(|s| s.abs() < SILENT_PEAK)
+}
+
+/// Start samples of `count` windows of `window` samples in audio of `len`
+/// samples: back to back from the start, or evenly spread with the last one
+/// ending at the end of the audio. Windows past the end are left out.
+fn window_starts(len: usize, window: usize, count: u32, spread: bool) -> Vec<usize> {
+    if len == 0 {
+        return Vec::new();
+    }
+    let count = count.max(1) as usize;
+    if len <= window || count == 1 {
+        return vec![0];
+    }
+    if !spread {
+        return (0..count).map(|i| i * window).take_while(|&s| s < len).collect();
+    }
+    let last = len - window;
+    let mut starts: Vec<usize> = (0..count).map(|i| last * i / (count - 1)).collect();
+    starts.dedup();
+    starts
+}
+
+/// One transcription pass on `state`, detecting the language per window if
+/// enabled. Samples must be 16kHz mono f32.
+pub fn transcribe_pass(
+    state: &mut WhisperState,
+    samples: &[f32],
+    prompt: &[WhisperTokenId],
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    if options.language_per_window {
+        transcribe_per_window_language(state, samples, prompt, options)
+    } else {
+        transcribe_prompted(state, samples, prompt, options)
+    }
+}
+
+/// Transcribe with the prompt in front of every 30-second window.
+///
+/// whisper.cpp only prompts the first window of a run, so audio longer than
+/// that is transcribed one window at a time. Each window is still
+/// conditioned on up to `n_max_text_ctx` tokens of the text before it, as it
+/// would be in a single run. With [`Language::Auto`] the language detected in
+/// the first window is kept for the rest.
+fn transcribe_prompted(
+    state: &mut WhisperState,
+    samples: &[f32],
+    prompt: &[WhisperTokenId],
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    let rate = crate::audio::WHISPER_SAMPLE_RATE as f64;
+    let window = WINDOW_SECS * crate::audio::WHISPER_SAMPLE_RATE as usize;
+    if prompt.is_empty() || samples.len() <= window {
+        return transcribe_with_state(state, samples, prompt, options);
+    }
+
+    let duration = samples.len() as f64 / rate;
+    let past_len = options.decoder.n_max_text_ctx as usize;
+    let mut window_options = options.clone();
+    let mut past: Vec<WhisperTokenId> = Vec::new();
+    let mut language = None;
+    let mut segments: Vec<Segment> = Vec::new();
+    for (i, chunk) in samples.chunks(window).enumerate() {
+        let offset = (i * window) as f64 / rate;
+        window_options.progress =
+            part_progress(options, offset, chunk.len() as f64 / rate, duration, segments.len());
+
+        // Past text goes first: whisper drops the start of an overlong
+        // prompt, and the prompt itself always fits
+        let mut tokens = past[past.len().saturating_sub(past_len)..].to_vec();
+        tokens.extend_from_slice(prompt);
+        let part = transcribe_with_state(state, chunk, &tokens, &window_options)?;
+        if past_len > 0 {
+            past.extend(text_tokens(state));
+        }
+        if matches!(window_options.language, Language::Auto) {
+            if let Some(detected) = language_from_id(state.full_lang_id_from_state()) {
+                window_options.language = detected;
+            }
+        }
+        language.get_or_insert(part.language);
+        segments.extend(part.segments.into_iter().map(|mut seg| {
+            seg.shift(offset);
+            seg
+        }));
+    }
 
+    Ok(Transcript {
+        segments,
+        language: language.unwrap_or_else(|| "unknown".into()),
+        duration,
+        model: options.model.name().to_string(),
+        source_url: None,
+        source_title: None,
+        speaker_summary: None,
+        languages: Vec::new(),
+    })
+}
+
+/// Text tokens of the segments on `state`, without timestamps and other
+/// special tokens.
+fn text_tokens(state: &WhisperState) -> Vec<WhisperTokenId> {
+    let mut tokens = Vec::new();
+    for i in 0..state.full_n_segments().max(0) {
+        let Some(segment) = state.get_segment(i) else { continue };
+        for t in 0..segment.n_tokens() {
+            let Some(token) = segment.get_token(t) else { continue };
+            let special = token
+                .to_str_lossy()
+                .map_or(true, |text| text.starts_with('[') || text.starts_with('<'));
+            if !special {
+                tokens.push(token.token_id());
+            }
+        }
+    }
+    tokens
+}
+
+/// Transcribe audio samples on an existing whisper state.
+/// Samples must be 16kHz mono f32. `prompt` comes from [`prompt_tokens`].
+pub fn transcribe_with_state(
+    state: &mut WhisperState,
+    samples: &[f32],
+    prompt: &[WhisperTokenId],
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    let decoder = &options.decoder;
     let mut params = match options.beam_size {
         Some(beam_size) => FullParams::new(SamplingStrategy::BeamSearch {
             beam_size: i32::try_from(beam_size)
                 .map_err(|_| Error::Transcription(format!("beam_size {} exceeds i32 range", beam_size)))?,
             patience: -1.0,
         }),
-        None => FullParams::new(SamplingStrategy::Greedy { best_of: 5 }),
+        None => FullParams::new(SamplingStrategy::Greedy {
+            best_of: i32::try_from(decoder.best_of)
+                .map_err(|_| Error::Transcription(format!("best_of {} exceeds i32 range", decoder.best_of)))?,
+        }),
     };
 
     // Language
@@ -55,25 +442,38 @@ pub fn transcribe_samples(
     }
 
     params.set_translate(options.translate);
-    params.set_token_timestamps(options.word_timestamps);
+    // Splitting segments at max_len works on token timestamps
+    params.set_token_timestamps(options.word_timestamps || decoder.max_len.is_some());
     params.set_temperature(options.temperature);
 
-    // Anti-hallucination decoder settings:
+    // Anti-hallucination decoder settings (defaults from `DecoderOptions`):
     // - entropy_thold: segments with high entropy (repetitive/low-info) get retried
     //   at higher temperature. Default 2.4, we use 2.4 (whisper's own default).
     // - logprob_thold: segments with very low confidence get retried. Default -1.0.
     // - temperature_inc: how much to bump temperature on retry. Default 0.2.
     // - suppress_nst: suppress non-speech tokens to reduce hallucinated filler.
     // - no_speech_thold: threshold for no-speech probability. Default 0.6.
-    // - n_max_text_ctx: limit past text used as decoder prompt. Default 16384.
-    //   Setting to 0 prevents hallucination loops from poisoning subsequent chunks —
-    //   each 30s window starts with a clean decoder slate.
-    params.set_entropy_thold(2.4);
-    params.set_logprob_thold(-1.0);
-    params.set_temperature_inc(0.2);
-    params.set_suppress_nst(true);
-    params.set_no_speech_thold(0.6);
-    params.set_n_max_text_ctx(0);
+    // - n_max_text_ctx: limit past text used as decoder prompt. Whisper's default
+    //   is 16384, ours 0: this prevents hallucination loops from poisoning
+    //   subsequent chunks — each 30s window starts with a clean decoder slate. A
+    //   prompt is only fed to the decoder through this context, so it is
+    //   widened by the prompt's length. whisper.cpp puts the prompt in front
+    //   of the first window only; `transcribe_prompted` runs every window
+    //   separately to repeat it.
+    params.set_entropy_thold(decoder.entropy_threshold);
+    params.set_logprob_thold(decoder.logprob_threshold);
+    params.set_temperature_inc(decoder.temperature_increment);
+    params.set_suppress_nst(decoder.suppress_nst);
+    params.set_no_speech_thold(decoder.no_speech_threshold);
+    let text_ctx = (decoder.n_max_text_ctx as usize).saturating_add(prompt.len());
+    params.set_n_max_text_ctx(i32::try_from(text_ctx).unwrap_or(i32::MAX));
+    if !prompt.is_empty() {
+        params.set_tokens(prompt);
+    }
+    if let Some(max_len) = decoder.max_len {
+        params.set_max_len(i32::try_from(max_len).unwrap_or(i32::MAX));
+        params.set_split_on_word(decoder.split_on_word);
+    }
 
     #[cfg(feature = "diarize")]
     params.set_tdrz_enable(options.diarize);
@@ -98,8 +498,50 @@ pub fn transcribe_samples(
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
 
+    let duration = samples.len() as f64 / crate::audio::WHISPER_SAMPLE_RATE as f64;
+
+    // Progress: whisper reports percent done, and each new segment moves the
+    // transcribed position forward.
+    if let Some(cb) = options.progress.clone() {
+        let current = std::rc::Rc::new(std::cell::Cell::new((0.0f32, 0usize, 0.0f64)));
+        let report = move |(fraction, segments, position_secs): (f32, usize, f64)| {
+            cb.call(&Progress {
+                stage: Stage::Transcribing,
+                fraction,
+                segments,
+                position_secs,
+                duration_secs: Some(duration),
+            });
+        };
+
+        params.set_progress_callback_safe({
+            let current = std::rc::Rc::clone(&current);
+            let report = report.clone();
+            move |percent: i32| {
+                let (_, segments, position) = current.get();
+                let next = ((percent as f32 / 100.0).clamp(0.0, 1.0), segments, position);
+                current.set(next);
+                report(next);
+            }
+        });
+        params.set_segment_callback_safe_lossy(move |data: whisper_rs::SegmentCallbackData| {
+            let (fraction, segments, _) = current.get();
+            let next = (fraction, segments + 1, data.end_timestamp as f64 / 100.0);
+            current.set(next);
+            report(next);
+        });
+    }
+
+    if let Some(token) = options.cancellation.clone() {
+        params.set_abort_callback_safe(move || token.is_cancelled());
+    }
+
+    options.check_cancelled()?;
     info!(samples = samples.len(), "running transcription");
-    state.full(params, samples)?;
+    let result = state.full(params, samples);
+    // An aborted run surfaces as a generic whisper failure
+    options.check_cancelled()?;
+    result?;
 
     let num_segments = state.full_n_segments().max(0);
     debug!(num_segments, "transcription complete");
@@ -168,6 +610,8 @@ pub fn transcribe_samples(
             words,
             speaker_id: None,
             speaker_confidence: None,
+            language: None,
+            channel: None,
         });
     }
 
@@ -179,8 +623,6 @@ pub fn transcribe_samples(
         warn!(removed, "suppressed hallucinated segments");
     }
 
-    let duration = samples.len() as f64 / crate::audio::WHISPER_SAMPLE_RATE as f64;
-
     // Get detected language from whisper state
     let detected_lang_id = state.full_lang_id_from_state();
     let language = whisper_rs::get_lang_str(detected_lang_id)
@@ -195,6 +637,7 @@ pub fn transcribe_samples(
         source_url: None,
         source_title: None,
         speaker_summary: None,
+        languages: Vec::new(),
     })
 }
 
@@ -247,6 +690,72 @@ fn suppress_hallucinations(segments: Vec<Segment>) -> Vec<Segment> {
 mod tests {
     use super::*;
 
+    /// Count words instead of tokens.
+    fn words(text: &str) -> Result<usize> {
+        Ok(text.split_whitespace().count())
+    }
+
+    fn terms(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_language_runs() {
+        let (de, en) = (Some(4), Some(0));
+        assert_eq!(
+            language_runs(&[None, de, de, None, en, en, de]),
+            vec![(0..4, de), (4..6, en), (6..7, de)]
+        );
+        assert_eq!(language_runs(&[None, None]), vec![(0..2, None)]);
+        assert!(language_runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_window_starts() {
+        // Back to back, stopping at the end of the audio
+        assert_eq!(window_starts(100, 30, 3, false), vec![0, 30, 60]);
+        assert_eq!(window_starts(50, 30, 3, false), vec![0, 30]);
+        // Spread over the whole recording
+        assert_eq!(window_starts(100, 30, 3, true), vec![0, 35, 70]);
+        // Short audio gets a single window
+        assert_eq!(window_starts(20, 30, 3, true), vec![0]);
+        assert_eq!(window_starts(0, 30, 1, false), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_detection_windows() {
+        let sr = crate::audio::WHISPER_SAMPLE_RATE as usize;
+        let detection = LanguageDetection::new().window_secs(10.0).unwrap().windows(2).unwrap();
+        assert_eq!(detection_windows(25 * sr, &detection), vec![0..10 * sr, 10 * sr..20 * sr]);
+        // The last window is cut off at the end of the audio
+        assert_eq!(detection_windows(15 * sr, &detection), vec![0..10 * sr, 10 * sr..15 * sr]);
+        let spread = detection.spread(true);
+        assert_eq!(detection_windows(60 * sr, &spread), vec![0..10 * sr, 50 * sr..60 * sr]);
+    }
+
+    #[test]
+    fn test_build_prompt_combines_vocabulary_and_prompt() {
+        let vocab = terms(&["Kubernetes", "gRPC"]);
+        let prompt = build_prompt(Some("Weekly platform sync."), &vocab, 100, words).unwrap();
+        assert_eq!(prompt.as_deref(), Some("Kubernetes, gRPC. Weekly platform sync."));
+
+        let prompt = build_prompt(None, &vocab, 100, words).unwrap();
+        assert_eq!(prompt.as_deref(), Some("Kubernetes, gRPC."));
+        assert_eq!(build_prompt(None, &[], 100, words).unwrap(), None);
+    }
+
+    #[test]
+    fn test_build_prompt_drops_vocabulary_over_budget() {
+        let vocab = terms(&["one", "two", "three", "four"]);
+        // "one, two. A B" is 4 words
+        let prompt = build_prompt(Some("A B"), &vocab, 4, words).unwrap();
+        assert_eq!(prompt.as_deref(), Some("one, two. A B"));
+
+        // The initial prompt is kept even when it alone is over budget
+        let prompt = build_prompt(Some("A B C D E"), &vocab, 4, words).unwrap();
+        assert_eq!(prompt.as_deref(), Some("A B C D E"));
+    }
+
     fn seg(text: &str, start: f64, end: f64) -> Segment {
         Segment {
             start,
@@ -257,6 +766,8 @@ mod tests {
             words: None,
             speaker_id: None,
             speaker_confidence: None,
+            language: None,
+            channel: None,
         }
     }
 
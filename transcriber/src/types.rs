@@ -18,12 +18,33 @@ pub struct Segment {
     pub speaker_turn: bool,
     pub no_speech_probability: f32,
     pub words: Option<Vec<Word>>,
-    /// Speaker name (set by speaker identification, if enabled).
+    /// Speaker name (set by speaker identification, if enabled, or from the
+    /// channel's name in per-channel transcription).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub speaker_id: Option<String>,
     /// Cosine similarity confidence for the speaker match (0.0 - 1.0).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub speaker_confidence: Option<f32>,
+    /// Language this segment was transcribed in (set by per-window language
+    /// detection).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Input channel this segment was heard on (0-based; set by per-channel
+    /// transcription).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u16>,
+}
+
+impl Segment {
+    /// Move the segment and its words `offset` seconds later.
+    pub(crate) fn shift(&mut self, offset: f64) {
+        self.start += offset;
+        self.end += offset;
+        for word in self.words.iter_mut().flatten() {
+            word.start += offset;
+            word.end += offset;
+        }
+    }
 }
 
 /// Complete transcription result.
@@ -31,6 +52,8 @@ pub struct Segment {
 pub struct Transcript {
     pub segments: Vec<Segment>,
     pub language: String,
+    /// Seconds of audio transcribed. With a time range that is the length of
+    /// the range, while segment times count from the start of the input.
     pub duration: f64,
     pub model: String,
     pub source_url: Option<String>,
@@ -39,6 +62,46 @@ pub struct Transcript {
     /// Contains post-processing stats and unknown speaker cluster suggestions.
     #[serde(skip)]
     pub speaker_summary: Option<Box<SpeakerIdSummary>>,
+    /// Share of speech per language, largest first (populated by per-window
+    /// language detection).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<LanguageShare>,
+}
+
+/// How much of a transcript is in one language.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageShare {
+    pub language: String,
+    /// Seconds of transcribed segments in this language.
+    pub duration: f64,
+    /// Fraction of all transcribed segment time (0.0 - 1.0).
+    pub share: f64,
+}
+
+impl LanguageShare {
+    /// Sum segment durations per language, largest share first. Segments
+    /// without a language are left out.
+    pub fn from_segments(segments: &[Segment]) -> Vec<LanguageShare> {
+        let mut shares: Vec<LanguageShare> = Vec::new();
+        for seg in segments {
+            let Some(language) = &seg.language else { continue };
+            let duration = (seg.end - seg.start).max(0.0);
+            match shares.iter_mut().find(|s| &s.language == language) {
+                Some(share) => share.duration += duration,
+                None => shares.push(LanguageShare {
+                    language: language.clone(),
+                    duration,
+                    share: 0.0,
+                }),
+            }
+        }
+        let total: f64 = shares.iter().map(|s| s.duration).sum();
+        for share in &mut shares {
+            share.share = if total > 0.0 { share.duration / total } else { 0.0 };
+        }
+        shares.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+        shares
+    }
 }
 
 /// Type-erased speaker identification summary.
@@ -52,6 +115,8 @@ pub struct SpeakerIdSummary {
     pub skipped: u32,
     pub merged: u32,
     pub smoothed: u32,
+    /// Distinct speakers found (excluding "Unknown").
+    pub speakers: u32,
     /// Each entry: (segment_count, total_duration, representative_start, representative_end)
     pub unknown_clusters: Vec<(usize, f64, f64, f64)>,
 }
@@ -157,6 +222,8 @@ mod tests {
                     ]),
                     speaker_id: None,
                     speaker_confidence: None,
+                    language: None,
+                    channel: None,
                 },
                 Segment {
                     start: 3.0,
@@ -167,6 +234,8 @@ mod tests {
                     words: None,
                     speaker_id: None,
                     speaker_confidence: None,
+                    language: None,
+                    channel: None,
                 },
             ],
             language: "en".into(),
@@ -175,9 +244,19 @@ mod tests {
             source_url: Some("https://example.com/video".into()),
             source_title: Some("Test Video".into()),
             speaker_summary: None,
+            languages: Vec::new(),
         }
     }
 
+    #[test]
+    fn test_segment_shift_moves_words() {
+        let mut seg = sample_transcript().segments.remove(0);
+        seg.shift(10.0);
+        assert_eq!((seg.start, seg.end), (10.0, 12.5));
+        let words = seg.words.unwrap();
+        assert_eq!((words[1].start, words[1].end), (11.0, 12.5));
+    }
+
     #[test]
     fn test_text_output() {
         let t = sample_transcript();
@@ -194,6 +273,7 @@ mod tests {
             source_url: None,
             source_title: None,
             speaker_summary: None,
+            languages: Vec::new(),
         };
         assert_eq!(t.text(), "");
     }
@@ -210,6 +290,8 @@ mod tests {
                 words: None,
                 speaker_id: None,
                 speaker_confidence: None,
+                language: None,
+                channel: None,
             }],
             language: "en".into(),
             duration: 1.0,
@@ -217,6 +299,7 @@ mod tests {
             source_url: None,
             source_title: None,
             speaker_summary: None,
+            languages: Vec::new(),
         };
         assert_eq!(t.text(), "Just one segment.");
     }
@@ -244,6 +327,7 @@ mod tests {
             source_url: None,
             source_title: None,
             speaker_summary: None,
+            languages: Vec::new(),
         };
         assert_eq!(t.to_srt(), "");
     }
@@ -270,6 +354,7 @@ mod tests {
             source_url: None,
             source_title: None,
             speaker_summary: None,
+            languages: Vec::new(),
         };
         assert_eq!(t.to_vtt(), "WEBVTT\n\n");
     }
@@ -343,6 +428,8 @@ mod tests {
                     words: None,
                     speaker_id: None,
                     speaker_confidence: None,
+                    language: None,
+                    channel: None,
                 })
                 .collect(),
             language: "en".into(),
@@ -351,10 +438,42 @@ mod tests {
             source_url: None,
             source_title: None,
             speaker_summary: None,
+            languages: Vec::new(),
         };
         let srt = t.to_srt();
         for i in 1..=5 {
             assert!(srt.contains(&format!("{i}\n")));
         }
     }
+
+    #[test]
+    fn test_language_shares() {
+        let mut segments = sample_transcript().segments; // 2.5s and 2.5s
+        segments[0].language = Some("de".into());
+        segments[1].language = Some("en".into());
+        segments[1].end = 10.5; // 7.5s
+        segments.push(Segment { language: None, ..segments[0].clone() });
+
+        let shares = LanguageShare::from_segments(&segments);
+        assert_eq!(shares.len(), 2);
+        assert_eq!(shares[0].language, "en");
+        assert_eq!(shares[0].duration, 7.5);
+        assert_eq!(shares[0].share, 0.75);
+        assert_eq!(shares[1].language, "de");
+        assert_eq!(shares[1].share, 0.25);
+        assert!(LanguageShare::from_segments(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_json_omits_languages_unless_detected() {
+        let mut t = sample_transcript();
+        assert!(!t.to_json().unwrap().contains("\"languages\""));
+        t.segments[0].language = Some("de".into());
+        t.languages = LanguageShare::from_segments(&t.segments);
+        let json = t.to_json().unwrap();
+        assert!(json.contains("\"languages\":[{\"language\":\"de\""));
+        let back: Transcript = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.languages, t.languages);
+        assert_eq!(back.segments[0].language.as_deref(), Some("de"));
+    }
 }
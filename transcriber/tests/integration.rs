@@ -1,15 +1,13 @@
 use std::path::PathBuf;
 
-use transcriber::{AudioProcessing, Model, TranscribeOptions};
+use transcriber::{AudioBackend, AudioProcessing, ChannelMode, Model, TranscribeOptions};
 
 fn fixtures_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
 }
 
-/// Test that the audio pipeline decodes, processes, and produces valid samples
-/// across all supported fixture formats.
-#[tokio::test]
-async fn audio_pipeline_decodes_all_formats() {
+/// Decode every fixture with `backend` and check length and sample range.
+async fn assert_decodes_all_formats(backend: AudioBackend) {
     let files = [
         ("sine_440hz_2s.wav", 30_000, 34_000),
         ("sine_440hz_1s.mp3", 14_000, 18_000),
@@ -24,15 +22,15 @@ async fn audio_pipeline_decodes_all_formats() {
 
         // Use spawn_blocking since load_audio is sync
         let samples = tokio::task::spawn_blocking(move || {
-            transcriber::__test_load_audio(&path, &AudioProcessing::default())
+            transcriber::__test_load_audio(&path, &AudioProcessing::new().backend(backend))
         })
         .await
         .unwrap()
-        .unwrap_or_else(|e| panic!("{file}: {e}"));
+        .unwrap_or_else(|e| panic!("{file} ({backend}): {e}"));
 
         assert!(
             samples.len() > min_samples && samples.len() < max_samples,
-            "{file}: expected {min_samples}-{max_samples} samples, got {}",
+            "{file} ({backend}): expected {min_samples}-{max_samples} samples, got {}",
             samples.len()
         );
 
@@ -40,12 +38,44 @@ async fn audio_pipeline_decodes_all_formats() {
         for &s in &samples {
             assert!(
                 (-1.0..=1.0).contains(&s),
-                "{file}: sample {s} out of range"
+                "{file} ({backend}): sample {s} out of range"
             );
         }
     }
 }
 
+/// Test that the audio pipeline decodes, processes, and produces valid samples
+/// across all supported fixture formats.
+#[tokio::test]
+async fn audio_pipeline_decodes_all_formats() {
+    assert_decodes_all_formats(AudioBackend::Ffmpeg).await;
+}
+
+/// Same fixtures through the in-process decoder.
+#[cfg(feature = "native-audio")]
+#[tokio::test]
+async fn audio_pipeline_decodes_all_formats_native() {
+    assert_decodes_all_formats(AudioBackend::Native).await;
+}
+
+/// Only the requested range of the file is returned.
+#[cfg(feature = "native-audio")]
+#[tokio::test]
+async fn load_audio_range_cuts_the_range() {
+    let path = fixtures_dir().join("sine_440hz_2s.wav");
+    let range = TranscribeOptions::new().time_range(0.5, Some(1.5)).unwrap().time_range;
+    let processing = AudioProcessing::new().backend(AudioBackend::Native);
+
+    let samples = tokio::task::spawn_blocking(move || {
+        transcriber::load_audio_range(&path, &processing, range)
+    })
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert!((15_000..17_000).contains(&samples.len()), "got {} samples", samples.len());
+}
+
 /// Test that audio processing options (DC offset, normalization, trimming)
 /// all work together without errors.
 #[tokio::test]
@@ -83,6 +113,8 @@ fn output_formats_roundtrip() {
                 words: None,
                 speaker_id: None,
                 speaker_confidence: None,
+                language: None,
+                channel: None,
             },
             transcriber::Segment {
                 start: 3.0,
@@ -93,6 +125,8 @@ fn output_formats_roundtrip() {
                 words: None,
                 speaker_id: None,
                 speaker_confidence: None,
+                language: None,
+                channel: None,
             },
         ],
         language: "en".into(),
@@ -101,6 +135,7 @@ fn output_formats_roundtrip() {
         source_url: None,
         source_title: None,
         speaker_summary: None,
+        languages: Vec::new(),
     };
 
     // SRT
@@ -145,3 +180,337 @@ async fn full_transcription_pipeline() {
     // The tiny model may or may not produce segments for a sine wave,
     // but it should not error out.
 }
+
+/// A time range transcribes only that part, with timestamps in input time.
+/// Run with: cargo test --test integration -- --ignored time_range_keeps_input_timestamps
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn time_range_keeps_input_timestamps() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .time_range(4.0, Some(8.0))
+        .unwrap();
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    // 10s of a quiet tone
+    let samples: Vec<f32> = (0..160_000)
+        .map(|i| 0.1 * (i as f32 * 440.0 * std::f32::consts::TAU / 16_000.0).sin())
+        .collect();
+    let transcript = engine
+        .transcribe_samples(&samples)
+        .await
+        .expect("transcription of a range should succeed");
+    assert!((transcript.duration - 4.0).abs() < 1e-6);
+    assert!(transcript.segments.iter().all(|s| s.start >= 4.0));
+
+    let err = engine.transcribe_samples(&samples[..32_000]).await.unwrap_err();
+    assert!(matches!(err, transcriber::Error::InvalidOption(_)));
+}
+
+/// With a prompt, audio longer than 30 seconds is transcribed window by
+/// window, with timestamps still relative to the whole input.
+/// Run with: cargo test --test integration -- --ignored prompt_spans_all_windows
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn prompt_spans_all_windows() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .initial_prompt("Quarterly review of the ACME platform.")
+        .unwrap();
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    // 70s of a quiet tone: three windows
+    let samples: Vec<f32> = (0..1_120_000)
+        .map(|i| 0.1 * (i as f32 * 440.0 * std::f32::consts::TAU / 16_000.0).sin())
+        .collect();
+    let transcript = engine
+        .transcribe_samples(&samples)
+        .await
+        .expect("prompted transcription should succeed");
+    assert!((transcript.duration - 70.0).abs() < 1e-6);
+    assert!(transcript.segments.iter().all(|s| s.end <= 70.0 + 1e-6));
+    assert!(transcript.segments.windows(2).all(|w| w[0].start <= w[1].start));
+}
+
+/// Per-channel mode transcribes each channel of interleaved PCM and labels
+/// the segments with their channel and speaker name.
+/// Run with: cargo test --test integration -- --ignored per_channel_labels_segments
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn per_channel_labels_segments() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .audio_processing(AudioProcessing::new().channels(ChannelMode::PerChannel))
+        .channel_speakers(["Agent", "Customer"]);
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    // 4s of 16kHz stereo: a tone on the left, silence on the right
+    let pcm: Vec<f32> = (0..64_000)
+        .flat_map(|i| [0.1 * (i as f32 * 440.0 * std::f32::consts::TAU / 16_000.0).sin(), 0.0])
+        .collect();
+    let transcript = engine
+        .transcribe_pcm(&pcm, 16_000, 2)
+        .await
+        .expect("per-channel transcription should succeed");
+    assert!((transcript.duration - 4.0).abs() < 1e-3);
+    assert!(transcript.segments.windows(2).all(|w| w[0].start <= w[1].start));
+    for seg in &transcript.segments {
+        let expected = match seg.channel {
+            Some(0) => "Agent",
+            Some(1) => "Customer",
+            other => panic!("unexpected channel {other:?}"),
+        };
+        assert_eq!(seg.speaker_id.as_deref(), Some(expected));
+    }
+}
+
+/// Building a `Transcriber` fails fast when a custom model file is missing.
+#[tokio::test]
+async fn transcriber_missing_custom_model() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Custom(PathBuf::from("/nonexistent/ggml-model.bin")));
+
+    let result = transcriber::Transcriber::new(opts).await;
+    assert!(matches!(
+        result,
+        Err(transcriber::Error::ModelNotFound { .. })
+    ));
+}
+
+/// The loaded model's header is exposed and English-only checks pass for tiny.
+/// Run with: cargo test --test integration -- --ignored transcriber_reports_model_info
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn transcriber_reports_model_info() {
+    let opts = TranscribeOptions::new().model(Model::Tiny).gpu(false);
+    let transcriber = transcriber::Transcriber::new(opts).await.unwrap();
+
+    let info = transcriber.model_info();
+    assert!(info.is_multilingual());
+    assert_eq!(info.n_audio_layer, 4);
+    assert_eq!(info.size_name(), "tiny");
+}
+
+/// Language detection ranks every language and its probabilities add up to one.
+/// Run with: cargo test --test integration -- --ignored detect_language_ranks_probabilities
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn detect_language_ranks_probabilities() {
+    let path = fixtures_dir().join("sine_440hz_2s.wav");
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .language_detection(transcriber::LanguageDetection::new().windows(2).unwrap());
+
+    let ranked = transcriber::detect_language(&path, &opts).await.unwrap();
+    assert!(ranked.len() >= 50);
+    assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+    let total: f32 = ranked.iter().map(|(_, p)| p).sum();
+    assert!((total - 1.0).abs() < 0.01, "probabilities sum to {total}");
+}
+
+/// Per-window language detection tags every segment with its language.
+/// Run with: cargo test --test integration -- --ignored per_window_language_tags_segments
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn per_window_language_tags_segments() {
+    let path = fixtures_dir().join("sine_440hz_2s.wav");
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .language_per_window(true);
+
+    let transcript = transcriber::transcribe_file_with_options(&path, &opts)
+        .await
+        .expect("per-window transcription should succeed");
+    assert!(transcript.duration > 1.0);
+    assert!(transcript.segments.iter().all(|s| s.language.is_some()));
+    if !transcript.segments.is_empty() {
+        assert_eq!(transcript.languages[0].language, transcript.language);
+    }
+}
+
+/// Chunked transcription splits long audio across workers and keeps the
+/// segments in order.
+/// Run with: cargo test --test integration -- --ignored chunked_transcription_stitches_segments
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn chunked_transcription_stitches_segments() {
+    let chunking = transcriber::ChunkOptions::new()
+        .chunk_secs(30.0)
+        .and_then(|c| c.search_secs(5.0))
+        .and_then(|c| c.workers(2))
+        .unwrap();
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .chunking(chunking);
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    // 100s of a quiet tone: long enough for three chunks
+    let samples: Vec<f32> = (0..1_600_000)
+        .map(|i| 0.1 * (i as f32 * 440.0 * std::f32::consts::TAU / 16_000.0).sin())
+        .collect();
+    let transcript = engine
+        .transcribe_samples(&samples)
+        .await
+        .expect("chunked transcription should succeed");
+    assert!((transcript.duration - 100.0).abs() < 1e-6);
+    assert!(transcript
+        .segments
+        .windows(2)
+        .all(|w| w[0].start <= w[1].start));
+}
+
+/// A single `Transcriber` can run several transcriptions on one loaded model.
+/// Run with: cargo test --test integration -- --ignored transcriber_reuses_loaded_model
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn transcriber_reuses_loaded_model() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false);
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    let first = engine
+        .transcribe_file(fixtures_dir().join("sine_440hz_2s.wav"))
+        .await
+        .expect("first transcription should succeed");
+    let second = engine
+        .transcribe_samples(&vec![0.0f32; 16_000])
+        .await
+        .expect("second transcription should succeed");
+
+    assert_eq!(first.model, "tiny");
+    assert!((second.duration - 1.0).abs() < 1e-6);
+
+    // Per-call options share the model but cannot swap it
+    let english = engine.with_options(
+        TranscribeOptions::new().model(Model::Base).language("en").unwrap(),
+    );
+    assert_eq!(english.options().model, Model::Tiny);
+    let third = english
+        .transcribe_samples(&vec![0.0f32; 16_000])
+        .await
+        .expect("transcription with per-call options should succeed");
+    assert_eq!(third.model, "tiny");
+}
+
+/// In-memory inputs go through the same pipeline as files.
+/// Run with: cargo test --test integration -- --ignored transcriber_accepts_in_memory_inputs
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn transcriber_accepts_in_memory_inputs() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false);
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    let bytes = std::fs::read(fixtures_dir().join("sine_440hz_2s.wav")).unwrap();
+    let from_bytes = engine
+        .transcribe_bytes(bytes.clone())
+        .await
+        .expect("bytes transcription should succeed");
+    let from_reader = engine
+        .transcribe_reader(std::io::Cursor::new(bytes))
+        .await
+        .expect("reader transcription should succeed");
+    assert!((from_bytes.duration - from_reader.duration).abs() < 1e-6);
+
+    // 1s of 48kHz stereo silence
+    let from_pcm = engine
+        .transcribe_pcm(&vec![0.0f32; 96_000], 48_000, 2)
+        .await
+        .expect("PCM transcription should succeed");
+    assert!((from_pcm.duration - 1.0).abs() < 1e-3);
+
+    let err = engine.transcribe_pcm(&[0.0; 3], 48_000, 2).await.unwrap_err();
+    assert!(matches!(err, transcriber::Error::InvalidOption(_)));
+}
+
+/// Streaming emits finals in order and ends after the input is finished.
+/// Run with: cargo test --test integration -- --ignored streaming_finalizes_all_audio
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn streaming_finalizes_all_audio() {
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false);
+    let engine = transcriber::Transcriber::new(opts)
+        .await
+        .expect("tiny model should load");
+
+    let stream_opts = transcriber::StreamOptions::new()
+        .window_secs(4.0)
+        .unwrap()
+        .step_secs(0.5)
+        .unwrap();
+    let (input, mut events) = engine.stream(stream_opts).expect("stream should start");
+
+    // 6s of 16kHz silence in 100ms chunks
+    for _ in 0..60 {
+        input.push(&[0.0f32; 1_600]).unwrap();
+    }
+    input.finish();
+
+    let mut last_end = 0.0;
+    while let Some(event) = events.next_event().await {
+        if let transcriber::StreamEvent::Final(seg) = event.expect("stream should not fail") {
+            assert!(seg.start >= last_end - 1e-6, "finals must be in order");
+            assert!(seg.end <= 6.0 + 1e-6);
+            last_end = seg.end;
+        }
+    }
+}
+
+/// Progress covers every stage, and a cancelled token aborts with `Error::Cancelled`.
+/// Run with: cargo test --test integration -- --ignored progress_and_cancellation
+#[tokio::test]
+#[ignore = "requires whisper tiny model (run with --download-model tiny first)"]
+async fn progress_and_cancellation() {
+    use std::sync::{Arc, Mutex};
+    use transcriber::{CancellationToken, Stage};
+
+    let stages = Arc::new(Mutex::new(Vec::new()));
+    let opts = TranscribeOptions::new()
+        .model(Model::Tiny)
+        .gpu(false)
+        .on_progress({
+            let stages = Arc::clone(&stages);
+            move |p| stages.lock().unwrap().push(p.stage)
+        });
+    transcriber::Transcriber::new(opts.clone())
+        .await
+        .expect("tiny model should load")
+        .transcribe_samples(&vec![0.0f32; 32_000])
+        .await
+        .expect("transcription should succeed");
+    let seen = stages.lock().unwrap().clone();
+    assert!(seen.contains(&Stage::Transcribing));
+    assert_eq!(seen.last(), Some(&Stage::Done));
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let err = transcriber::Transcriber::new(opts.cancellation_token(token))
+        .await
+        .expect("tiny model should load")
+        .transcribe_samples(&vec![0.0f32; 32_000])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, transcriber::Error::Cancelled));
+}